@@ -1,11 +1,13 @@
 import React, { useState, type LazyExoticComponent } from 'react'
 import { IconContext } from 'react-icons'
 import { ErrorBoundary } from './components/organisms/ErrorBoundary'
+import { getDeviceToken } from './global/auth'
 import { setInputFrozen } from './input'
 import { Layout } from './Layout'
 import { type RouteRenderers, Router } from './router/Router'
 import { routes, type Routes } from './routes'
 import { useValueWatcher } from './utils/hooks'
+import { LoginView } from './views/LoginView'
 
 export function App() {
   const [isLoading, setIsLoading] = useState(false)
@@ -21,21 +23,26 @@ export function App() {
 
       <div className="min-h-screen min-w-screen font-mono p-2 leading-6 text-[#d7dadc] text-[15px] not-lg:text-[12px] selection:bg-indigo-500">
         <ErrorBoundary>
-          <Layout isLoading={isLoading} currentRouteName={currentRouteName}>
-            <Router
-              routes={routes}
-              renderers={routeRenderers}
-              // oxlint-disable-next-line react/no-unstable-nested-components
-              fallback={() => <h1>404</h1>}
-              onLoading={() => {
-                setIsLoading(true)
-              }}
-              onPageReady={(routeName) => {
-                setIsLoading(false)
-                setCurrentRouteName(routeName)
-              }}
-            />
-          </Layout>
+          {/* The application is reloaded when logging in or out */}
+          {getDeviceToken() === null ? (
+            <LoginView />
+          ) : (
+            <Layout isLoading={isLoading} currentRouteName={currentRouteName}>
+              <Router
+                routes={routes}
+                renderers={routeRenderers}
+                // oxlint-disable-next-line react/no-unstable-nested-components
+                fallback={() => <h1>404</h1>}
+                onLoading={() => {
+                  setIsLoading(true)
+                }}
+                onPageReady={(routeName) => {
+                  setIsLoading(false)
+                  setCurrentRouteName(routeName)
+                }}
+              />
+            </Layout>
+          )}
         </ErrorBoundary>
       </div>
     </IconContext>
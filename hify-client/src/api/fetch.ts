@@ -1,4 +1,5 @@
 import { ArkErrors, type } from 'arktype'
+import { getDeviceToken, setDeviceToken } from '#/global/auth.ts'
 import { showFailure } from '#/global/notifications.ts'
 import { tryFallible, tryFallibleAsync, type JsonStringifyable } from '#/utils/common.ts'
 import type { CachableQuery } from './hooks'
@@ -24,10 +25,17 @@ async function callRemoteApi<Validator extends type.Any>(
     }
   }
 
-  const req: RequestInit = { method }
+  const headers: Record<string, string> = {}
+  const req: RequestInit = { method, headers }
+
+  const deviceToken = getDeviceToken()
+
+  if (deviceToken !== null) {
+    headers['Authorization'] = `Bearer ${deviceToken}`
+  }
 
   if (bodyParams) {
-    req.headers = { 'Content-Type': 'application/json' }
+    headers['Content-Type'] = 'application/json'
     req.body = JSON.stringify(bodyParams)
   }
 
@@ -43,6 +51,11 @@ async function callRemoteApi<Validator extends type.Any>(
 
   const text = await res.text()
 
+  // The device token was revoked, so the user needs to log in again
+  if (res.status === 401 && deviceToken !== null) {
+    setDeviceToken(null)
+  }
+
   if (!res.ok) {
     const jsonError = tryFallible(() => JSON.parse(text) as unknown)
     const parsedError = apiResponse.onDeepUndeclaredKey('reject')(jsonError)
@@ -118,6 +131,15 @@ export function queryApiKeyed<Validator extends type.Any>(
   }
 }
 
+export function callApiMutationWithResult<Validator extends type.Any>(
+  method: 'POST' | 'PUT' | 'DELETE',
+  uri: string,
+  bodyParams: Record<string, JsonStringifyable> | null,
+  validator: Validator,
+): Promise<Validator['inferOut']> {
+  return callRemoteApi(method, uri, null, bodyParams, validator)
+}
+
 export async function callApiMutation(
   method: 'POST' | 'PUT' | 'DELETE',
   uri: string,
@@ -1,6 +1,27 @@
-import { callApiMutation } from './fetch'
+import { type } from 'arktype'
+import { callApiMutation, callApiMutationWithResult } from './fetch'
 import type { Rating } from './types'
 
+/**
+ * Log in with a user's credentials
+ *
+ * @returns A device token, to authenticate the next requests with
+ */
+export async function logIn(username: string, password: string): Promise<string> {
+  const { token } = await callApiMutationWithResult(
+    'POST',
+    '/auth/login',
+    { username, password, deviceName: navigator.userAgent },
+    type({ token: 'string', user: 'unknown' }),
+  )
+
+  return token
+}
+
+export function logOut(): Promise<void> {
+  return callApiMutation('POST', '/auth/logout')
+}
+
 export function updateIndex(): Promise<void> {
   return callApiMutation('POST', '/index/update')
 }
@@ -1,13 +1,27 @@
+import { getDeviceToken } from '#/global/auth.ts'
 import { API_DOMAIN } from './fetch'
 import type { Album, Artist, ArtSize, Genre } from './types'
 
 export const urls = {
-  albumArt: (album: Album, size: ArtSize) => `${API_DOMAIN}/album/${album.id}/art?size=${size}`,
+  albumArt: (album: Album, size: ArtSize) =>
+    withDeviceToken(`${API_DOMAIN}/album/${album.id}/art?size=${size}`),
 
   artistArt: (artist: Artist, size: ArtSize) =>
-    `${API_DOMAIN}/artist/${artist.id}/art?size=${size}`,
+    withDeviceToken(`${API_DOMAIN}/artist/${artist.id}/art?size=${size}`),
 
-  genreArt: (genre: Genre, size: ArtSize) => `${API_DOMAIN}/genre/${genre.id}/art?size=${size}`,
+  genreArt: (genre: Genre, size: ArtSize) =>
+    withDeviceToken(`${API_DOMAIN}/genre/${genre.id}/art?size=${size}`),
 
-  trackAudioUrl: (trackId: string) => `${API_DOMAIN}/track/${trackId}/audio`,
+  trackAudioUrl: (trackId: string) => withDeviceToken(`${API_DOMAIN}/track/${trackId}/audio`),
+}
+
+// Media elements (images, audio) can't set the 'Authorization' header, so the token is provided in the URL
+function withDeviceToken(url: string): string {
+  const deviceToken = getDeviceToken()
+
+  if (deviceToken === null) {
+    return url
+  }
+
+  return `${url}${url.includes('?') ? '&' : '?'}token=${encodeURIComponent(deviceToken)}`
 }
@@ -0,0 +1,25 @@
+const LOCAL_STORAGE_KEY = 'hify-device-token'
+
+/**
+ * Get the device token the API is authenticated with
+ *
+ * @returns The token, or `null` if the user needs to log in
+ */
+export function getDeviceToken(): string | null {
+  return localStorage.getItem(LOCAL_STORAGE_KEY)
+}
+
+/**
+ * Store or forget the device token, then reload the application
+ *
+ * Reloading ensures no data fetched on behalf of another user (or without being logged in) is kept around.
+ */
+export function setDeviceToken(token: string | null): void {
+  if (token === null) {
+    localStorage.removeItem(LOCAL_STORAGE_KEY)
+  } else {
+    localStorage.setItem(LOCAL_STORAGE_KEY, token)
+  }
+
+  location.reload()
+}
@@ -3,11 +3,14 @@ import { createRoot } from 'react-dom/client'
 // oxlint-disable-next-line no-unassigned-import
 import './main.css'
 import { App } from './App.tsx'
+import { getDeviceToken } from './global/auth.ts'
 import { loadPersistedPlayerState } from './global/player.ts'
 import { setupInputHandler } from './input.ts'
 
-// oxlint-disable-next-line typescript/no-floating-promises
-loadPersistedPlayerState()
+if (getDeviceToken() !== null) {
+  // oxlint-disable-next-line typescript/no-floating-promises
+  loadPersistedPlayerState()
+}
 
 // oxlint-disable-next-line no-non-null-assertion
 createRoot(document.querySelector('#root')!).render(
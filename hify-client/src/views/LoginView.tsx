@@ -0,0 +1,81 @@
+import { useRef, useState, type KeyboardEvent } from 'react'
+import { logIn } from '#/api/mutations.ts'
+import { Button } from '#/components/atoms/Button.tsx'
+import { BlockNavItem } from '#/components/navigables/Item.tsx'
+import { NavList } from '#/components/navigables/List.tsx'
+import { setDeviceToken } from '#/global/auth.ts'
+
+export function LoginView() {
+  const usernameDom = useRef<HTMLInputElement>(null)
+  const passwordDom = useRef<HTMLInputElement>(null)
+
+  const [isLoggingIn, setIsLoggingIn] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  const submit = () => {
+    const username = usernameDom.current?.value ?? ''
+    const password = passwordDom.current?.value ?? ''
+
+    setIsLoggingIn(true)
+    setError(null)
+
+    // oxlint-disable-next-line typescript/no-floating-promises
+    logIn(username, password)
+      .then(setDeviceToken)
+      .catch((e: unknown) => {
+        setError(e instanceof Error ? e.message : String(e))
+        setIsLoggingIn(false)
+      })
+  }
+
+  // Keys typed in inputs are not handled by the navigation, so validation is handled here
+  const submitOnEnter = (e: KeyboardEvent<HTMLInputElement>) => {
+    if (e.key === 'Enter') {
+      submit()
+    }
+  }
+
+  return (
+    <NavList>
+      <div className="flex flex-col items-center mt-10 gap-2">
+        <h2>Log in</h2>
+
+        <BlockNavItem
+          className="w-1/4"
+          onFocused={() => usernameDom.current?.focus()}
+          onUnfocused={() => usernameDom.current?.blur()}
+        >
+          <input
+            ref={usernameDom}
+            type="text"
+            placeholder="Username"
+            autoComplete="username"
+            className="p-1 border border-gray-600 w-full outline-none"
+            onKeyDown={submitOnEnter}
+          />
+        </BlockNavItem>
+
+        <BlockNavItem
+          className="w-1/4"
+          onFocused={() => passwordDom.current?.focus()}
+          onUnfocused={() => passwordDom.current?.blur()}
+        >
+          <input
+            ref={passwordDom}
+            type="password"
+            placeholder="Password"
+            autoComplete="current-password"
+            className="p-1 border border-gray-600 w-full outline-none"
+            onKeyDown={submitOnEnter}
+          />
+        </BlockNavItem>
+
+        <Button onPress={submit} disabled={isLoggingIn}>
+          Log in
+        </Button>
+
+        {error !== null && <p className="text-red-400 whitespace-pre-wrap">{error}</p>}
+      </div>
+    </NavList>
+  )
+}
@@ -1,8 +1,9 @@
 import { useApiMutation } from '#/api/hooks.ts'
-import { updateIndex } from '#/api/mutations.ts'
+import { logOut, updateIndex } from '#/api/mutations.ts'
 import { Button } from '#/components/atoms/Button.tsx'
 import { MutationStatus } from '#/components/atoms/MutationIndicator.tsx'
 import { NavRow } from '#/components/navigables/Row.tsx'
+import { setDeviceToken } from '#/global/auth.ts'
 
 export function ToolsView() {
   return (
@@ -11,6 +12,7 @@ export function ToolsView() {
 
       <NavRow>
         <IndexUpdateButton />
+        <LogOutButton />
       </NavRow>
     </div>
   )
@@ -25,3 +27,16 @@ function IndexUpdateButton() {
     </Button>
   )
 }
+
+function LogOutButton() {
+  const mutation = useApiMutation(async () => {
+    await logOut()
+    setDeviceToken(null)
+  })
+
+  return (
+    <Button onPress={() => mutation.run()} disabled={mutation.status === 'pending'}>
+      Log out <MutationStatus status={mutation.status} />
+    </Button>
+  )
+}
@@ -1,17 +1,14 @@
 use std::{
-    collections::HashMap,
-    ffi::OsStr,
-    path::{Path, PathBuf},
+    path::Path,
     sync::{
         Arc,
         atomic::{AtomicUsize, Ordering},
     },
 };
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result};
 use colored::Colorize;
 use log::{debug, info};
-use walkdir::WalkDir;
 
 use crate::{
     index::{AlbumID, IndexCache},
@@ -19,46 +16,120 @@ use crate::{
     utils::TaskRunner,
 };
 
-use super::ArtsManager;
-
-static COVER_FILE_STEMS: &[&str] = &["cover", "folder"];
-static COVER_EXTENSIONS: &[&str] = &["jpg", "jpeg", "jfif", "png", "webp"];
+use super::{
+    ArtsManager,
+    custom::CustomImage,
+    embedded::{find_album_embedded_art, open_embedded_art},
+    overrides::ArtOverride,
+    placeholder::generate_placeholder_art,
+    selection::AlbumCovers,
+};
 
-// TODO: find all image files, then match against album directories
+/// Generate arts for all albums, or only for the provided one
+#[allow(clippy::too_many_lines)]
 pub fn generate_album_arts(
     index_cache: &IndexCache,
     music_dir: &Path,
+    album_covers: &AlbumCovers,
     album_arts: &ArtsManager<AlbumID>,
+    only: Option<AlbumID>,
 ) -> Result<()> {
-    debug!(
-        "-> Looking for album arts for {} albums...",
-        index_cache.albums.len().to_string().bright_yellow()
-    );
-
-    let album_covers = find_album_arts(music_dir, index_cache)?;
-    assert_eq!(album_covers.len(), index_cache.albums.len());
-
-    debug!(
-        "-> Found {} potential album arts in total",
-        album_covers.len().to_string().bright_yellow()
-    );
-
     info!("-> Generating miniatures for album arts...");
 
     let mut album_arts_tasks = TaskRunner::new();
     let total = Arc::new(AtomicUsize::new(0));
+    let placeholders = Arc::new(AtomicUsize::new(0));
+
+    for (album_id, cover) in album_covers {
+        let album_id = *album_id;
+
+        if only.is_some_and(|only| only != album_id) {
+            continue;
+        }
+        let album_arts = album_arts.clone();
 
-    for (album_id, art_path) in album_covers {
-        let Some(art_path) = art_path else {
-            if album_arts.has(album_id) {
-                album_arts.delete(album_id)?;
+        // Overrides take precedence over covers found in the music directory
+        if let Some(ArtOverride::Image { path, hash }) = album_arts.overrides().get(album_id) {
+            if album_arts.has_with_source_data(album_id, hash) {
+                continue;
             }
 
+            let total = Arc::clone(&total);
+
+            album_arts_tasks.spawn(move || {
+                assert!(album_arts.register(album_id, hash, &CustomImage::open(&path)?)?);
+
+                total.fetch_add(1, Ordering::SeqCst);
+
+                Ok(())
+            });
+
             continue;
-        };
+        }
 
-        let album_arts = album_arts.clone();
+        // Albums without a cover file fall back to the art embedded in their tracks
+        if cover.is_none()
+            && let Some((embedded_art, track_id)) = find_album_embedded_art(index_cache, album_id)
+        {
+            let hash = stable_hash!("embedded", embedded_art);
+
+            if album_arts.has_with_source_data(album_id, hash) {
+                continue;
+            }
+
+            let track = index_cache.tracks.get(&track_id).unwrap().clone();
+            let music_dir = music_dir.to_owned();
+            let total = Arc::clone(&total);
+
+            album_arts_tasks.spawn(move || {
+                let img = open_embedded_art(&music_dir, &track)?;
+
+                assert!(album_arts.register(album_id, hash, &img)?);
+
+                total.fetch_add(1, Ordering::SeqCst);
+
+                Ok(())
+            });
+
+            continue;
+        }
+
+        let Some(cover) = cover else {
+            let album = index_cache.albums.get(&album_id).unwrap();
+
+            let album_name = album.name.clone();
+            let album_artists = album
+                .artists_id
+                .iter()
+                .map(|artist_id| index_cache.artists.get(artist_id).unwrap().name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            // Placeholders only depend on the album's name and artists, so they will automatically
+            // be replaced by the actual cover once one is found (as its hash will be different)
+            let hash = stable_hash!("placeholder", album_name, album_artists);
+
+            if album_arts.has_with_source_data(album_id, hash) {
+                continue;
+            }
+
+            let placeholders = Arc::clone(&placeholders);
+
+            album_arts_tasks.spawn(move || {
+                let img =
+                    generate_placeholder_art(stable_hash!(album_id), &album_name, &album_artists);
+
+                assert!(album_arts.register(album_id, hash, &img)?);
 
+                placeholders.fetch_add(1, Ordering::SeqCst);
+
+                Ok(())
+            });
+
+            continue;
+        };
+
+        let art_path = cover.path.clone();
         let music_dir = music_dir.to_owned();
         let total = Arc::clone(&total);
 
@@ -118,64 +189,14 @@ pub fn generate_album_arts(
         );
     }
 
-    Ok(())
-}
-
-fn find_album_arts(dir: &Path, index: &IndexCache) -> Result<HashMap<AlbumID, Option<PathBuf>>> {
-    let mut img_files = WalkDir::new(dir)
-        .min_depth(1)
-        .into_iter()
-        .filter(|entry| match entry {
-            Ok(entry) => is_cover_file_path(entry.path()),
-            Err(_) => true,
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-
-    img_files.sort_by(|a, b| a.path().cmp(b.path()));
-
-    let mut arts = HashMap::new();
-
-    for album_id in index.albums.keys() {
-        let album_root_path = index
-            .albums_tracks_relative_common_path
-            .get(album_id)
-            .unwrap();
-
-        let art_path = img_files.iter().find(|entry| {
-            entry
-                .path()
-                .strip_prefix(dir)
-                .unwrap()
-                .strip_prefix(album_root_path)
-                .is_ok()
-        });
-
-        arts.insert(*album_id, art_path.map(|p| p.path().to_owned()));
+    let placeholders = placeholders.load(Ordering::SeqCst);
 
-        if art_path.is_none() {
-            bail!(
-                "No art found for album at path: {}",
-                album_root_path.display()
-            );
-        }
+    if placeholders > 0 {
+        info!(
+            "--> Generated placeholder arts for {} albums without a cover",
+            placeholders.to_string().bright_yellow()
+        );
     }
 
-    Ok(arts)
-}
-
-fn is_cover_file_path(path: &Path) -> bool {
-    let Some(file_stem) = path.file_stem().and_then(OsStr::to_str) else {
-        return false;
-    };
-
-    let Some(ext) = path.extension().and_then(OsStr::to_str) else {
-        return false;
-    };
-
-    COVER_FILE_STEMS
-        .iter()
-        .any(|valid_file_stem| file_stem.eq_ignore_ascii_case(valid_file_stem))
-        && COVER_EXTENSIONS
-            .iter()
-            .any(|valid_ext| valid_ext.eq_ignore_ascii_case(ext))
+    Ok(())
 }
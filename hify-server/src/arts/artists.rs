@@ -9,26 +9,68 @@ use image::DynamicImage;
 use log::{debug, info};
 
 use crate::{
-    arts::{LARGE_ART_SIDE_PX, manager::ArtSize, tools::assemble_four_images},
+    arts::{
+        custom::{ArtSource, CustomImage},
+        manager::ArtSize,
+        overrides::override_source,
+        selection::cmp_mosaic_preference,
+        sources::ArtSources,
+        tools::assemble_four_images,
+    },
     index::{AlbumID, ArtistID, IndexCache},
     utils::{TaskRunner, unordered_iter_stable_hash},
 };
 
 use super::ArtsManager;
 
+/// Generate arts for all artists, or only for the provided one
+///
+/// Only arts whose source changed are generated again, so this should be called
+/// whenever album arts change, to update the mosaics made of them.
 // NOTE: should only be called *AFTER* album arts have been generated
 #[allow(clippy::too_many_lines)]
 pub fn generate_artists_art(
     index: &IndexCache,
+    sources: &ArtSources,
     album_arts: &ArtsManager<AlbumID>,
     artist_arts: &ArtsManager<ArtistID>,
+    only: Option<ArtistID>,
 ) -> Result<()> {
     debug!("-> Checking artists that require new art generation...");
 
-    let mut artist_album_arts = vec![];
+    let ArtSources {
+        album_covers,
+        artist_images,
+        genre_images: _,
+    } = sources;
+
+    let mut artists_to_generate = vec![];
 
     for artist_id in index.artists.keys() {
-        let artist_in_albums = index
+        if only.is_some_and(|only| only != *artist_id) {
+            continue;
+        }
+
+        // Overrides take precedence over custom images, which take precedence over mosaics
+        let source = artist_arts
+            .overrides()
+            .get(*artist_id)
+            .and_then(|art_override| override_source(art_override, album_arts))
+            .or_else(|| {
+                artist_images
+                    .get(artist_id)
+                    .map(|image| (ArtSource::Image(image.path.clone()), image.hash))
+            });
+
+        if let Some((source, hash)) = source {
+            if !artist_arts.has_with_source_data(*artist_id, hash) {
+                artists_to_generate.push((*artist_id, source, hash));
+            }
+
+            continue;
+        }
+
+        let mut artist_in_albums = index
             .artists_albums
             .get(artist_id)
             .unwrap()
@@ -40,10 +82,19 @@ pub fn generate_artists_art(
                     .unwrap()
                     .iter(),
             )
-            .take(4)
             .copied()
             .collect::<Vec<_>>();
 
+        // Stable sort, so the albums' original order is kept between equivalent covers
+        artist_in_albums.sort_by(|a, b| {
+            cmp_mosaic_preference(
+                album_covers.get(a).and_then(Option::as_ref),
+                album_covers.get(b).and_then(Option::as_ref),
+            )
+        });
+
+        artist_in_albums.truncate(4);
+
         assert!(!artist_in_albums.is_empty());
 
         let img_hash = unordered_iter_stable_hash(
@@ -56,71 +107,77 @@ pub fn generate_artists_art(
             continue;
         }
 
-        artist_album_arts.push((*artist_id, artist_in_albums, img_hash));
+        artists_to_generate.push((*artist_id, ArtSource::Mosaic(artist_in_albums), img_hash));
     }
 
-    if artist_album_arts.is_empty() {
+    if artists_to_generate.is_empty() {
         return Ok(());
     }
 
     info!(
         "-> Generating {} artist arts...",
-        artist_album_arts.len().to_string().bright_yellow()
+        artists_to_generate.len().to_string().bright_yellow()
     );
 
+    let side_px = artist_arts.settings().large_side_px;
+
     let mut tasks = TaskRunner::new();
 
     let total = Arc::new(AtomicUsize::new(0));
 
-    for (artist_id, first_albums, img_hash) in artist_album_arts {
+    for (artist_id, source, img_hash) in artists_to_generate {
         let album_arts = album_arts.clone();
         let artist_arts = artist_arts.clone();
         let total = Arc::clone(&total);
 
         tasks.spawn(move || {
-            // TODO: prefer images with 1:1 aspect ratio (or closest to it)
-            let images = first_albums
-                .into_iter()
-                .map(|album_id| album_arts.get_art_path(album_id, ArtSize::Large).unwrap())
-                .map(|art| {
-                    image::open(&art)
-                        .with_context(|| {
-                            format!("Failed to open album art image at path: {}", art.display())
+            let img = match source {
+                ArtSource::Image(path) => CustomImage::open(&path)?,
+
+                ArtSource::Mosaic(first_albums) => {
+                    let images = first_albums
+                        .into_iter()
+                        .map(|album_id| album_arts.get_art_path(album_id, ArtSize::Large).unwrap())
+                        .map(|art| {
+                            image::open(&art)
+                                .with_context(|| {
+                                    format!(
+                                        "Failed to open album art image at path: {}",
+                                        art.display()
+                                    )
+                                })
+                                .map(DynamicImage::into_rgb8)
                         })
-                        .map(DynamicImage::into_rgb8)
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-
-            assert!((1..=4).contains(&images.len()));
-
-            let img = match images.as_slice() {
-                [single] => single.clone(),
-
-                [top_left, top_right] => assemble_four_images(
-                    top_left,
-                    top_right,
-                    top_right,
-                    top_left,
-                    LARGE_ART_SIDE_PX,
-                )?,
-
-                [top_left, top_right, bottom_left] => assemble_four_images(
-                    top_left,
-                    top_right,
-                    bottom_left,
-                    top_left,
-                    LARGE_ART_SIDE_PX,
-                )?,
-
-                [top_left, top_right, bottom_left, bottom_right] => assemble_four_images(
-                    top_left,
-                    top_right,
-                    bottom_left,
-                    bottom_right,
-                    LARGE_ART_SIDE_PX,
-                )?,
-
-                [] | [_, _, _, _, ..] => unreachable!(),
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    assert!((1..=4).contains(&images.len()));
+
+                    match images.as_slice() {
+                        [single] => single.clone(),
+
+                        [top_left, top_right] => {
+                            assemble_four_images(top_left, top_right, top_right, top_left, side_px)?
+                        }
+
+                        [top_left, top_right, bottom_left] => assemble_four_images(
+                            top_left,
+                            top_right,
+                            bottom_left,
+                            top_left,
+                            side_px,
+                        )?,
+
+                        [top_left, top_right, bottom_left, bottom_right] => assemble_four_images(
+                            top_left,
+                            top_right,
+                            bottom_left,
+                            bottom_right,
+                            side_px,
+                        )?,
+
+                        [] | [_, _, _, _, ..] => unreachable!(),
+                    }
+                }
             };
 
             assert!(artist_arts.register(artist_id, img_hash, &img)?);
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use image::{DynamicImage, RgbImage, imageops::FilterType};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+/// Side (in pixels) images are downscaled to before computing their colors
+static ANALYSIS_SIDE_PX: u32 = 64;
+
+/// Number of horizontal and vertical components of the `BlurHash`
+static BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+/// Number of bits kept for each channel when grouping similar colors together
+static PALETTE_BITS_PER_CHANNEL: u32 = 4;
+
+/// Colors of an art, used by clients to display placeholders and tint their backgrounds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtColors {
+    /// [`BlurHash`](https://blurha.sh) of the art
+    pub blurhash: String,
+    pub palette: ArtPalette,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtPalette {
+    /// Most common color
+    pub dominant: HexColor,
+
+    /// Most common saturated color with a medium lightness
+    pub vibrant: HexColor,
+
+    /// Most common desaturated color with a medium lightness
+    pub muted: HexColor,
+}
+
+/// An RGB color, serialized as `#rrggbb`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexColor(pub [u8; 3]);
+
+impl Serialize for HexColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b] = self.0;
+        serializer.serialize_str(&format!("#{r:02x}{g:02x}{b:02x}"))
+    }
+}
+
+impl<'de> Deserialize<'de> for HexColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.is_ascii())
+            .ok_or_else(|| D::Error::custom(format!("Invalid hex color: {s:?}")))?;
+
+        let mut rgb = [0; 3];
+
+        for (i, channel) in rgb.iter_mut().enumerate() {
+            *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| D::Error::custom(format!("Invalid hex color: {s:?}")))?;
+        }
+
+        Ok(Self(rgb))
+    }
+}
+
+/// Compute the colors of an art
+pub fn compute_art_colors(img: &RgbImage) -> Result<ArtColors> {
+    let small = image::imageops::resize(
+        img,
+        ANALYSIS_SIDE_PX,
+        ANALYSIS_SIDE_PX,
+        FilterType::Triangle,
+    );
+
+    let rgba = DynamicImage::ImageRgb8(small.clone()).into_rgba8();
+
+    let (components_x, components_y) = BLURHASH_COMPONENTS;
+
+    let blurhash = blurhash::encode(
+        components_x,
+        components_y,
+        rgba.width(),
+        rgba.height(),
+        rgba.as_raw(),
+    )
+    .map_err(|err| anyhow!("Failed to compute BlurHash: {err:?}"))?;
+
+    Ok(ArtColors {
+        blurhash,
+        palette: compute_palette(&small),
+    })
+}
+
+fn compute_palette(img: &RgbImage) -> ArtPalette {
+    let shift = 8 - PALETTE_BITS_PER_CHANNEL;
+
+    // Group similar colors together, keeping the average of each group
+    let mut groups = HashMap::<[u8; 3], ([u64; 3], u64)>::new();
+
+    for pixel in img.pixels() {
+        let [r, g, b] = pixel.0;
+
+        let (sum, count) = groups
+            .entry([r >> shift, g >> shift, b >> shift])
+            .or_default();
+
+        sum[0] += u64::from(r);
+        sum[1] += u64::from(g);
+        sum[2] += u64::from(b);
+        *count += 1;
+    }
+
+    let mut swatches = groups
+        .into_values()
+        .map(|(sum, count)| {
+            let avg = sum.map(|channel| u8::try_from(channel / count).unwrap());
+            (HexColor(avg), count)
+        })
+        .collect::<Vec<_>>();
+
+    // Ensure a deterministic order between groups with the same population
+    swatches.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.0.cmp(&b.0)));
+
+    let dominant = swatches
+        .first()
+        .map_or(HexColor([0, 0, 0]), |(color, _)| *color);
+
+    let best_with = |predicate: &dyn Fn(f64, f64) -> bool, weight: &dyn Fn(f64) -> f64| {
+        swatches
+            .iter()
+            .filter_map(|(color, count)| {
+                let (saturation, lightness) = saturation_and_lightness(*color);
+
+                predicate(saturation, lightness).then(|| {
+                    let count = f64::from(u32::try_from(*count).unwrap());
+                    (*color, count.sqrt() * weight(saturation))
+                })
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(dominant, |(color, _)| color)
+    };
+
+    ArtPalette {
+        dominant,
+        vibrant: best_with(
+            &|saturation, lightness| saturation >= 0.35 && (0.25..=0.75).contains(&lightness),
+            &|saturation| saturation,
+        ),
+        muted: best_with(
+            &|saturation, lightness| saturation < 0.35 && (0.2..=0.8).contains(&lightness),
+            &|saturation| 1.0 - saturation,
+        ),
+    }
+}
+
+fn saturation_and_lightness(HexColor(rgb): HexColor) -> (f64, f64) {
+    let [r, g, b] = rgb.map(|channel| f64::from(channel) / 255.0);
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+
+    let lightness = f64::midpoint(max, min);
+
+    let saturation = if max == min {
+        0.0
+    } else {
+        (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+    };
+
+    (saturation, lightness)
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use image::{DynamicImage, RgbImage};
+use log::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::{
+    index::{AlbumID, ArtistID, GenreID, IndexCache},
+    stable_hash,
+};
+
+use super::selection::COVER_EXTENSIONS;
+
+/// File stem of artist images in the music directory
+pub static ARTIST_IMAGE_STEM: &str = "artist";
+
+/// What an artist or genre art is generated from
+pub enum ArtSource {
+    /// A custom image provided by the user
+    Image(PathBuf),
+
+    /// A mosaic of album covers
+    Mosaic(Vec<AlbumID>),
+}
+
+/// An image provided by the user for an artist or a genre
+pub struct CustomImage {
+    pub path: PathBuf,
+
+    /// Hash of the image's path and modification time
+    pub hash: u64,
+}
+
+impl CustomImage {
+    fn new(path: PathBuf) -> Result<Self> {
+        let mtime = fs::metadata(&path)
+            .and_then(|mt| mt.modified())
+            .with_context(|| {
+                format!(
+                    "Failed to get modification time for image file: {}",
+                    path.display()
+                )
+            })?;
+
+        Ok(Self {
+            hash: stable_hash!("custom", path, mtime),
+            path,
+        })
+    }
+
+    pub fn open(path: &Path) -> Result<RgbImage> {
+        image::open(path)
+            .with_context(|| format!("Failed to open custom image at path: {}", path.display()))
+            .map(DynamicImage::into_rgb8)
+    }
+}
+
+fn is_image_with_stem(path: &Path, stem: Option<&str>) -> bool {
+    let has_valid_ext = path.extension().and_then(OsStr::to_str).is_some_and(|ext| {
+        COVER_EXTENSIONS
+            .iter()
+            .any(|valid_ext| valid_ext.eq_ignore_ascii_case(ext))
+    });
+
+    let has_valid_stem = stem.is_none_or(|stem| {
+        path.file_stem()
+            .and_then(OsStr::to_str)
+            .is_some_and(|file_stem| file_stem.eq_ignore_ascii_case(stem))
+    });
+
+    has_valid_ext && has_valid_stem
+}
+
+/// Find artist images in the music directory
+///
+/// An `artist.*` image is attributed to an artist if all the albums located inside its directory
+/// belong to that artist. If multiple images are found for the same artist, the one whose
+/// directory contains the most albums is used.
+pub fn find_artist_images(
+    music_dir: &Path,
+    index: &IndexCache,
+) -> Result<HashMap<ArtistID, CustomImage>> {
+    let mut candidates = HashMap::<ArtistID, Vec<(usize, PathBuf)>>::new();
+
+    for entry in WalkDir::new(music_dir).min_depth(1).sort_by_file_name() {
+        let entry = entry.context("Failed to read music directory entry")?;
+
+        if !entry.file_type().is_file()
+            || !is_image_with_stem(entry.path(), Some(ARTIST_IMAGE_STEM))
+        {
+            continue;
+        }
+
+        let relative_path = entry.path().strip_prefix(music_dir).unwrap();
+        let image_dir = relative_path.parent().unwrap();
+
+        let albums = index
+            .albums_tracks_relative_common_path
+            .iter()
+            .filter(|(_, album_root)| album_root.starts_with(image_dir))
+            .map(|(album_id, _)| index.albums.get(album_id).unwrap())
+            .collect::<Vec<_>>();
+
+        let Some((first, others)) = albums.split_first() else {
+            debug!(
+                "--> Ignoring artist image without any album in its directory: {}",
+                relative_path.display()
+            );
+
+            continue;
+        };
+
+        let common_artists = others.iter().fold(
+            first.artists_id.iter().copied().collect::<HashSet<_>>(),
+            |mut common, album| {
+                common.retain(|artist_id| album.artists_id.contains(artist_id));
+                common
+            },
+        );
+
+        match common_artists.len() {
+            1 => {
+                let artist_id = common_artists.into_iter().next().unwrap();
+
+                candidates
+                    .entry(artist_id)
+                    .or_default()
+                    .push((albums.len(), entry.path().to_owned()));
+            }
+
+            _ => {
+                warn!(
+                    "Ignoring artist image in directory that doesn't contain albums of a single artist: {}",
+                    relative_path.display()
+                );
+            }
+        }
+    }
+
+    let mut images = HashMap::with_capacity(candidates.len());
+
+    for (artist_id, mut paths) in candidates {
+        // Stable sort, so the first path (by name) wins in case of equality
+        paths.sort_by_key(|(albums_count, _)| std::cmp::Reverse(*albums_count));
+
+        let (_, path) = paths.into_iter().next().unwrap();
+
+        images.insert(artist_id, CustomImage::new(path)?);
+    }
+
+    debug!(
+        "-> Found {} custom artist images",
+        images.len().to_string().bright_yellow()
+    );
+
+    Ok(images)
+}
+
+/// Find genre images in the provided directory
+///
+/// Images are matched with genres using their file stem, case-insensitively.
+pub fn find_genre_images(
+    genres_art_dir: &Path,
+    index: &IndexCache,
+) -> Result<HashMap<GenreID, CustomImage>> {
+    let genres_by_name = index
+        .genres
+        .values()
+        .map(|genre| (genre.name.to_lowercase(), genre.id))
+        .collect::<HashMap<_, _>>();
+
+    let mut entries = fs::read_dir(genres_art_dir)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .with_context(|| {
+            format!(
+                "Failed to read genres art directory: {}",
+                genres_art_dir.display()
+            )
+        })?;
+
+    entries.sort_by_key(fs::DirEntry::path);
+
+    let mut images = HashMap::new();
+
+    for entry in entries {
+        let path = entry.path();
+
+        if !path.is_file() || !is_image_with_stem(&path, None) {
+            continue;
+        }
+
+        let Some(stem) = path.file_stem().and_then(OsStr::to_str) else {
+            continue;
+        };
+
+        let Some(genre_id) = genres_by_name.get(&stem.to_lowercase()) else {
+            debug!("--> Ignoring image for unknown genre: {}", path.display());
+
+            continue;
+        };
+
+        if images.contains_key(genre_id) {
+            warn!(
+                "Ignoring duplicate image for genre '{stem}': {}",
+                path.display()
+            );
+
+            continue;
+        }
+
+        images.insert(*genre_id, CustomImage::new(path)?);
+    }
+
+    debug!(
+        "-> Found {} custom genre images",
+        images.len().to_string().bright_yellow()
+    );
+
+    Ok(images)
+}
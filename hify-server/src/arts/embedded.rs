@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, RgbImage};
+use indexmap::IndexMap;
+
+use crate::{
+    index::{AlbumID, EmbeddedArtHash, IndexCache, Track, TrackID},
+    indexer::extract_embedded_art,
+};
+
+/// Find the art embedded in most of an album's tracks, alongside a track it can be extracted from
+///
+/// In case of equality, the art of the first track wins.
+pub fn find_album_embedded_art(
+    index: &IndexCache,
+    album_id: AlbumID,
+) -> Option<(EmbeddedArtHash, TrackID)> {
+    let mut arts = IndexMap::<EmbeddedArtHash, (usize, TrackID)>::new();
+
+    for track_id in index.albums_tracks.get(&album_id).unwrap() {
+        let track = index.tracks.get(track_id).unwrap();
+
+        if let Some(hash) = track.metadata.embedded_art {
+            arts.entry(hash).or_insert((0, *track_id)).0 += 1;
+        }
+    }
+
+    arts.into_iter()
+        .fold(None, |best, (hash, (count, track_id))| match best {
+            Some((_, best_count, _)) if best_count >= count => best,
+            _ => Some((hash, count, track_id)),
+        })
+        .map(|(hash, _, track_id)| (hash, track_id))
+}
+
+/// Extract and decode the art embedded in a track's file
+pub fn open_embedded_art(music_dir: &Path, track: &Track) -> Result<RgbImage> {
+    let path = music_dir.join(&track.relative_path);
+
+    let data = extract_embedded_art(&path)
+        .with_context(|| format!("Failed to analyze audio file: {}", path.display()))?
+        .with_context(|| format!("No art is embedded in audio file: {}", path.display()))?;
+
+    image::load_from_memory(&data)
+        .with_context(|| format!("Failed to decode art embedded in: {}", path.display()))
+        .map(DynamicImage::into_rgb8)
+}
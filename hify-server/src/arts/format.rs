@@ -0,0 +1,116 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+/// Image format arts can be served in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtFormat {
+    Webp,
+    Jpeg,
+    Png,
+    Avif,
+}
+
+impl ArtFormat {
+    /// Formats by order of preference when a client accepts several of them equally
+    ///
+    /// AVIF comes last as it is by far the slowest to encode.
+    const PREFERENCE_ORDER: [Self; 4] = [Self::Webp, Self::Jpeg, Self::Png, Self::Avif];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Webp => "webp",
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Avif => "avif",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Webp => "image/webp",
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Avif => "image/avif",
+        }
+    }
+
+    /// Pick a format from the value of an `Accept` header
+    ///
+    /// Formats explicitly listed by the client are preferred, by order of quality value.
+    /// If none is, the fallback format is used (e.g. for `*/*`, `image/*` or a missing header).
+    pub fn negotiate(accept: Option<&str>, fallback: Self) -> Self {
+        let Some(accept) = accept else {
+            return fallback;
+        };
+
+        let mut best: Option<(Self, f32)> = None;
+
+        for media_range in accept.split(',') {
+            let mut params = media_range.split(';').map(str::trim);
+
+            let mime_type = params.next().unwrap_or_default();
+
+            let Some(format) = Self::PREFERENCE_ORDER
+                .into_iter()
+                .find(|format| format.mime_type().eq_ignore_ascii_case(mime_type))
+            else {
+                continue;
+            };
+
+            let quality = params
+                .find_map(|param| param.strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse::<f32>().ok())
+                .unwrap_or(0.0);
+
+            if quality <= 0.0 {
+                continue;
+            }
+
+            let is_better = match best {
+                None => true,
+                Some((best_format, best_quality)) => {
+                    quality > best_quality
+                        || (quality == best_quality
+                            && format.preference_rank() < best_format.preference_rank())
+                }
+            };
+
+            if is_better {
+                best = Some((format, quality));
+            }
+        }
+
+        best.map_or(fallback, |(format, _)| format)
+    }
+
+    fn preference_rank(self) -> usize {
+        Self::PREFERENCE_ORDER
+            .iter()
+            .position(|format| *format == self)
+            .unwrap()
+    }
+}
+
+impl FromStr for ArtFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "webp" => Ok(Self::Webp),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "png" => Ok(Self::Png),
+            "avif" => Ok(Self::Avif),
+            _ => Err(format!(
+                "Invalid art format {s:?} (expected 'webp', 'jpeg', 'png' or 'avif')"
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ArtFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
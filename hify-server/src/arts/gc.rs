@@ -0,0 +1,54 @@
+use std::{ops::AddAssign, path::Path};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use walkdir::WalkDir;
+
+/// Result of a garbage collection pass over the generated arts
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtsGarbageReport {
+    /// Number of removed art directories
+    pub removed_arts: usize,
+
+    /// Number of removed arts from the resized arts cache
+    pub removed_resized_arts: usize,
+
+    /// Total size of the removed files, in bytes
+    pub reclaimed_bytes: u64,
+}
+
+impl AddAssign for ArtsGarbageReport {
+    fn add_assign(&mut self, rhs: Self) {
+        let Self {
+            removed_arts,
+            removed_resized_arts,
+            reclaimed_bytes,
+        } = rhs;
+
+        self.removed_arts += removed_arts;
+        self.removed_resized_arts += removed_resized_arts;
+        self.reclaimed_bytes += reclaimed_bytes;
+    }
+}
+
+/// Compute the total size of all files inside a directory
+pub fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+
+    for entry in WalkDir::new(path) {
+        let entry = entry
+            .with_context(|| format!("Failed to read directory entry in: {}", path.display()))?;
+
+        if entry.file_type().is_file() {
+            size += entry
+                .metadata()
+                .with_context(|| {
+                    format!("Failed to read metadata for: {}", entry.path().display())
+                })?
+                .len();
+        }
+    }
+
+    Ok(size)
+}
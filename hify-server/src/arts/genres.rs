@@ -9,34 +9,86 @@ use image::DynamicImage;
 use log::{debug, info};
 
 use crate::{
-    arts::{LARGE_ART_SIDE_PX, manager::ArtSize, tools::assemble_four_images},
+    arts::{
+        custom::{ArtSource, CustomImage},
+        manager::ArtSize,
+        overrides::override_source,
+        selection::cmp_mosaic_preference,
+        sources::ArtSources,
+        tools::assemble_four_images,
+    },
     index::{AlbumID, GenreID, IndexCache},
     utils::{TaskRunner, unordered_iter_stable_hash},
 };
 
 use super::ArtsManager;
 
+/// Generate arts for all genres, or only for the provided one
+///
+/// Only arts whose source changed are generated again, so this should be called
+/// whenever album arts change, to update the mosaics made of them.
 // NOTE: should only be called *AFTER* album arts have been generated
+#[allow(clippy::too_many_lines)]
 pub fn generate_genres_art(
     index: &IndexCache,
+    sources: &ArtSources,
     album_arts: &ArtsManager<AlbumID>,
     genre_arts: &ArtsManager<GenreID>,
+    only: Option<GenreID>,
 ) -> Result<()> {
     debug!("-> Checking genres that require new art generation...");
 
-    let mut genre_album_arts = vec![];
+    let ArtSources {
+        album_covers,
+        artist_images: _,
+        genre_images,
+    } = sources;
+
+    let mut genres_to_generate = vec![];
 
     for genre_id in index.genres.keys() {
-        let first_albums_with_arts = index
+        if only.is_some_and(|only| only != *genre_id) {
+            continue;
+        }
+
+        // Overrides take precedence over custom images, which take precedence over mosaics
+        let source = genre_arts
+            .overrides()
+            .get(*genre_id)
+            .and_then(|art_override| override_source(art_override, album_arts))
+            .or_else(|| {
+                genre_images
+                    .get(genre_id)
+                    .map(|image| (ArtSource::Image(image.path.clone()), image.hash))
+            });
+
+        if let Some((source, hash)) = source {
+            if !genre_arts.has_with_source_data(*genre_id, hash) {
+                genres_to_generate.push((*genre_id, source, hash));
+            }
+
+            continue;
+        }
+
+        let mut first_albums_with_arts = index
             .genres_albums
             .get(genre_id)
             .unwrap()
             .iter()
             .filter(|album_id| album_arts.has(**album_id))
-            .take(4)
             .copied()
             .collect::<Vec<_>>();
 
+        // Stable sort, so the albums' original order is kept between equivalent covers
+        first_albums_with_arts.sort_by(|a, b| {
+            cmp_mosaic_preference(
+                album_covers.get(a).and_then(Option::as_ref),
+                album_covers.get(b).and_then(Option::as_ref),
+            )
+        });
+
+        first_albums_with_arts.truncate(4);
+
         if first_albums_with_arts.is_empty() {
             if genre_arts.has(*genre_id) {
                 genre_arts.delete(*genre_id)?;
@@ -55,71 +107,81 @@ pub fn generate_genres_art(
             continue;
         }
 
-        genre_album_arts.push((*genre_id, first_albums_with_arts, img_hash));
+        genres_to_generate.push((
+            *genre_id,
+            ArtSource::Mosaic(first_albums_with_arts),
+            img_hash,
+        ));
     }
 
-    if genre_album_arts.is_empty() {
+    if genres_to_generate.is_empty() {
         return Ok(());
     }
 
     info!(
         "-> Generating {} genre arts...",
-        genre_album_arts.len().to_string().bright_yellow()
+        genres_to_generate.len().to_string().bright_yellow()
     );
 
+    let side_px = genre_arts.settings().large_side_px / 2;
+
     let mut tasks = TaskRunner::new();
 
     let total = Arc::new(AtomicUsize::new(0));
 
-    for (genre_id, first_albums, img_hash) in genre_album_arts {
+    for (genre_id, source, img_hash) in genres_to_generate {
         let album_arts = album_arts.clone();
         let genre_arts = genre_arts.clone();
         let total = Arc::clone(&total);
 
         tasks.spawn(move || {
-            // TODO: prefer images with 1:1 aspect ratio (or closest to it)
-            let images = first_albums
-                .into_iter()
-                .map(|album_id| album_arts.get_art_path(album_id, ArtSize::Large).unwrap())
-                .map(|art| {
-                    image::open(&art)
-                        .with_context(|| {
-                            format!("Failed to open album art image at path: {}", art.display())
+            let img = match source {
+                ArtSource::Image(path) => CustomImage::open(&path)?,
+
+                ArtSource::Mosaic(first_albums) => {
+                    let images = first_albums
+                        .into_iter()
+                        .map(|album_id| album_arts.get_art_path(album_id, ArtSize::Large).unwrap())
+                        .map(|art| {
+                            image::open(&art)
+                                .with_context(|| {
+                                    format!(
+                                        "Failed to open album art image at path: {}",
+                                        art.display()
+                                    )
+                                })
+                                .map(DynamicImage::into_rgb8)
                         })
-                        .map(DynamicImage::into_rgb8)
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-
-            assert!((1..=4).contains(&images.len()));
-
-            let img = match images.as_slice() {
-                [single] => single.clone(),
-
-                [top_left, top_right] => assemble_four_images(
-                    top_left,
-                    top_right,
-                    top_right,
-                    top_left,
-                    LARGE_ART_SIDE_PX / 2,
-                )?,
-
-                [top_left, top_right, bottom_left] => assemble_four_images(
-                    top_left,
-                    top_right,
-                    bottom_left,
-                    top_left,
-                    LARGE_ART_SIDE_PX / 2,
-                )?,
-
-                [top_left, top_right, bottom_left, bottom_right] => assemble_four_images(
-                    top_left,
-                    top_right,
-                    bottom_left,
-                    bottom_right,
-                    LARGE_ART_SIDE_PX / 2,
-                )?,
-
-                [] | [_, _, _, _, ..] => unreachable!(),
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    assert!((1..=4).contains(&images.len()));
+
+                    match images.as_slice() {
+                        [single] => single.clone(),
+
+                        [top_left, top_right] => {
+                            assemble_four_images(top_left, top_right, top_right, top_left, side_px)?
+                        }
+
+                        [top_left, top_right, bottom_left] => assemble_four_images(
+                            top_left,
+                            top_right,
+                            bottom_left,
+                            top_left,
+                            side_px,
+                        )?,
+
+                        [top_left, top_right, bottom_left, bottom_right] => assemble_four_images(
+                            top_left,
+                            top_right,
+                            bottom_left,
+                            bottom_right,
+                            side_px,
+                        )?,
+
+                        [] | [_, _, _, _, ..] => unreachable!(),
+                    }
+                }
             };
 
             assert!(genre_arts.register(genre_id, img_hash, &img)?);
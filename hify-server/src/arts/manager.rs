@@ -1,8 +1,10 @@
 use std::{
-    collections::HashMap,
-    fs::{self, DirEntry},
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
     marker::PhantomData,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    str::FromStr,
     sync::{Arc, LazyLock, RwLock},
 };
 
@@ -10,10 +12,16 @@ use anyhow::{Context, Result, anyhow, bail};
 use image::RgbImage;
 use log::error;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
 
 use crate::{
-    arts::{LARGE_ART_SIDE_PX, MEDIUM_ART_SIDE_PX, SMALL_ART_SIDE_PX, TINY_ART_SIDE_PX, tools},
+    arts::{
+        ArtFormat, ArtSettings, SOURCE_ART_MAX_SIDE_PX,
+        colors::{ArtColors, compute_art_colors},
+        gc::{ArtsGarbageReport, dir_size},
+        overrides::ArtOverrides,
+        tools,
+    },
     index::IdType,
     utils::{decode_base62_u64, encode_base62_u64},
 };
@@ -23,23 +31,51 @@ static MEDIUM_WEBP_FILENAME: &str = "medium.webp";
 static SMALL_WEBP_FILENAME: &str = "small.webp";
 static TINY_WEBP_FILENAME: &str = "tiny.webp";
 
+/// High-quality version of the original image, used to render arts of arbitrary sizes
+static SOURCE_WEBP_FILENAME: &str = "source.webp";
+
+/// Informations about how the art was generated
+static METADATA_FILENAME: &str = "meta.json";
+
+static PRESET_WEBP_FILENAMES: &[&str] = &[
+    LARGE_WEBP_FILENAME,
+    MEDIUM_WEBP_FILENAME,
+    SMALL_WEBP_FILENAME,
+    TINY_WEBP_FILENAME,
+];
+
 static INCOMPLETE_DIR_NAME: &str = ".incomplete";
 
 #[derive(Clone)]
 pub struct ArtsManager<I: IdType> {
     arts: Arc<RwLock<HashMap<I, ArtDirForItem>>>,
+    overrides: ArtOverrides<I>,
+    name: String,
+    settings: ArtSettings,
     dir: PathBuf,
     incomplete_dir: PathBuf,
     _i: PhantomData<I>,
 }
 
 impl<I: IdType> ArtsManager<I> {
-    pub fn open(dir: PathBuf) -> Result<Self> {
+    #[allow(clippy::too_many_lines)]
+    pub fn open(dir: PathBuf, overrides_dir: PathBuf, settings: ArtSettings) -> Result<Self> {
+        let name = dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context("Art directory must have a valid UTF-8 name")?
+            .to_owned();
+
+        let overrides = ArtOverrides::open(overrides_dir)?;
+
         if !dir.exists() {
             fs::create_dir_all(&dir).context("Failed to create art directory")?;
 
             return Ok(Self {
                 arts: Arc::new(RwLock::new(HashMap::new())),
+                overrides,
+                name,
+                settings,
                 incomplete_dir: dir.join(INCOMPLETE_DIR_NAME),
                 dir,
                 _i: PhantomData,
@@ -96,7 +132,7 @@ impl<I: IdType> ArtsManager<I> {
                     format!("Invalid hash in directory name in arts directory: {for_data:?})")
                 })?;
 
-            let mut dir_entries = fs::read_dir(entry.path())
+            let dir_entries = fs::read_dir(entry.path())
                 .and_then(Iterator::collect::<Result<Vec<_>, _>>)
                 .with_context(|| {
                     format!(
@@ -105,15 +141,33 @@ impl<I: IdType> ArtsManager<I> {
                     )
                 })?;
 
-            dir_entries.sort_by_key(DirEntry::path);
-
-            #[allow(clippy::indexing_slicing)]
-            if dir_entries.len() != 4
-                || dir_entries[0].file_name() != LARGE_WEBP_FILENAME
-                || dir_entries[1].file_name() != MEDIUM_WEBP_FILENAME
-                || dir_entries[2].file_name() != SMALL_WEBP_FILENAME
-                || dir_entries[3].file_name() != TINY_WEBP_FILENAME
-            {
+            let filenames = dir_entries
+                .iter()
+                .map(fs::DirEntry::file_name)
+                .collect::<HashSet<_>>();
+
+            // Arts generated before source images were stored don't have one,
+            // in which case the large preset is used as a fallback for resizing
+            let has_valid_files = PRESET_WEBP_FILENAMES
+                .iter()
+                .all(|filename| filenames.contains(OsStr::new(filename)))
+                && filenames.iter().all(|filename| {
+                    filename == SOURCE_WEBP_FILENAME
+                        || filename == METADATA_FILENAME
+                        || PRESET_WEBP_FILENAMES
+                            .iter()
+                            .any(|preset| filename == preset)
+                });
+
+            // Arts generated before metadata were stored don't have any,
+            // in which case they will be regenerated during the next index update
+            let metadata = if filenames.contains(OsStr::new(METADATA_FILENAME)) {
+                read_art_metadata(&entry.path()).ok()
+            } else {
+                Some(ArtMetadata::default())
+            };
+
+            let Some(metadata) = metadata.filter(|_| has_valid_files) else {
                 error!(
                     "Art directory for item {} has invalid content, deleting...",
                     item_id.encode()
@@ -122,19 +176,23 @@ impl<I: IdType> ArtsManager<I> {
                 fs::remove_dir_all(entry.path())?;
 
                 continue;
-            }
+            };
 
             arts.insert(
                 item_id,
                 ArtDirForItem {
                     for_data,
                     path: entry.path(),
+                    metadata,
                 },
             );
         }
 
         Ok(Self {
             arts: Arc::new(RwLock::new(arts)),
+            overrides,
+            name,
+            settings,
             incomplete_dir,
             dir,
             _i: PhantomData,
@@ -149,8 +207,9 @@ impl<I: IdType> ArtsManager<I> {
     pub fn has_with_source_data(&self, item_id: I, source_data: u64) -> bool {
         let arts = self.arts.read().unwrap();
 
-        arts.get(&item_id)
-            .is_some_and(|art_dir| art_dir.for_data == source_data)
+        arts.get(&item_id).is_some_and(|art_dir| {
+            art_dir.for_data == source_data && self.is_up_to_date(&art_dir.metadata)
+        })
     }
 
     pub fn get_art_source_data(&self, item_id: I) -> Option<u64> {
@@ -161,6 +220,47 @@ impl<I: IdType> ArtsManager<I> {
         Some(art_dir.for_data)
     }
 
+    pub fn settings(&self) -> &ArtSettings {
+        &self.settings
+    }
+
+    fn is_up_to_date(&self, metadata: &ArtMetadata) -> bool {
+        metadata.settings_hash == Some(self.settings.stable_hash()) && metadata.colors.is_some()
+    }
+
+    pub fn get_art_colors(&self, item_id: I) -> Option<ArtColors> {
+        let arts = self.arts.read().unwrap();
+
+        arts.get(&item_id)?.metadata.colors.clone()
+    }
+
+    /// Arts provided by the user, which take precedence over the generated ones
+    pub fn overrides(&self) -> &ArtOverrides<I> {
+        &self.overrides
+    }
+
+    /// Name of the arts' category (e.g. `albums`)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the path to the image the art with the provided ID should be resized from
+    pub fn get_source_art_path(&self, item_id: I) -> Result<PathBuf> {
+        let arts = self.arts.read().unwrap();
+
+        let art_dir = arts
+            .get(&item_id)
+            .with_context(|| format!("No art registered for item {}", item_id.encode()))?;
+
+        let source_path = art_dir.path.join(SOURCE_WEBP_FILENAME);
+
+        Ok(if source_path.exists() {
+            source_path
+        } else {
+            art_dir.path.join(LARGE_WEBP_FILENAME)
+        })
+    }
+
     pub fn get_art_path(&self, item_id: I, size: ArtSize) -> Result<PathBuf> {
         let arts = self.arts.read().unwrap();
 
@@ -168,12 +268,7 @@ impl<I: IdType> ArtsManager<I> {
             .get(&item_id)
             .with_context(|| format!("No art registered for item {}", item_id.encode()))?;
 
-        Ok(art_dir.path.join(match size {
-            ArtSize::Large => LARGE_WEBP_FILENAME,
-            ArtSize::Medium => MEDIUM_WEBP_FILENAME,
-            ArtSize::Small => SMALL_WEBP_FILENAME,
-            ArtSize::Tiny => TINY_WEBP_FILENAME,
-        }))
+        Ok(art_dir.path.join(preset_filename(size)))
     }
 
     pub fn register(&self, item_id: I, for_data: u64, img: &RgbImage) -> Result<bool> {
@@ -187,17 +282,17 @@ impl<I: IdType> ArtsManager<I> {
             let existing = self.arts.read().unwrap();
 
             if let Some(existing) = existing.get(&item_id) {
-                // If data didn't change, just do nothing
-                if existing.for_data == for_data {
+                // If data and settings didn't change, just do nothing
+                if existing.for_data == for_data && self.is_up_to_date(&existing.metadata) {
                     return Ok(false);
                 }
 
-                // If data changed, remove the old art directory
-                if art_dir.exists() {
-                    fs::remove_dir_all(&art_dir).with_context(|| {
+                // Otherwise, remove the old art directory
+                if existing.path.exists() {
+                    fs::remove_dir_all(&existing.path).with_context(|| {
                         format!(
                             "Failed to remove previous art directory for item {item_id:?}: {}",
-                            art_dir.display()
+                            existing.path.display()
                         )
                     })?;
                 }
@@ -209,15 +304,28 @@ impl<I: IdType> ArtsManager<I> {
         if art_dir.exists() {
             // TODO: check if all required files exist
 
-            self.arts.write().unwrap().insert(
-                item_id,
-                ArtDirForItem {
-                    for_data,
-                    path: art_dir,
-                },
-            );
+            match read_art_metadata(&art_dir) {
+                Ok(metadata) if self.is_up_to_date(&metadata) => {
+                    self.arts.write().unwrap().insert(
+                        item_id,
+                        ArtDirForItem {
+                            for_data,
+                            path: art_dir,
+                            metadata,
+                        },
+                    );
+
+                    return Ok(false);
+                }
 
-            return Ok(false);
+                // Arts generated with different settings must be regenerated
+                _ => fs::remove_dir_all(&art_dir).with_context(|| {
+                    format!(
+                        "Failed to remove outdated art directory for item {item_id:?}: {}",
+                        art_dir.display()
+                    )
+                })?,
+            }
         }
 
         let incomplete_dir = self.incomplete_dir.join(format!(
@@ -233,17 +341,31 @@ impl<I: IdType> ArtsManager<I> {
             )
         })?;
 
-        let tiny = tools::resize_image(img, TINY_ART_SIDE_PX, TINY_ART_SIDE_PX);
-        tools::save_image_webp(&incomplete_dir.join(TINY_WEBP_FILENAME), &tiny)?;
-
-        let small = tools::resize_image(img, SMALL_ART_SIDE_PX, SMALL_ART_SIDE_PX);
-        tools::save_image_webp(&incomplete_dir.join(SMALL_WEBP_FILENAME), &small)?;
+        let source = tools::resize_image_to_fit(img, SOURCE_ART_MAX_SIDE_PX);
+        tools::save_image(
+            &incomplete_dir.join(SOURCE_WEBP_FILENAME),
+            &source,
+            ArtFormat::Webp,
+            tools::SOURCE_WEBP_QUALITY,
+        )?;
+
+        for size in ArtSize::ALL {
+            let side_px = self.settings.side_px(size);
+
+            tools::save_image(
+                &incomplete_dir.join(preset_filename(size)),
+                &tools::resize_image(img, side_px, side_px),
+                ArtFormat::Webp,
+                self.settings.quality,
+            )?;
+        }
 
-        let medium = tools::resize_image(img, MEDIUM_ART_SIDE_PX, MEDIUM_ART_SIDE_PX);
-        tools::save_image_webp(&incomplete_dir.join(MEDIUM_WEBP_FILENAME), &medium)?;
+        let metadata = ArtMetadata {
+            settings_hash: Some(self.settings.stable_hash()),
+            colors: Some(compute_art_colors(img)?),
+        };
 
-        let large = tools::resize_image(img, LARGE_ART_SIDE_PX, LARGE_ART_SIDE_PX);
-        tools::save_image_webp(&incomplete_dir.join(LARGE_WEBP_FILENAME), &large)?;
+        write_art_metadata(&incomplete_dir, &metadata)?;
 
         fs::rename(&incomplete_dir, &art_dir).with_context(|| {
             format!(
@@ -260,12 +382,57 @@ impl<I: IdType> ArtsManager<I> {
             ArtDirForItem {
                 for_data,
                 path: art_dir,
+                metadata,
             },
         );
 
         Ok(true)
     }
 
+    /// List all registered arts, alongside the hash of the data they were generated from
+    pub fn registered(&self) -> Vec<(I, u64)> {
+        let arts = self.arts.read().unwrap();
+
+        arts.iter()
+            .map(|(item_id, art_dir)| (*item_id, art_dir.for_data))
+            .collect()
+    }
+
+    /// Remove the arts of all items that don't exist anymore
+    pub fn collect_garbage(&self, exists: impl Fn(I) -> bool) -> Result<ArtsGarbageReport> {
+        let orphans = {
+            let mut arts = self.arts.write().unwrap();
+
+            let orphan_ids = arts
+                .keys()
+                .filter(|item_id| !exists(**item_id))
+                .copied()
+                .collect::<Vec<_>>();
+
+            orphan_ids
+                .into_iter()
+                .map(|item_id| (item_id, arts.remove(&item_id).unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut report = ArtsGarbageReport::default();
+
+        for (item_id, art_dir) in orphans {
+            report.reclaimed_bytes += dir_size(&art_dir.path)?;
+
+            fs::remove_dir_all(&art_dir.path).with_context(|| {
+                format!(
+                    "Failed to remove orphaned art directory for item {item_id:?}: {}",
+                    art_dir.path.display()
+                )
+            })?;
+
+            report.removed_arts += 1;
+        }
+
+        Ok(report)
+    }
+
     pub fn delete(&self, item_id: I) -> Result<()> {
         let mut arts = self.arts.write().unwrap();
 
@@ -288,10 +455,49 @@ static FILENAME_PARSER: LazyLock<Regex> =
 struct ArtDirForItem {
     for_data: u64,
     path: PathBuf,
+    metadata: ArtMetadata,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ArtMetadata {
+    /// Hash of the settings the art was generated with
+    settings_hash: Option<u64>,
+
+    /// Colors of the art
+    colors: Option<ArtColors>,
+}
+
+fn read_art_metadata(art_dir: &Path) -> Result<ArtMetadata> {
+    let path = art_dir.join(METADATA_FILENAME);
+
+    let metadata_str = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read art metadata file: {}", path.display()))?;
+
+    serde_json::from_str(&metadata_str)
+        .with_context(|| format!("Failed to parse art metadata file: {}", path.display()))
+}
+
+fn write_art_metadata(art_dir: &Path, metadata: &ArtMetadata) -> Result<()> {
+    let path = art_dir.join(METADATA_FILENAME);
+
+    let metadata_str =
+        serde_json::to_string(metadata).context("Failed to serialize art metadata")?;
+
+    fs::write(&path, metadata_str)
+        .with_context(|| format!("Failed to write art metadata file: {}", path.display()))
+}
+
+fn preset_filename(size: ArtSize) -> &'static str {
+    match size {
+        ArtSize::Large => LARGE_WEBP_FILENAME,
+        ArtSize::Medium => MEDIUM_WEBP_FILENAME,
+        ArtSize::Small => SMALL_WEBP_FILENAME,
+        ArtSize::Tiny => TINY_WEBP_FILENAME,
+    }
 }
 
 // TODO: check if ALL these sizes are actually used
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ArtSize {
     Large,
@@ -299,3 +505,56 @@ pub enum ArtSize {
     Small,
     Tiny,
 }
+
+impl ArtSize {
+    pub const ALL: [Self; 4] = [Self::Large, Self::Medium, Self::Small, Self::Tiny];
+}
+
+/// Size of an art requested by a client
+///
+/// Either one of the pre-generated sizes, or an arbitrary side in pixels
+#[derive(Debug, Clone, Copy)]
+pub enum RequestedArtSize {
+    Preset(ArtSize),
+    Px(u32),
+}
+
+impl RequestedArtSize {
+    /// Use the matching preset if the requested side is one of them
+    pub fn normalize(self, settings: &ArtSettings) -> Self {
+        match self {
+            Self::Preset(_) => self,
+            Self::Px(px) => ArtSize::ALL
+                .into_iter()
+                .find(|preset| settings.side_px(*preset) == px)
+                .map_or(self, Self::Preset),
+        }
+    }
+}
+
+impl FromStr for RequestedArtSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "large" => Ok(Self::Preset(ArtSize::Large)),
+            "medium" => Ok(Self::Preset(ArtSize::Medium)),
+            "small" => Ok(Self::Preset(ArtSize::Small)),
+            "tiny" => Ok(Self::Preset(ArtSize::Tiny)),
+            _ => match s.parse::<u32>() {
+                Ok(0) => Err("Art size cannot be zero".to_owned()),
+                Ok(px) => Ok(Self::Px(px)),
+                Err(_) => Err(format!(
+                    "Invalid art size {s:?} (expected 'large', 'medium', 'small', 'tiny' or a number of pixels)"
+                )),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RequestedArtSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
@@ -1,17 +1,35 @@
 mod albums;
 mod artists;
+mod colors;
+mod custom;
+mod embedded;
+mod format;
+mod gc;
 mod genres;
 mod manager;
+mod overrides;
+mod placeholder;
+mod resized;
+mod selection;
+mod settings;
+mod sources;
 mod tools;
+mod tracks;
 
 pub use self::{
     albums::generate_album_arts,
     artists::generate_artists_art,
+    colors::ArtColors,
+    format::ArtFormat,
+    gc::ArtsGarbageReport,
     genres::generate_genres_art,
-    manager::{ArtSize, ArtsManager},
+    manager::{ArtSize, ArtsManager, RequestedArtSize},
+    resized::ResizedArtsCache,
+    selection::CoverSelectionRules,
+    settings::ArtSettings,
+    sources::ArtSources,
+    tracks::generate_track_arts,
 };
 
-pub static LARGE_ART_SIDE_PX: u32 = 2000;
-pub static MEDIUM_ART_SIDE_PX: u32 = 500;
-pub static SMALL_ART_SIDE_PX: u32 = 250;
-pub static TINY_ART_SIDE_PX: u32 = 125;
+/// Maximum side of the source images arts of arbitrary sizes are rendered from
+pub static SOURCE_ART_MAX_SIDE_PX: u32 = 4096;
@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use anyhow::{Context, Result, bail};
+use image::ImageFormat;
+use log::warn;
+use regex::Regex;
+
+use crate::{
+    arts::{ArtsManager, custom::ArtSource},
+    index::{AlbumID, IdType},
+    stable_hash,
+    utils::{decode_base62_u64, encode_base62_u64},
+};
+
+/// Extension of the files pinning the albums an art's mosaic is made of
+static MOSAIC_OVERRIDE_EXTENSION: &str = "json";
+
+/// Formats accepted for uploaded images
+static ACCEPTED_IMAGE_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Jpeg,
+    ImageFormat::Png,
+    ImageFormat::WebP,
+    ImageFormat::Avif,
+];
+
+/// Maximum number of albums an art's mosaic can be made of
+static MAX_MOSAIC_ALBUMS: usize = 4;
+
+/// An art provided by the user, which takes precedence over the generated one
+#[derive(Debug, Clone)]
+pub enum ArtOverride {
+    /// An uploaded image
+    Image {
+        path: PathBuf,
+
+        /// Hash of the image's content
+        hash: u64,
+    },
+
+    /// A mosaic made of the provided albums' arts, in order
+    Mosaic(Vec<AlbumID>),
+}
+
+/// Store for art overrides
+///
+/// Overrides are stored outside of the generated arts' directory, so they survive
+/// index updates as well as arts regeneration.
+#[derive(Clone)]
+pub struct ArtOverrides<I: IdType> {
+    overrides: Arc<RwLock<HashMap<I, ArtOverride>>>,
+    dir: PathBuf,
+}
+
+impl<I: IdType> ArtOverrides<I> {
+    pub fn open(dir: PathBuf) -> Result<Self> {
+        if !dir.exists() {
+            fs::create_dir_all(&dir).context("Failed to create art overrides directory")?;
+        }
+
+        let mut overrides = HashMap::new();
+
+        for entry in fs::read_dir(&dir).context("Failed to read art overrides directory")? {
+            let entry = entry.context("Failed to read art overrides directory entry")?;
+
+            // Unknown files (e.g. left by the OS or an editor) are ignored
+            let filename = entry.file_name();
+
+            let Some((item_id, art_override)) = filename
+                .to_str()
+                .and_then(|filename| parse_override_filename::<I>(filename, &entry.path()))
+            else {
+                warn!(
+                    "Ignoring unknown file in art overrides directory: {}",
+                    entry.path().display()
+                );
+
+                continue;
+            };
+
+            let art_override = match art_override {
+                Some(art_override) => art_override,
+                None => ArtOverride::Mosaic(read_mosaic_override(&entry.path())?),
+            };
+
+            if overrides.insert(item_id, art_override).is_some() {
+                bail!(
+                    "Found multiple overrides for item {} in art overrides directory",
+                    item_id.encode()
+                );
+            }
+        }
+
+        Ok(Self {
+            overrides: Arc::new(RwLock::new(overrides)),
+            dir,
+        })
+    }
+
+    pub fn get(&self, item_id: I) -> Option<ArtOverride> {
+        let overrides = self.overrides.read().unwrap();
+        overrides.get(&item_id).cloned()
+    }
+
+    /// Store an uploaded image, which must be in one of the accepted formats
+    pub fn set_image(&self, item_id: I, data: &[u8]) -> Result<()> {
+        let format = image::guess_format(data).context("Failed to detect the image's format")?;
+
+        if !ACCEPTED_IMAGE_FORMATS.contains(&format) {
+            bail!("Unsupported image format: {format:?}");
+        }
+
+        // Ensure the image can actually be decoded before storing it
+        image::load_from_memory_with_format(data, format)
+            .context("Failed to decode the uploaded image")?;
+
+        let hash = stable_hash!("override", data);
+
+        let path = self.dir.join(format!(
+            "{}--@--{}.{}",
+            item_id.encode(),
+            encode_base62_u64(hash),
+            format.extensions_str()[0]
+        ));
+
+        self.replace(
+            item_id,
+            &path,
+            data,
+            ArtOverride::Image {
+                path: path.clone(),
+                hash,
+            },
+        )
+    }
+
+    /// Pin the albums an art's mosaic is made of
+    pub fn set_mosaic(&self, item_id: I, albums: Vec<AlbumID>) -> Result<()> {
+        if albums.is_empty() || albums.len() > MAX_MOSAIC_ALBUMS {
+            bail!("Mosaics must be made of between 1 and {MAX_MOSAIC_ALBUMS} albums");
+        }
+
+        let path = self
+            .dir
+            .join(format!("{}.{MOSAIC_OVERRIDE_EXTENSION}", item_id.encode()));
+
+        let data = serde_json::to_vec(&albums).context("Failed to serialize mosaic override")?;
+
+        self.replace(item_id, &path, &data, ArtOverride::Mosaic(albums))
+    }
+
+    fn replace(
+        &self,
+        item_id: I,
+        path: &Path,
+        data: &[u8],
+        art_override: ArtOverride,
+    ) -> Result<()> {
+        let mut overrides = self.overrides.write().unwrap();
+
+        if let Some(existing) = overrides.remove(&item_id) {
+            remove_override_file(&self.dir, item_id, &existing)?;
+        }
+
+        fs::write(path, data)
+            .with_context(|| format!("Failed to write art override: {}", path.display()))?;
+
+        overrides.insert(item_id, art_override);
+
+        Ok(())
+    }
+
+    /// Remove an override, returning `false` if there was none
+    pub fn remove(&self, item_id: I) -> Result<bool> {
+        let mut overrides = self.overrides.write().unwrap();
+
+        let Some(existing) = overrides.remove(&item_id) else {
+            return Ok(false);
+        };
+
+        remove_override_file(&self.dir, item_id, &existing)?;
+
+        Ok(true)
+    }
+}
+
+/// Get what an art should be generated from when it is overridden, alongside the source data's hash
+///
+/// Pinned albums without an art are ignored, and `None` is returned if none of them has one.
+pub fn override_source(
+    art_override: ArtOverride,
+    album_arts: &ArtsManager<AlbumID>,
+) -> Option<(ArtSource, u64)> {
+    match art_override {
+        ArtOverride::Image { path, hash } => Some((ArtSource::Image(path), hash)),
+
+        ArtOverride::Mosaic(albums) => {
+            let (albums, source_data): (Vec<_>, Vec<_>) = albums
+                .into_iter()
+                .filter_map(|album_id| {
+                    album_arts
+                        .get_art_source_data(album_id)
+                        .map(|source_data| (album_id, source_data))
+                })
+                .unzip();
+
+            if albums.is_empty() {
+                return None;
+            }
+
+            // Unlike regular mosaics, the order of pinned albums matters
+            Some((
+                ArtSource::Mosaic(albums),
+                stable_hash!("pinned", source_data),
+            ))
+        }
+    }
+}
+
+fn remove_override_file<I: IdType>(
+    dir: &Path,
+    item_id: I,
+    art_override: &ArtOverride,
+) -> Result<()> {
+    let path = match art_override {
+        ArtOverride::Image { path, hash: _ } => path.clone(),
+        ArtOverride::Mosaic(_) => {
+            dir.join(format!("{}.{MOSAIC_OVERRIDE_EXTENSION}", item_id.encode()))
+        }
+    };
+
+    fs::remove_file(&path)
+        .with_context(|| format!("Failed to remove art override: {}", path.display()))
+}
+
+/// Parse an override's filename into its item's ID and, unless it is a mosaic, its content
+fn parse_override_filename<I: IdType>(
+    filename: &str,
+    path: &Path,
+) -> Option<(I, Option<ArtOverride>)> {
+    let parsed = FILENAME_PARSER.captures(filename)?;
+
+    let item_id = I::decode(parsed.get(1).unwrap().as_str()).ok()?;
+
+    match (parsed.get(2), parsed.get(3).unwrap().as_str()) {
+        (None, ext) if ext == MOSAIC_OVERRIDE_EXTENSION => Some((item_id, None)),
+
+        (Some(hash), _) => Some((
+            item_id,
+            Some(ArtOverride::Image {
+                path: path.to_owned(),
+                hash: decode_base62_u64(hash.as_str()).ok()?,
+            }),
+        )),
+
+        (None, _) => None,
+    }
+}
+
+fn read_mosaic_override(path: &Path) -> Result<Vec<AlbumID>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read mosaic override: {}", path.display()))?;
+
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse mosaic override: {}", path.display()))
+}
+
+static FILENAME_PARSER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new("^([a-zA-Z0-9]+)(?:--@--([a-zA-Z0-9]+))?\\.([a-z0-9]+)$").unwrap());
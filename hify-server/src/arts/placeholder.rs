@@ -0,0 +1,321 @@
+use image::{Rgb, RgbImage};
+
+use crate::utils::{Rng, deterministic_shuffle};
+
+/// Side of placeholder arts, in pixels (font scales below are relative to it)
+static PLACEHOLDER_SIDE_PX: u32 = 2000;
+
+/// Width of a glyph in the built-in bitmap font, in font pixels
+static GLYPH_WIDTH: u32 = 5;
+
+/// Height of a glyph in the built-in bitmap font, in font pixels
+static GLYPH_HEIGHT: u32 = 7;
+
+/// Scale of the title's font pixels, in image pixels
+static TITLE_SCALE: u32 = 16;
+
+/// Scale of the subtitle's font pixels, in image pixels
+static SUBTITLE_SCALE: u32 = 10;
+
+/// Maximum number of lines for the title
+static TITLE_MAX_LINES: usize = 4;
+
+/// Maximum number of lines for the subtitle
+static SUBTITLE_MAX_LINES: usize = 2;
+
+/// Generate a deterministic placeholder art
+///
+/// The background is a diagonal gradient which colors are derived from the provided seed,
+/// with the title and subtitle rendered on top of it.
+pub fn generate_placeholder_art(seed: u64, title: &str, subtitle: &str) -> RgbImage {
+    let side_px = PLACEHOLDER_SIDE_PX;
+
+    let mut rng = Rng::with_seed(seed, Rng::DEFAULT_INCREMENT);
+
+    let mut hues = [0, 45, 90, 135, 180, 225, 270, 315];
+    deterministic_shuffle(&mut hues, &mut rng);
+
+    let start_hue = f64::from(hues[0] + rng.next_u32() % 45);
+    let end_hue = start_hue + f64::from(30 + rng.next_u32() % 60);
+
+    let start = hsl_to_rgb(start_hue, 0.55, 0.45);
+    let end = hsl_to_rgb(end_hue, 0.55, 0.25);
+
+    let max_dist = f64::from(2 * (side_px - 1));
+
+    let mut img = RgbImage::from_fn(side_px, side_px, |x, y| {
+        mix(start, end, f64::from(x + y) / max_dist)
+    });
+
+    let max_width = side_px * 8 / 10;
+
+    let title_lines = wrap_text(
+        title,
+        max_width / (TITLE_SCALE * (GLYPH_WIDTH + 1)),
+        TITLE_MAX_LINES,
+    );
+
+    let subtitle_lines = wrap_text(
+        subtitle,
+        max_width / (SUBTITLE_SCALE * (GLYPH_WIDTH + 1)),
+        SUBTITLE_MAX_LINES,
+    );
+
+    let title_line_height = TITLE_SCALE * (GLYPH_HEIGHT + 2);
+    let subtitle_line_height = SUBTITLE_SCALE * (GLYPH_HEIGHT + 2);
+
+    let gap = if subtitle_lines.is_empty() {
+        0
+    } else {
+        title_line_height
+    };
+
+    let block_height = u32::try_from(title_lines.len()).unwrap() * title_line_height
+        + gap
+        + u32::try_from(subtitle_lines.len()).unwrap() * subtitle_line_height;
+
+    let mut y = side_px.saturating_sub(block_height) / 2;
+
+    for line in &title_lines {
+        draw_centered_line(&mut img, line, y, TITLE_SCALE);
+        y += title_line_height;
+    }
+
+    y += gap;
+
+    for line in &subtitle_lines {
+        draw_centered_line(&mut img, line, y, SUBTITLE_SCALE);
+        y += subtitle_line_height;
+    }
+
+    img
+}
+
+/// Split a text into lines of at most `max_chars` characters, cutting at word boundaries when possible
+///
+/// Characters unsupported by the built-in font are removed.
+/// If the text doesn't fit in `max_lines`, the last line is terminated with an ellipsis.
+fn wrap_text(text: &str, max_chars: u32, max_lines: usize) -> Vec<String> {
+    let max_chars = usize::try_from(max_chars).unwrap().max(4);
+
+    let mut lines = vec![];
+    let mut curr = String::new();
+
+    let words = text
+        .split_whitespace()
+        .map(|word| word.chars().filter_map(fold_char).collect::<String>())
+        .filter(|word| !word.is_empty());
+
+    for word in words {
+        let mut word = word.as_str();
+
+        loop {
+            let curr_len = curr.chars().count();
+            let sep_len = usize::from(curr_len > 0);
+
+            if curr_len + sep_len + word.chars().count() <= max_chars {
+                if sep_len > 0 {
+                    curr.push(' ');
+                }
+
+                curr.push_str(word);
+                break;
+            }
+
+            if curr_len > 0 {
+                lines.push(std::mem::take(&mut curr));
+                continue;
+            }
+
+            // Word is longer than a whole line, so it needs to be split
+            let split_at = word.char_indices().nth(max_chars).unwrap().0;
+            lines.push(word[..split_at].to_owned());
+            word = &word[split_at..];
+        }
+    }
+
+    if !curr.is_empty() {
+        lines.push(curr);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+
+        let last = lines.last_mut().unwrap();
+        let kept = last.chars().take(max_chars - 3).collect::<String>();
+        *last = format!("{}...", kept.trim_end());
+    }
+
+    lines
+}
+
+fn draw_centered_line(img: &mut RgbImage, line: &str, y: u32, scale: u32) {
+    let chars_count = u32::try_from(line.chars().count()).unwrap();
+    let line_width = (chars_count * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale;
+
+    let mut x = img.width().saturating_sub(line_width) / 2;
+
+    for c in line.chars() {
+        if let Some(glyph) = glyph_for(c) {
+            // Draw a shadow first to ensure the text remains readable on light backgrounds
+            draw_glyph(img, glyph, x + scale / 2, y + scale / 2, scale, |pixel| {
+                Rgb(pixel.0.map(|channel| channel / 2))
+            });
+
+            draw_glyph(img, glyph, x, y, scale, |_| Rgb([255, 255, 255]));
+        }
+
+        x += (GLYPH_WIDTH + 1) * scale;
+    }
+}
+
+fn draw_glyph(
+    img: &mut RgbImage,
+    glyph: [u8; 7],
+    x: u32,
+    y: u32,
+    scale: u32,
+    paint: impl Fn(Rgb<u8>) -> Rgb<u8>,
+) {
+    for (row, bits) in (0..).zip(glyph) {
+        for col in 0..GLYPH_WIDTH {
+            if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                continue;
+            }
+
+            for px_y in 0..scale {
+                for px_x in 0..scale {
+                    let (img_x, img_y) = (x + col * scale + px_x, y + row * scale + px_y);
+
+                    if img_x < img.width() && img_y < img.height() {
+                        let pixel = *img.get_pixel(img_x, img_y);
+                        img.put_pixel(img_x, img_y, paint(pixel));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Convert a character to its equivalent in the built-in font, if any
+fn fold_char(c: char) -> Option<char> {
+    let folded = match c {
+        'À'..='Å' | 'à'..='å' => 'A',
+        'Ç' | 'ç' => 'C',
+        'È'..='Ë' | 'è'..='ë' => 'E',
+        'Ì'..='Ï' | 'ì'..='ï' => 'I',
+        'Ñ' | 'ñ' => 'N',
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' => 'O',
+        'Ù'..='Ü' | 'ù'..='ü' => 'U',
+        'Ý' | 'ý' | 'ÿ' => 'Y',
+        'ß' => 'S',
+        _ => c.to_ascii_uppercase(),
+    };
+
+    glyph_for(folded).map(|_| folded)
+}
+
+/// Get the glyph for a character in the built-in 5x7 font
+///
+/// Each byte represents a row, with the 5 lowest bits being the pixels from left to right.
+fn glyph_for(c: char) -> Option<[u8; 7]> {
+    let glyph = match c {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ';' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '\'' => [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '"' => [0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '?' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '*' => [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '&' => [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => return None,
+    };
+
+    Some(glyph)
+}
+
+/// Linear interpolation between two colors
+#[allow(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn mix(start: [f64; 3], end: [f64; 3], ratio: f64) -> Rgb<u8> {
+    let channel = |i: usize| {
+        let value = start[i] + (end[i] - start[i]) * ratio;
+        (value * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+
+    Rgb([channel(0), channel(1), channel(2)])
+}
+
+/// Convert an HSL color to RGB, with all output channels between 0 and 1
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> [f64; 3] {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - (hue.rem_euclid(2.0) - 1.0).abs());
+    let m = lightness - chroma / 2.0;
+
+    let (red, green, blue) = if hue < 1.0 {
+        (chroma, x, 0.0)
+    } else if hue < 2.0 {
+        (x, chroma, 0.0)
+    } else if hue < 3.0 {
+        (0.0, chroma, x)
+    } else if hue < 4.0 {
+        (0.0, x, chroma)
+    } else if hue < 5.0 {
+        (x, 0.0, chroma)
+    } else {
+        (chroma, 0.0, x)
+    };
+
+    [red + m, green + m, blue + m]
+}
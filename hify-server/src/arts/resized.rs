@@ -0,0 +1,386 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use image::DynamicImage;
+use log::{debug, warn};
+
+use crate::{
+    arts::{ArtFormat, ArtsGarbageReport, ArtsManager, SOURCE_ART_MAX_SIDE_PX, tools},
+    index::IdType,
+    utils::encode_base62_u64,
+};
+
+static TEMPORARY_FILE_EXTENSION: &str = "tmp";
+
+/// Granularity of the arbitrary sides arts are rendered at
+static RENDERED_SIDE_STEP_PX: u32 = 32;
+
+/// Size-bounded cache of arts rendered on demand at arbitrary sizes or in other formats
+///
+/// Entries are evicted by order of least recent use once the cache exceeds its maximum size.
+/// As the cached files' names include the hash of the art's source data, entries for outdated
+/// arts are simply never used again, and end up being evicted.
+#[derive(Clone)]
+pub struct ResizedArtsCache {
+    inner: Arc<ResizedArtsCacheInner>,
+}
+
+struct ResizedArtsCacheInner {
+    dir: PathBuf,
+    max_bytes: u64,
+    entries: Mutex<CacheEntries>,
+    next_temporary_id: AtomicU64,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    by_filename: HashMap<String, CacheEntry>,
+    total_bytes: u64,
+    clock: u64,
+}
+
+struct CacheEntry {
+    size_bytes: u64,
+    last_access: u64,
+}
+
+impl ResizedArtsCache {
+    pub fn open(dir: PathBuf, max_bytes: u64) -> Result<Self> {
+        if !dir.exists() {
+            fs::create_dir_all(&dir).context("Failed to create resized arts directory")?;
+        }
+
+        let mut files = vec![];
+
+        for entry in fs::read_dir(&dir).context("Failed to read resized arts directory")? {
+            let entry = entry.context("Failed to read resized arts directory entry")?;
+
+            let path = entry.path();
+
+            let mt = entry.metadata().with_context(|| {
+                format!(
+                    "Failed to read metadata for resized arts directory entry: {}",
+                    path.display()
+                )
+            })?;
+
+            let filename = entry.file_name().into_string().ok();
+
+            match filename {
+                Some(filename)
+                    if mt.is_file()
+                        && path.extension().is_some_and(|ext| {
+                            ext.to_str()
+                                .is_some_and(|ext| ext.parse::<ArtFormat>().is_ok())
+                        }) =>
+                {
+                    let mtime = mt.modified().with_context(|| {
+                        format!(
+                            "Failed to get modification time for resized art: {}",
+                            path.display()
+                        )
+                    })?;
+
+                    files.push((filename, mt.len(), mtime));
+                }
+
+                _ => {
+                    // Leftovers from interrupted renderings, or unknown files
+                    if mt.is_dir() {
+                        fs::remove_dir_all(&path)
+                    } else {
+                        fs::remove_file(&path)
+                    }
+                    .with_context(|| {
+                        format!(
+                            "Failed to remove invalid entry in resized arts directory: {}",
+                            path.display()
+                        )
+                    })?;
+                }
+            }
+        }
+
+        // Approximate the order of last access with the order of creation
+        files.sort_by_key(|(_, _, mtime)| *mtime);
+
+        let mut entries = CacheEntries::default();
+
+        for (filename, size_bytes, _) in files {
+            entries.insert(filename, size_bytes);
+        }
+
+        let cache = Self {
+            inner: Arc::new(ResizedArtsCacheInner {
+                dir,
+                max_bytes,
+                entries: Mutex::new(entries),
+                next_temporary_id: AtomicU64::new(0),
+            }),
+        };
+
+        cache.evict_if_needed(None);
+
+        Ok(cache)
+    }
+
+    /// Round an arbitrary side up to the next multiple of [`RENDERED_SIDE_STEP_PX`],
+    /// so close sizes share the same rendering and cache entry
+    ///
+    /// Sides of the presets should not be rounded, so they are rendered exactly.
+    pub fn round_side_px(side_px: u32) -> u32 {
+        side_px
+            .clamp(1, SOURCE_ART_MAX_SIDE_PX)
+            .next_multiple_of(RENDERED_SIDE_STEP_PX)
+            .min(SOURCE_ART_MAX_SIDE_PX)
+    }
+
+    /// Get the path to an art rendered with the provided side and format, rendering it if not already cached
+    ///
+    /// This function is blocking and may be slow, so it should not be called from async contexts.
+    pub fn get_or_render<I: IdType>(
+        &self,
+        arts: &ArtsManager<I>,
+        item_id: I,
+        side_px: u32,
+        format: ArtFormat,
+    ) -> Result<PathBuf> {
+        let side_px = side_px.clamp(1, SOURCE_ART_MAX_SIDE_PX);
+
+        let for_data = arts
+            .get_art_source_data(item_id)
+            .with_context(|| format!("No art registered for item {}", item_id.encode()))?;
+
+        let quality = arts.settings().quality;
+
+        let filename = format!(
+            "{}--{side_px}--q{quality}.{}",
+            art_key(arts, item_id, for_data),
+            format.extension()
+        );
+
+        let path = self.inner.dir.join(&filename);
+
+        if self.inner.entries.lock().unwrap().touch(&filename) {
+            return Ok(path);
+        }
+
+        let source_path = arts.get_source_art_path(item_id)?;
+
+        let source = image::open(&source_path)
+            .with_context(|| {
+                format!(
+                    "Failed to open source art image at path: {}",
+                    source_path.display()
+                )
+            })
+            .map(DynamicImage::into_rgb8)?;
+
+        let resized = tools::resize_image(&source, side_px, side_px);
+
+        // Render into a temporary file first, so concurrent requests never see a partial image
+        let temporary_path = self.inner.dir.join(format!(
+            "{filename}.{}.{TEMPORARY_FILE_EXTENSION}",
+            self.inner.next_temporary_id.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        tools::save_image(&temporary_path, &resized, format, quality)?;
+
+        let size_bytes = fs::metadata(&temporary_path)
+            .with_context(|| {
+                format!(
+                    "Failed to read metadata for resized art: {}",
+                    temporary_path.display()
+                )
+            })?
+            .len();
+
+        fs::rename(&temporary_path, &path).with_context(|| {
+            format!(
+                "Failed to rename resized art: {} -> {}",
+                temporary_path.display(),
+                path.display()
+            )
+        })?;
+
+        debug!(
+            "Rendered {} art for item {} at {}px in {format:?}",
+            arts.name(),
+            item_id.encode(),
+            side_px.to_string().bright_yellow()
+        );
+
+        self.inner
+            .entries
+            .lock()
+            .unwrap()
+            .insert(filename.clone(), size_bytes);
+
+        self.evict_if_needed(Some(&filename));
+
+        Ok(path)
+    }
+
+    /// Get the keys of all the arts registered in a manager, for use with [`Self::collect_garbage`]
+    pub fn live_keys<I: IdType>(arts: &ArtsManager<I>) -> impl Iterator<Item = String> {
+        arts.registered()
+            .into_iter()
+            .map(|(item_id, for_data)| art_key(arts, item_id, for_data))
+    }
+
+    /// Remove all cached arts whose key isn't in the provided set
+    pub fn collect_garbage(&self, live_keys: &HashSet<String>) -> ArtsGarbageReport {
+        let mut entries = self.inner.entries.lock().unwrap();
+
+        let orphans = entries
+            .by_filename
+            .keys()
+            .filter(|filename| {
+                // Remove the side, quality and extension parts
+                let key = filename
+                    .splitn(5, "--")
+                    .take(4)
+                    .collect::<Vec<_>>()
+                    .join("--");
+                !live_keys.contains(&key)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut report = ArtsGarbageReport::default();
+
+        for filename in orphans {
+            let size_bytes = entries.by_filename.get(&filename).unwrap().size_bytes;
+
+            entries.remove(&filename);
+
+            let path = self.inner.dir.join(&filename);
+
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    report.removed_resized_arts += 1;
+                    report.reclaimed_bytes += size_bytes;
+                }
+
+                Err(err) => warn!(
+                    "Failed to remove orphaned resized art '{}': {err}",
+                    path.display()
+                ),
+            }
+        }
+
+        report
+    }
+
+    fn evict_if_needed(&self, keep: Option<&str>) {
+        let mut entries = self.inner.entries.lock().unwrap();
+
+        while entries.total_bytes > self.inner.max_bytes {
+            let Some(filename) = entries.least_recently_used(keep) else {
+                break;
+            };
+
+            entries.remove(&filename);
+
+            let path = self.inner.dir.join(&filename);
+
+            if let Err(err) = fs::remove_file(&path) {
+                warn!(
+                    "Failed to remove evicted resized art '{}': {err}",
+                    path.display()
+                );
+            }
+        }
+    }
+}
+
+/// Identify an art (and the data it was generated from) in the cache's filenames
+fn art_key<I: IdType>(arts: &ArtsManager<I>, item_id: I, for_data: u64) -> String {
+    format!(
+        "{}--{}--@--{}",
+        arts.name(),
+        item_id.encode(),
+        encode_base62_u64(for_data)
+    )
+}
+
+impl CacheEntries {
+    fn insert(&mut self, filename: String, size_bytes: u64) {
+        self.clock += 1;
+
+        let previous = self.by_filename.insert(
+            filename,
+            CacheEntry {
+                size_bytes,
+                last_access: self.clock,
+            },
+        );
+
+        if let Some(previous) = previous {
+            self.total_bytes -= previous.size_bytes;
+        }
+
+        self.total_bytes += size_bytes;
+    }
+
+    fn touch(&mut self, filename: &str) -> bool {
+        self.clock += 1;
+
+        match self.by_filename.get_mut(filename) {
+            Some(entry) => {
+                entry.last_access = self.clock;
+                true
+            }
+
+            None => false,
+        }
+    }
+
+    fn remove(&mut self, filename: &str) {
+        if let Some(entry) = self.by_filename.remove(filename) {
+            self.total_bytes -= entry.size_bytes;
+        }
+    }
+
+    fn least_recently_used(&self, except: Option<&str>) -> Option<String> {
+        self.by_filename
+            .iter()
+            .filter(|(filename, _)| Some(filename.as_str()) != except)
+            .min_by_key(|(_, entry)| entry.last_access)
+            .map(|(filename, _)| filename.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_side_px() {
+        for (side_px, expected) in [
+            (1, 32),
+            (32, 32),
+            (33, 64),
+            (300, 320),
+            (520, 544),
+            (4000, 4000),
+            (4001, 4032),
+            (5000, SOURCE_ART_MAX_SIDE_PX),
+        ] {
+            assert_eq!(
+                ResizedArtsCache::round_side_px(side_px),
+                expected,
+                "Unexpected rounding for side {side_px}px"
+            );
+        }
+    }
+}
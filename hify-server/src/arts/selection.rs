@@ -0,0 +1,373 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    ffi::OsStr,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, ensure};
+use colored::Colorize;
+use log::{debug, trace, warn};
+use serde::Deserialize;
+use walkdir::WalkDir;
+
+use crate::index::{AlbumID, IndexCache};
+
+use super::custom::ARTIST_IMAGE_STEM;
+
+pub static COVER_EXTENSIONS: &[&str] = &["jpg", "jpeg", "jfif", "png", "webp"];
+
+/// Selected cover for each album, or [`None`] if no candidate was found
+pub type AlbumCovers = HashMap<AlbumID, Option<CoverCandidate>>;
+
+/// Rules used to pick the best cover among all the images found in an album's directory
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoverSelectionRules {
+    /// File stems (names without extension) of cover images, by order of preference
+    pub stems: Vec<String>,
+
+    /// Ignore images whose file stem is not in the list above
+    pub stems_only: bool,
+
+    /// Maximum depth of candidate images, relative to the album's root directory
+    pub max_depth: usize,
+
+    /// Side (in pixels) above which a higher resolution does not improve an image's score
+    pub target_side_px: u32,
+
+    /// Weight of the file stem's priority in the final score
+    pub stem_weight: f64,
+
+    /// Weight of the image's resolution in the final score
+    pub resolution_weight: f64,
+
+    /// Weight of the image's aspect ratio (closest to 1:1 is best) in the final score
+    pub squareness_weight: f64,
+
+    /// Weight of the image's depth (closest to the album's root is best) in the final score
+    pub depth_weight: f64,
+}
+
+impl Default for CoverSelectionRules {
+    fn default() -> Self {
+        Self {
+            stems: ["cover", "folder", "front", "album"]
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+            stems_only: false,
+            max_depth: 2,
+            target_side_px: 1000,
+            stem_weight: 4.0,
+            resolution_weight: 2.0,
+            squareness_weight: 2.0,
+            depth_weight: 1.0,
+        }
+    }
+}
+
+impl CoverSelectionRules {
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.stems_only || !self.stems.is_empty(),
+            "Cover stems must be provided when only cover stems are allowed"
+        );
+
+        for weight in [
+            self.stem_weight,
+            self.resolution_weight,
+            self.squareness_weight,
+            self.depth_weight,
+        ] {
+            ensure!(
+                weight.is_finite() && weight >= 0.0,
+                "Cover selection weights must be positive numbers"
+            );
+        }
+
+        Ok(())
+    }
+
+    fn is_candidate(&self, path: &Path) -> bool {
+        let (Some(stem), Some(ext)) = (
+            path.file_stem().and_then(OsStr::to_str),
+            path.extension().and_then(OsStr::to_str),
+        ) else {
+            return false;
+        };
+
+        // Artist images are used for the artist's art, not for their albums' covers
+        if stem.eq_ignore_ascii_case(ARTIST_IMAGE_STEM) {
+            return false;
+        }
+
+        if !COVER_EXTENSIONS
+            .iter()
+            .any(|valid_ext| valid_ext.eq_ignore_ascii_case(ext))
+        {
+            return false;
+        }
+
+        !self.stems_only || self.stem_priority(path).is_some()
+    }
+
+    fn stem_priority(&self, path: &Path) -> Option<usize> {
+        let file_stem = path.file_stem().and_then(OsStr::to_str)?;
+
+        self.stems
+            .iter()
+            .position(|stem| stem.eq_ignore_ascii_case(file_stem))
+    }
+
+    fn score(&self, candidate: &CoverCandidate) -> CoverScore {
+        let stem = self
+            .stem_priority(&candidate.path)
+            .map_or(0.0, |pos| 1.0 - ratio(pos, self.stems.len()));
+
+        let resolution = (f64::from(candidate.width.min(candidate.height))
+            / f64::from(self.target_side_px.max(1)))
+        .min(1.0);
+
+        let squareness = candidate.squareness();
+
+        let depth = 1.0 / (1.0 + f64::from(u32::try_from(candidate.depth).unwrap()));
+
+        CoverScore {
+            total: stem * self.stem_weight
+                + resolution * self.resolution_weight
+                + squareness * self.squareness_weight
+                + depth * self.depth_weight,
+            stem,
+            resolution,
+            squareness,
+            depth,
+        }
+    }
+}
+
+/// An image that may be used as an album's cover
+#[derive(Debug, Clone)]
+pub struct CoverCandidate {
+    /// Absolute path to the image
+    pub path: PathBuf,
+
+    /// Width of the image, in pixels
+    pub width: u32,
+
+    /// Height of the image, in pixels
+    pub height: u32,
+
+    /// Depth of the image relative to the album's root directory
+    pub depth: usize,
+}
+
+impl CoverCandidate {
+    /// Ratio between the image's smallest and largest side (1.0 for perfectly square images)
+    pub fn squareness(&self) -> f64 {
+        f64::from(self.width.min(self.height)) / f64::from(self.width.max(self.height).max(1))
+    }
+}
+
+struct CoverScore {
+    total: f64,
+    stem: f64,
+    resolution: f64,
+    squareness: f64,
+    depth: f64,
+}
+
+impl fmt::Display for CoverScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            total,
+            stem,
+            resolution,
+            squareness,
+            depth,
+        } = self;
+
+        write!(
+            f,
+            "score {total:.2} (stem: {stem:.2}, resolution: {resolution:.2}, squareness: {squareness:.2}, depth: {depth:.2})"
+        )
+    }
+}
+
+/// Find the best cover for each album in the index
+///
+/// Each image is attributed to the album(s) with the deepest root directory containing it,
+/// then all the candidates of an album are scored using the provided rules.
+pub fn find_album_covers(
+    music_dir: &Path,
+    index: &IndexCache,
+    rules: &CoverSelectionRules,
+) -> Result<AlbumCovers> {
+    let mut albums_by_root = HashMap::<&Path, Vec<AlbumID>>::new();
+
+    for (album_id, root) in &index.albums_tracks_relative_common_path {
+        albums_by_root
+            .entry(root.as_path())
+            .or_default()
+            .push(*album_id);
+    }
+
+    let mut candidates = HashMap::<AlbumID, Vec<CoverCandidate>>::new();
+
+    for entry in WalkDir::new(music_dir).min_depth(1) {
+        let entry = entry.context("Failed to read music directory entry")?;
+
+        if !entry.file_type().is_file() || !rules.is_candidate(entry.path()) {
+            continue;
+        }
+
+        let relative_path = entry.path().strip_prefix(music_dir).unwrap();
+
+        let Some((depth, album_ids)) = relative_path
+            .ancestors()
+            .skip(1)
+            .enumerate()
+            .find_map(|(depth, ancestor)| albums_by_root.get(ancestor).map(|ids| (depth, ids)))
+        else {
+            continue;
+        };
+
+        if depth > rules.max_depth {
+            continue;
+        }
+
+        let (width, height) = match image::image_dimensions(entry.path()) {
+            Ok(dimensions) => dimensions,
+            Err(err) => {
+                warn!(
+                    "Ignoring unreadable image file '{}': {err}",
+                    relative_path.display()
+                );
+
+                continue;
+            }
+        };
+
+        for album_id in album_ids {
+            candidates
+                .entry(*album_id)
+                .or_default()
+                .push(CoverCandidate {
+                    path: entry.path().to_owned(),
+                    width,
+                    height,
+                    depth,
+                });
+        }
+    }
+
+    let mut covers = HashMap::new();
+
+    for album_id in index.albums.keys() {
+        let album_root_path = index
+            .albums_tracks_relative_common_path
+            .get(album_id)
+            .unwrap();
+
+        let mut candidates = candidates.remove(album_id).unwrap_or_default();
+
+        // Ensure a deterministic choice between candidates that have the exact same score
+        candidates.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut scored = candidates
+            .into_iter()
+            .map(|candidate| (rules.score(&candidate), candidate))
+            .collect::<Vec<_>>();
+
+        // Stable sort, so the first candidate wins in case of equality
+        scored.sort_by(|(a, _), (b, _)| b.total.total_cmp(&a.total));
+
+        match scored.as_slice() {
+            [] => {
+                debug!(
+                    "--> No art found for album at path: {}",
+                    album_root_path.display()
+                );
+            }
+
+            [_] => {}
+
+            [(best_score, best), others @ ..] => {
+                debug!(
+                    "--> Selected cover '{}' with {best_score} over {} other candidate(s) for album at path: {}",
+                    best.path.strip_prefix(music_dir).unwrap().display(),
+                    others.len().to_string().bright_yellow(),
+                    album_root_path.display()
+                );
+
+                for (score, candidate) in others {
+                    trace!(
+                        "---> Discarded '{}' with {score}",
+                        candidate.path.strip_prefix(music_dir).unwrap().display()
+                    );
+                }
+            }
+        }
+
+        covers.insert(
+            *album_id,
+            scored.into_iter().next().map(|(_, candidate)| candidate),
+        );
+    }
+
+    Ok(covers)
+}
+
+/// Compare two album covers by order of preference for use in a mosaic
+///
+/// Actual covers are preferred over placeholders, then covers closest to a 1:1 aspect ratio.
+pub fn cmp_mosaic_preference(a: Option<&CoverCandidate>, b: Option<&CoverCandidate>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.squareness().total_cmp(&a.squareness()),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn ratio(value: usize, total: usize) -> f64 {
+    f64::from(u32::try_from(value).unwrap()) / f64::from(u32::try_from(total.max(1)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_candidate() {
+        let rules = CoverSelectionRules::default();
+
+        let stems_only = CoverSelectionRules {
+            stems_only: true,
+            ..CoverSelectionRules::default()
+        };
+
+        for (path, expected, expected_stems_only) in [
+            ("cover.jpg", true, true),
+            ("Folder.PNG", true, true),
+            ("scans/back.jpg", true, false),
+            ("artist.jpg", false, false),
+            ("Artist.webp", false, false),
+            ("cover.txt", false, false),
+            ("cover", false, false),
+        ] {
+            assert_eq!(
+                rules.is_candidate(Path::new(path)),
+                expected,
+                "Unexpected result for path '{path}'"
+            );
+
+            assert_eq!(
+                stems_only.is_candidate(Path::new(path)),
+                expected_stems_only,
+                "Unexpected result for path '{path}' with stems only"
+            );
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{Result, ensure};
+use serde::Deserialize;
+
+use crate::stable_hash;
+
+use super::ArtSize;
+
+/// Settings used to encode arts
+///
+/// Arts that were generated with different settings are regenerated during the next index update.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArtSettings {
+    /// Encoding quality for lossy formats, between 1 and 100
+    pub quality: u8,
+
+    /// Side (in pixels) of each of the pre-generated sizes
+    pub large_side_px: u32,
+    pub medium_side_px: u32,
+    pub small_side_px: u32,
+    pub tiny_side_px: u32,
+}
+
+impl Default for ArtSettings {
+    fn default() -> Self {
+        Self {
+            quality: 70,
+            large_side_px: 2000,
+            medium_side_px: 500,
+            small_side_px: 250,
+            tiny_side_px: 125,
+        }
+    }
+}
+
+impl ArtSettings {
+    pub fn validate(&self) -> Result<()> {
+        let Self {
+            quality,
+            large_side_px,
+            medium_side_px,
+            small_side_px,
+            tiny_side_px,
+        } = *self;
+
+        ensure!(
+            (1..=100).contains(&quality),
+            "Art quality must be between 1 and 100"
+        );
+
+        ensure!(tiny_side_px > 0, "Art sizes cannot be zero");
+
+        ensure!(
+            large_side_px >= medium_side_px
+                && medium_side_px >= small_side_px
+                && small_side_px >= tiny_side_px,
+            "Art sizes must be provided in decreasing order (large, medium, small, tiny)"
+        );
+
+        // Genre arts are made of four images assembled in half the size of large arts
+        ensure!(
+            large_side_px.is_multiple_of(4),
+            "Large art size must be a multiple of 4"
+        );
+
+        Ok(())
+    }
+
+    pub fn side_px(&self, size: ArtSize) -> u32 {
+        match size {
+            ArtSize::Large => self.large_side_px,
+            ArtSize::Medium => self.medium_side_px,
+            ArtSize::Small => self.small_side_px,
+            ArtSize::Tiny => self.tiny_side_px,
+        }
+    }
+
+    /// Hash of the settings, changing whenever arts need to be regenerated
+    pub fn stable_hash(&self) -> u64 {
+        stable_hash!(
+            self.quality,
+            self.large_side_px,
+            self.medium_side_px,
+            self.small_side_px,
+            self.tiny_side_px
+        )
+    }
+}
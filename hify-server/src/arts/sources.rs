@@ -0,0 +1,62 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Result;
+use colored::Colorize;
+use log::debug;
+
+use crate::index::{ArtistID, GenreID, IndexCache};
+
+use super::{
+    custom::{CustomImage, find_artist_images, find_genre_images},
+    selection::{AlbumCovers, CoverSelectionRules, find_album_covers},
+};
+
+/// Images found in the music and genres art directories, which arts are generated from
+///
+/// Looking for them requires walking through the whole music directory, so they are kept
+/// around after each index update to regenerate single arts when their overrides change.
+pub struct ArtSources {
+    pub album_covers: AlbumCovers,
+    pub artist_images: HashMap<ArtistID, CustomImage>,
+    pub genre_images: HashMap<GenreID, CustomImage>,
+}
+
+impl ArtSources {
+    pub fn find(
+        index: &IndexCache,
+        music_dir: &Path,
+        cover_rules: &CoverSelectionRules,
+        genres_art_dir: Option<&Path>,
+    ) -> Result<Self> {
+        debug!(
+            "-> Looking for album arts for {} albums...",
+            index.albums.len().to_string().bright_yellow()
+        );
+
+        let album_covers = find_album_covers(music_dir, index, cover_rules)?;
+        assert_eq!(album_covers.len(), index.albums.len());
+
+        debug!(
+            "-> Found {} album arts in total",
+            album_covers
+                .values()
+                .filter(|cover| cover.is_some())
+                .count()
+                .to_string()
+                .bright_yellow()
+        );
+
+        let artist_images = find_artist_images(music_dir, index)?;
+
+        let genre_images = match genres_art_dir {
+            Some(genres_art_dir) => find_genre_images(genres_art_dir, index)?,
+            None => HashMap::new(),
+        };
+
+        Ok(Self {
+            album_covers,
+            artist_images,
+            genre_images,
+        })
+    }
+}
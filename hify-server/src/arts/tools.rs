@@ -1,7 +1,16 @@
 use std::{fs, path::Path};
 
 use anyhow::{Context, Result};
-use image::{GenericImage, RgbImage, imageops::FilterType};
+use image::{
+    ExtendedColorType, GenericImage, ImageEncoder, RgbImage,
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder},
+    imageops::FilterType,
+};
+
+use super::ArtFormat;
+
+/// Encoding speed for AVIF images, from 1 (slowest) to 10 (fastest)
+static AVIF_ENCODING_SPEED: u8 = 8;
 
 pub fn resize_image(image: &RgbImage, nwidth: u32, nheight: u32) -> RgbImage {
     if image.width() == nwidth && image.height() == nheight {
@@ -11,6 +20,27 @@ pub fn resize_image(image: &RgbImage, nwidth: u32, nheight: u32) -> RgbImage {
     image::imageops::resize(image, nwidth, nheight, FilterType::Lanczos3)
 }
 
+/// Downscale an image so that none of its sides exceeds the provided size, keeping its aspect ratio
+pub fn resize_image_to_fit(image: &RgbImage, max_side_px: u32) -> RgbImage {
+    if image.width() <= max_side_px && image.height() <= max_side_px {
+        return image.clone();
+    }
+
+    let scale = f64::from(max_side_px) / f64::from(image.width().max(image.height()));
+
+    #[allow(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    let (nwidth, nheight) = (
+        ((f64::from(image.width()) * scale).round() as u32).max(1),
+        ((f64::from(image.height()) * scale).round() as u32).max(1),
+    );
+
+    resize_image(image, nwidth, nheight)
+}
+
 pub fn assemble_four_images(
     top_left: &RgbImage,
     top_right: &RgbImage,
@@ -32,10 +62,43 @@ pub fn assemble_four_images(
     Ok(image)
 }
 
-pub fn save_image_webp(path: &Path, image: &RgbImage) -> Result<()> {
-    let encoder = webp::Encoder::from_rgb(image, image.width(), image.height()).encode(70_f32);
+pub static SOURCE_WEBP_QUALITY: u8 = 90;
+
+/// Encode an image in the provided format and write it to a file
+///
+/// The quality (between 1 and 100) is ignored for lossless formats.
+pub fn save_image(path: &Path, image: &RgbImage, format: ArtFormat, quality: u8) -> Result<()> {
+    let mut buf = vec![];
+
+    let (width, height) = (image.width(), image.height());
+
+    match format {
+        ArtFormat::Webp => {
+            let encoded = webp::Encoder::from_rgb(image, width, height).encode(f32::from(quality));
+
+            buf.extend_from_slice(&encoded);
+        }
+
+        ArtFormat::Jpeg => {
+            JpegEncoder::new_with_quality(&mut buf, quality).write_image(
+                image,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+        }
+
+        ArtFormat::Png => {
+            PngEncoder::new(&mut buf).write_image(image, width, height, ExtendedColorType::Rgb8)?;
+        }
+
+        ArtFormat::Avif => {
+            AvifEncoder::new_with_speed_quality(&mut buf, AVIF_ENCODING_SPEED, quality)
+                .write_image(image, width, height, ExtendedColorType::Rgb8)?;
+        }
+    }
 
-    fs::write(path, &*encoder)
+    fs::write(path, &buf)
         .with_context(|| format!("Failed to write image to file: {}", path.display()))?;
 
     Ok(())
@@ -0,0 +1,136 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyhow::Result;
+use colored::Colorize;
+use log::{debug, info};
+
+use crate::{
+    arts::{
+        custom::CustomImage,
+        embedded::{find_album_embedded_art, open_embedded_art},
+        overrides::ArtOverride,
+    },
+    index::{IndexCache, Track, TrackID},
+    stable_hash,
+    utils::TaskRunner,
+};
+
+use super::ArtsManager;
+
+/// What a track's art is generated from
+enum TrackArtSource {
+    /// An uploaded image
+    Override(PathBuf),
+
+    /// The art embedded in the track's file
+    Embedded(Box<Track>),
+}
+
+/// Generate arts for all tracks (or only for the provided one) that are overridden
+/// or whose embedded art differs from their album's
+///
+/// Other tracks use their album's art, which avoids storing the same art for every track.
+pub fn generate_track_arts(
+    index: &IndexCache,
+    music_dir: &Path,
+    track_arts: &ArtsManager<TrackID>,
+    only: Option<TrackID>,
+) -> Result<()> {
+    debug!("-> Checking tracks that require new art generation...");
+
+    let mut tracks_to_generate = vec![];
+
+    for (album_id, album_tracks) in &index.albums_tracks {
+        if only.is_some_and(|only| !album_tracks.contains(&only)) {
+            continue;
+        }
+
+        let album_embedded_art =
+            find_album_embedded_art(index, *album_id).map(|(embedded_art, _)| embedded_art);
+
+        for track_id in album_tracks {
+            if only.is_some_and(|only| only != *track_id) {
+                continue;
+            }
+
+            let track = index.tracks.get(track_id).unwrap();
+
+            // Overrides take precedence over embedded arts
+            if let Some(ArtOverride::Image { path, hash }) = track_arts.overrides().get(*track_id) {
+                if !track_arts.has_with_source_data(*track_id, hash) {
+                    tracks_to_generate.push((*track_id, TrackArtSource::Override(path), hash));
+                }
+
+                continue;
+            }
+
+            match track.metadata.embedded_art {
+                Some(embedded_art) if Some(embedded_art) != album_embedded_art => {
+                    let hash = stable_hash!("embedded", embedded_art);
+
+                    if !track_arts.has_with_source_data(*track_id, hash) {
+                        tracks_to_generate.push((
+                            *track_id,
+                            TrackArtSource::Embedded(Box::new(track.clone())),
+                            hash,
+                        ));
+                    }
+                }
+
+                // Tracks that now use their album's art must not keep a previously generated one
+                _ => {
+                    if track_arts.has(*track_id) {
+                        track_arts.delete(*track_id)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if tracks_to_generate.is_empty() {
+        return Ok(());
+    }
+
+    info!(
+        "-> Generating {} track arts...",
+        tracks_to_generate.len().to_string().bright_yellow()
+    );
+
+    let mut tasks = TaskRunner::new();
+
+    let total = Arc::new(AtomicUsize::new(0));
+
+    for (track_id, source, hash) in tracks_to_generate {
+        let music_dir = music_dir.to_owned();
+        let track_arts = track_arts.clone();
+        let total = Arc::clone(&total);
+
+        tasks.spawn(move || {
+            let img = match source {
+                TrackArtSource::Override(path) => CustomImage::open(&path)?,
+                TrackArtSource::Embedded(track) => open_embedded_art(&music_dir, &track)?,
+            };
+
+            assert!(track_arts.register(track_id, hash, &img)?);
+
+            total.fetch_add(1, Ordering::SeqCst);
+
+            Ok(())
+        });
+    }
+
+    tasks.join_all()?;
+
+    info!(
+        "-> Successfully generated {} track arts",
+        total.load(Ordering::SeqCst).to_string().bright_yellow()
+    );
+
+    Ok(())
+}
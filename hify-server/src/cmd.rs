@@ -1,13 +1,17 @@
 use std::{net::IpAddr, path::PathBuf};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::LevelFilter;
 
 #[derive(Parser)]
-#[clap(version, about, long_about = None)]
+#[clap(version, about, long_about = None, subcommand_negates_reqs = true)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct CmdArgs {
-    #[clap(help = "Path to the music directory to index")]
-    pub music_dir: PathBuf,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    #[clap(help = "Path to the music directory to index", required = true)]
+    pub music_dir: Option<PathBuf>,
 
     #[clap(short, long, help = "Path to the data directory")]
     pub data_dir: PathBuf,
@@ -28,9 +32,165 @@ pub struct CmdArgs {
     )]
     pub just_update_index: bool,
 
-    #[clap(short, long, help = "Address to listen on", default_value = "0.0.0.0")]
-    pub addr: IpAddr,
+    #[clap(short, long, help = "Address to listen on (default: 0.0.0.0)")]
+    pub addr: Option<IpAddr>,
+
+    #[clap(short, long, help = "Port to listen on (default: 8891)")]
+    pub port: Option<u16>,
+
+    #[clap(
+        long,
+        help = "Path to a directory containing images for genres, named after them (e.g. 'Jazz.jpg')"
+    )]
+    pub genres_art_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Comma-separated file stems of album cover images, by order of preference",
+        value_delimiter = ','
+    )]
+    pub cover_stems: Option<Vec<String>>,
+
+    #[clap(
+        long,
+        help = "Ignore album images whose file stem is not part of the cover stems"
+    )]
+    pub only_cover_stems: bool,
+
+    #[clap(
+        long,
+        help = "Maximum size (in MiB) of the cache for arts rendered at non-preset sizes (default: 512)"
+    )]
+    pub resized_arts_cache_mib: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Encoding quality of arts in lossy formats, between 1 and 100 (default: 70)"
+    )]
+    pub art_quality: Option<u8>,
+
+    #[clap(
+        long,
+        help = "Comma-separated sides (in pixels) of the large, medium, small and tiny arts",
+        value_delimiter = ',',
+        num_args = 4
+    )]
+    pub art_sizes: Option<Vec<u32>>,
+
+    #[clap(
+        long,
+        help = "Don't expose playlists generated from ratings to OpenSubsonic clients"
+    )]
+    pub no_auto_playlists: bool,
+
+    #[clap(
+        long,
+        help = "Import playlist files (M3U, M3U8, XSPF) found in the music directory when updating the index"
+    )]
+    pub import_playlists: bool,
+
+    #[clap(
+        long,
+        help = "Comma-separated origins allowed to make cross-origin requests ('*' to allow all), only allowing the bundled web client by default",
+        value_delimiter = ','
+    )]
+    pub cors_origins: Option<Vec<String>>,
+
+    #[clap(
+        long,
+        help = "Path all routes are served under, when behind a reverse proxy (e.g. '/hify')"
+    )]
+    pub base_path: Option<String>,
+
+    #[clap(
+        long,
+        help = "Build URIs handed out to clients from the X-Forwarded-Proto, -Host and -Prefix headers"
+    )]
+    pub trust_forwarded_headers: bool,
+
+    #[clap(
+        long,
+        help = "Maximum number of tasks run concurrently when updating the index (default: 32)"
+    )]
+    pub max_concurrent_tasks: Option<usize>,
+
+    #[clap(
+        long,
+        help = "Path to the TLS certificate chain (PEM), to serve HTTPS",
+        requires = "tls_key"
+    )]
+    pub tls_cert: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Path to the TLS private key (PEM), to serve HTTPS",
+        requires = "tls_cert"
+    )]
+    pub tls_key: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Port of an HTTP listener redirecting all requests to HTTPS"
+    )]
+    pub http_redirect_port: Option<u16>,
+
+    #[clap(
+        long,
+        help = "Path of a Unix socket to listen on, instead of a TCP address",
+        conflicts_with = "addr",
+        conflicts_with = "port",
+        conflicts_with = "tls_cert"
+    )]
+    pub unix_socket: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Permissions of the Unix socket, in octal (e.g. 660)",
+        value_parser = parse_octal_mode
+    )]
+    pub unix_socket_permissions: Option<u32>,
+}
+
+fn parse_octal_mode(mode: &str) -> Result<u32, String> {
+    let mode = mode.strip_prefix("0o").unwrap_or(mode);
+    u32::from_str_radix(mode, 8).map_err(|err| format!("Invalid octal mode: {err}"))
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[clap(
+        subcommand,
+        about = "Manage user accounts (takes effect on the server's next start)"
+    )]
+    Users(UsersCommand),
+}
+
+#[derive(Subcommand)]
+pub enum UsersCommand {
+    #[clap(about = "List user accounts")]
+    List,
+
+    #[clap(about = "Create a user account with a random password")]
+    Create {
+        #[clap(help = "Name of the user")]
+        name: String,
+
+        #[clap(
+            long,
+            help = "Allow the user to manage the library (index updates, arts overrides, ...)"
+        )]
+        admin: bool,
+    },
+
+    #[clap(about = "Replace a user's password with a new random one")]
+    ResetPassword {
+        #[clap(help = "Name of the user")]
+        name: String,
+    },
 
-    #[clap(short, long, help = "Port to listen on", default_value = "8891")]
-    pub port: u16,
+    #[clap(about = "Replace a user's API key with a new random one")]
+    ResetApiKey {
+        #[clap(help = "Name of the user")]
+        name: String,
+    },
 }
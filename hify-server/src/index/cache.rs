@@ -84,6 +84,8 @@ impl IndexCache {
         let cmp_index = CmpIndex::build(index);
 
         let Index {
+            format_version: _,
+            settings_hash: _,
             tracks,
             albums,
             artists,
@@ -15,8 +15,22 @@ use crate::{
 
 // TODO: custom debug impl for artistID etc. with base62 encoding
 
+/// Version of the index's format, to increment whenever tracks need to be analyzed again
+/// to fill new fields (e.g. embedded arts)
+pub static INDEX_FORMAT_VERSION: u32 = 1;
+
 #[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Index {
+    /// Version of the format the index was generated with (see [`INDEX_FORMAT_VERSION`])
+    #[serde(default)]
+    pub format_version: u32,
+
+    /// Hash of the indexer settings the tracks were analyzed with
+    /// (see [`crate::indexer::IndexerSettings::analysis_hash`])
+    #[serde(default)]
+    pub settings_hash: u64,
+
     pub tracks: Vec<Track>,
     pub albums: Vec<Album>,
     pub artists: Vec<Artist>,
@@ -48,8 +62,15 @@ pub struct Track {
 pub struct TrackMetadata {
     pub duration_s: u32,
     pub audio_codec: TrackAudioCodec,
+
+    /// Hash of the art embedded in the track's file, if any
+    #[serde(default)]
+    pub embedded_art: Option<EmbeddedArtHash>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmbeddedArtHash(#[serde(with = "u64_base62_serialization")] pub u64);
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
 #[allow(clippy::upper_case_acronyms)]
@@ -229,4 +250,6 @@ macro_rules! impl_id_type {
     };
 }
 
+pub(crate) use impl_id_type;
+
 impl_id_type!(TrackID, AlbumID, ArtistID, GenreID);
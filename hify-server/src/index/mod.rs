@@ -9,6 +9,8 @@ pub use self::{cache::*, cmp::*, content::*};
 /// Assert that the index is correct. Will panic if not.
 pub fn assert_index_correctness(index: &Index) {
     let Index {
+        format_version: _,
+        settings_hash: _,
         tracks,
         albums,
         artists,
@@ -6,44 +6,29 @@ use symphonia::core::{
         CODEC_ID_AAC, CODEC_ID_FLAC, CODEC_ID_MP3, CODEC_ID_OPUS, CODEC_ID_VORBIS,
     },
     formats::{
-        FormatOptions, Track, TrackType,
+        FormatOptions, FormatReader, Track, TrackType,
         probe::Hint,
         well_known::{FORMAT_ID_FLAC, FORMAT_ID_ISOMP4, FORMAT_ID_MP3, FORMAT_ID_OGG},
     },
     io::{MediaSourceStream, MediaSourceStreamOptions},
-    meta::MetadataOptions,
+    meta::{MetadataOptions, MetadataRevision, StandardVisualKey, Visual},
     units::Timestamp,
 };
 
 use crate::{
-    index::{TrackAudioCodec, TrackMetadata},
+    index::{EmbeddedArtHash, TrackAudioCodec, TrackMetadata},
     indexer::tags::convert_symphonia_metadata,
+    stable_hash,
 };
 
 use super::tags::TrackStrTags;
 
 /// Analyzes an audio file and returns its metadata and tags.
-pub fn analyze_file(path: &Path) -> Result<(TrackMetadata, TrackStrTags)> {
-    let src = File::open(path).context("Failed")?;
-
-    let mss = MediaSourceStream::new(Box::new(src), MediaSourceStreamOptions::default());
-
-    let mut hint = Hint::new();
-    hint.with_extension(
-        path.extension()
-            .context("File does not have an extension")?
-            .to_str()
-            .context("File extension contains invalid UTF-8 characters")?,
-    );
-
-    let mut format_reader = symphonia::default::get_probe()
-        .probe(
-            &hint,
-            mss,
-            FormatOptions::default(),
-            MetadataOptions::default(),
-        )
-        .context("Found unsupported codec")?;
+pub fn analyze_file(
+    path: &Path,
+    tag_separators: &[String],
+) -> Result<(TrackMetadata, TrackStrTags)> {
+    let mut format_reader = probe_file(path)?;
 
     let track_types = format_reader
         .tracks()
@@ -89,18 +74,7 @@ pub fn analyze_file(path: &Path) -> Result<(TrackMetadata, TrackStrTags)> {
         bail!("Expected audio track, but found {:?}", track.track_type());
     }
 
-    // Prefer metadata that's provided in the container format, over other tags found during the
-    // probe operation.
-    let mut mt = format_reader.metadata();
-
-    while mt.current().is_none() {
-        if mt.pop().is_none() {
-            bail!("No metadata was found in audio file");
-        }
-    }
-
-    // Get the newest metadata revision, which should be the one that contains the most complete set of tags.
-    let rev = mt.skip_to_latest().cloned().unwrap();
+    let rev = latest_metadata_revision(&mut *format_reader)?;
 
     let codec_params = track
         .codec_params
@@ -139,7 +113,68 @@ pub fn analyze_file(path: &Path) -> Result<(TrackMetadata, TrackStrTags)> {
             duration_s: u32::try_from(dur_secs)
                 .context("Audio track is longer than 2^32-1 seconds!")?
                 + u32::from(dur_nanos > 500_000_000),
+            embedded_art: select_embedded_art(&rev)
+                .map(|visual| EmbeddedArtHash(stable_hash!(visual.data))),
         },
-        convert_symphonia_metadata(&rev)?,
+        convert_symphonia_metadata(&rev, tag_separators)?,
     ))
 }
+
+/// Extracts the art embedded in an audio file, if any.
+///
+/// The returned data is encoded in the format the art was embedded with.
+pub fn extract_embedded_art(path: &Path) -> Result<Option<Box<[u8]>>> {
+    let mut format_reader = probe_file(path)?;
+
+    let rev = latest_metadata_revision(&mut *format_reader)?;
+
+    Ok(select_embedded_art(&rev).map(|visual| visual.data.clone()))
+}
+
+fn probe_file(path: &Path) -> Result<Box<dyn FormatReader>> {
+    let src = File::open(path).context("Failed")?;
+
+    let mss = MediaSourceStream::new(Box::new(src), MediaSourceStreamOptions::default());
+
+    let mut hint = Hint::new();
+    hint.with_extension(
+        path.extension()
+            .context("File does not have an extension")?
+            .to_str()
+            .context("File extension contains invalid UTF-8 characters")?,
+    );
+
+    symphonia::default::get_probe()
+        .probe(
+            &hint,
+            mss,
+            FormatOptions::default(),
+            MetadataOptions::default(),
+        )
+        .context("Found unsupported codec")
+}
+
+fn latest_metadata_revision(format_reader: &mut dyn FormatReader) -> Result<MetadataRevision> {
+    // Prefer metadata that's provided in the container format, over other tags found during the
+    // probe operation.
+    let mut mt = format_reader.metadata();
+
+    while mt.current().is_none() {
+        if mt.pop().is_none() {
+            bail!("No metadata was found in audio file");
+        }
+    }
+
+    // Get the newest metadata revision, which should be the one that contains the most complete set of tags.
+    Ok(mt.skip_to_latest().cloned().unwrap())
+}
+
+/// Select the embedded art to use for a track, preferring front covers over other pictures
+fn select_embedded_art(rev: &MetadataRevision) -> Option<&Visual> {
+    let visuals = &rev.media.visuals;
+
+    visuals
+        .iter()
+        .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+        .or_else(|| visuals.first())
+}
@@ -4,33 +4,139 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, bail, ensure};
 use colored::Colorize;
 use log::{debug, info, warn};
+use serde::Deserialize;
 use walkdir::WalkDir;
 
 use crate::{
-    index::{Album, Artist, FileTimes, Genre, Index, IndexCache, Track, TrackID, TrackTags},
+    index::{
+        Album, Artist, FileTimes, Genre, INDEX_FORMAT_VERSION, Index, IndexCache, Track, TrackID,
+        TrackTags,
+    },
+    stable_hash,
     utils::TaskRunner,
 };
 
 use self::{
     tags::TrackStrTags,
-    walker::{analyze_audio_files, may_be_audio_file},
+    walker::{analyze_audio_files, is_playlist_file, may_be_audio_file},
 };
 
 mod analyzer;
 mod tags;
 mod walker;
 
+pub use self::analyzer::extract_embedded_art;
+
+/// Extensions of the audio files the analyzer is able to handle
+pub static SUPPORTED_AUDIO_EXTENSIONS: &[&str] =
+    &["mp3", "flac", "m4a", "m4b", "ogg", "oga", "opus"];
+
+/// Settings used to analyze the music directory
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexerSettings {
+    /// Extensions (case-insensitive) of the files to analyze as audio files
+    ///
+    /// Only MP3, FLAC, MP4 and OGG files are supported by the analyzer
+    /// (see [`SUPPORTED_AUDIO_EXTENSIONS`]).
+    pub audio_extensions: Vec<String>,
+
+    /// Separators used to split tags with multiple values (e.g. artists), on top of newlines
+    pub tag_separators: Vec<String>,
+
+    /// Import playlist files (M3U, M3U8, XSPF) found in the music directory
+    pub import_playlists: bool,
+}
+
+impl Default for IndexerSettings {
+    fn default() -> Self {
+        Self {
+            audio_extensions: ["mp3", "flac", "m4a", "ogg", "opus"]
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+            tag_separators: [";", ","].into_iter().map(str::to_owned).collect(),
+            import_playlists: false,
+        }
+    }
+}
+
+impl IndexerSettings {
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.audio_extensions.is_empty(),
+            "At least one audio extension must be provided"
+        );
+
+        ensure!(
+            self.audio_extensions
+                .iter()
+                .all(|ext| !ext.is_empty() && !ext.starts_with('.')),
+            "Audio extensions cannot be empty or start with a dot"
+        );
+
+        if let Some(ext) = self.audio_extensions.iter().find(|ext| {
+            !SUPPORTED_AUDIO_EXTENSIONS
+                .iter()
+                .any(|supported| ext.eq_ignore_ascii_case(supported))
+        }) {
+            bail!(
+                "Audio extension '{ext}' is not supported, expected one of: {}",
+                SUPPORTED_AUDIO_EXTENSIONS.join(", ")
+            );
+        }
+
+        ensure!(
+            self.tag_separators.iter().all(|sep| !sep.is_empty()),
+            "Tag separators cannot be empty"
+        );
+
+        Ok(())
+    }
+
+    /// Hash of the settings which affect how tracks are analyzed
+    ///
+    /// When it changes, all tracks need to be analyzed again.
+    pub fn analysis_hash(&self) -> u64 {
+        let audio_extensions = self
+            .audio_extensions
+            .iter()
+            .map(|ext| ext.to_ascii_lowercase())
+            .collect::<Vec<_>>();
+
+        stable_hash!(audio_extensions, self.tag_separators)
+    }
+}
+
+/// Result of the analysis of a music directory
+pub struct LibraryAnalysis {
+    /// The next index if changes were detected, or [`None`] if no changes were found
+    pub index: Option<Index>,
+
+    /// Playlist files found in the directory (only if their discovery was requested)
+    pub playlist_files: Vec<PathBuf>,
+}
+
 /// Analyze the tracks in the given directory, merges with the previous index.
-///
-/// Returns the next index if changes were detected, or [`None`] if no changes were found.
 #[allow(clippy::too_many_lines)]
-pub fn analyze_tracks_in(dir: &Path, prev_index: Option<&IndexCache>) -> Result<Option<Index>> {
+pub fn analyze_tracks_in(
+    dir: &Path,
+    prev_index: Option<&IndexCache>,
+    settings: &IndexerSettings,
+) -> Result<LibraryAnalysis> {
     debug!("-> Building files list...");
 
-    let files = build_files_list(dir)?;
+    let (files, playlist_files) = build_files_list(dir, settings)?;
+
+    if settings.import_playlists {
+        debug!(
+            "-> Found {} playlist files",
+            playlist_files.len().to_string().bright_yellow()
+        );
+    }
 
     let empty_cache = IndexCache::default();
     let prev_index = prev_index.unwrap_or(&empty_cache);
@@ -46,7 +152,11 @@ pub fn analyze_tracks_in(dir: &Path, prev_index: Option<&IndexCache>) -> Result<
 
     if new_tracks.is_empty() && modified_tracks.is_empty() && deleted_tracks.is_empty() {
         info!("-> No changes detected, skipping analysis.");
-        return Ok(None);
+
+        return Ok(LibraryAnalysis {
+            index: None,
+            playlist_files,
+        });
     }
 
     info!(
@@ -82,8 +192,11 @@ pub fn analyze_tracks_in(dir: &Path, prev_index: Option<&IndexCache>) -> Result<
             .bright_yellow()
     );
 
-    let analyzed =
-        analyze_audio_files(new_tracks.into_iter().chain(modified_tracks).cloned(), dir)?;
+    let analyzed = analyze_audio_files(
+        new_tracks.into_iter().chain(modified_tracks).cloned(),
+        dir,
+        &settings.tag_separators,
+    )?;
 
     info!("--> Building new index...");
 
@@ -234,25 +347,44 @@ pub fn analyze_tracks_in(dir: &Path, prev_index: Option<&IndexCache>) -> Result<
 
     assert_eq!(index_tracks.len(), total_tracks);
 
-    Ok(Some(Index {
-        tracks: index_tracks,
-        albums: index_albums.into_values().collect(),
-        artists: index_artists.into_values().collect(),
-        genres: index_genres.into_values().collect(),
-    }))
+    Ok(LibraryAnalysis {
+        index: Some(Index {
+            format_version: INDEX_FORMAT_VERSION,
+            settings_hash: settings.analysis_hash(),
+            tracks: index_tracks,
+            albums: index_albums.into_values().collect(),
+            artists: index_artists.into_values().collect(),
+            genres: index_genres.into_values().collect(),
+        }),
+        playlist_files,
+    })
 }
 
 /// Build a list of all audio files in the given directory, along with their file times and sizes.
-fn build_files_list(dir: &Path) -> Result<BTreeMap<PathBuf, FileTimesWithSize>> {
+///
+/// Playlist files are listed as well (relative to the directory) if their import is requested.
+fn build_files_list(
+    dir: &Path,
+    settings: &IndexerSettings,
+) -> Result<(BTreeMap<PathBuf, FileTimesWithSize>, Vec<PathBuf>)> {
     let dir_bis = dir.to_owned();
 
     let mut tasks = TaskRunner::<Option<(PathBuf, FileTimesWithSize)>>::new();
 
+    let mut playlist_files = vec![];
+
     for item in WalkDir::new(&dir_bis).min_depth(1) {
         let item = item.context("Failed to read music directory entry")?;
+
+        if settings.import_playlists && item.file_type().is_file() && is_playlist_file(item.path())
+        {
+            playlist_files.push(item.path().strip_prefix(dir).unwrap().to_owned());
+            continue;
+        }
+
         let item = item.path().to_owned();
 
-        if !may_be_audio_file(&item) {
+        if !may_be_audio_file(&item, &settings.audio_extensions) {
             continue;
         }
 
@@ -287,7 +419,7 @@ fn build_files_list(dir: &Path) -> Result<BTreeMap<PathBuf, FileTimesWithSize>>
 
     let files = tasks.join_all()?;
 
-    Ok(files.into_iter().flatten().collect())
+    Ok((files.into_iter().flatten().collect(), playlist_files))
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -361,3 +493,57 @@ struct TracksChanges<'a> {
     deleted: Vec<&'a PathBuf>,
     unchanged: Vec<&'a PathBuf>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_extensions_validation() {
+        for (audio_extensions, expected_valid) in [
+            (vec!["mp3", "flac"], true),
+            (vec!["MP3", "Opus"], true),
+            (vec!["mp3", "wav"], false),
+            (vec![".mp3"], false),
+            (vec![], false),
+        ] {
+            let settings = IndexerSettings {
+                audio_extensions: audio_extensions.iter().map(|&ext| ext.to_owned()).collect(),
+                ..IndexerSettings::default()
+            };
+
+            assert_eq!(
+                settings.validate().is_ok(),
+                expected_valid,
+                "Unexpected validation result for extensions {audio_extensions:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_analysis_hash() {
+        let settings = IndexerSettings::default();
+
+        let playlists_imported = IndexerSettings {
+            import_playlists: true,
+            ..settings.clone()
+        };
+
+        assert_eq!(
+            settings.analysis_hash(),
+            playlists_imported.analysis_hash(),
+            "Importing playlists should not require analyzing tracks again"
+        );
+
+        let other_separators = IndexerSettings {
+            tag_separators: vec![";".to_owned()],
+            ..settings.clone()
+        };
+
+        assert_ne!(
+            settings.analysis_hash(),
+            other_separators.analysis_hash(),
+            "Changing tag separators should require analyzing tracks again"
+        );
+    }
+}
@@ -9,8 +9,12 @@ use symphonia::core::meta::{MetadataRevision, StandardTag};
 
 /// Extracts tags from a [`symphonia`] [`MetadataRevision`].
 ///
-/// Tags are trimmed, deduplicated in the case of arrays, and various errors are reported.
-pub fn convert_symphonia_metadata(rev: &MetadataRevision) -> Result<TrackStrTags> {
+/// Tags are trimmed, tags with multiple values are split using the provided separators
+/// and deduplicated, and various errors are reported.
+pub fn convert_symphonia_metadata(
+    rev: &MetadataRevision,
+    tag_separators: &[String],
+) -> Result<TrackStrTags> {
     // TODO: chain &rev.per_track.tags?
     let std_tags = rev
         .media
@@ -63,16 +67,16 @@ pub fn convert_symphonia_metadata(rev: &MetadataRevision) -> Result<TrackStrTags
         title: require_tag_str!(TrackTitle)?,
 
         // Track artists
-        artists: get_tag_str_array(&std_tags, tag_str_matcher!(Artist)),
+        artists: get_tag_str_array(&std_tags, tag_separators, tag_str_matcher!(Artist)),
 
         // Track composers
-        composers: get_tag_str_array(&std_tags, tag_str_matcher!(Composer)),
+        composers: get_tag_str_array(&std_tags, tag_separators, tag_str_matcher!(Composer)),
 
         // Album name
         album: require_tag_str!(Album)?,
 
         // Album artists
-        album_artists: get_tag_str_array(&std_tags, tag_str_matcher!(AlbumArtist)),
+        album_artists: get_tag_str_array(&std_tags, tag_separators, tag_str_matcher!(AlbumArtist)),
 
         // Disc number
         disc: get_tag_int!(DiscNumber)?.map(|disc| u16::try_from(disc).unwrap()),
@@ -87,7 +91,7 @@ pub fn convert_symphonia_metadata(rev: &MetadataRevision) -> Result<TrackStrTags
             .transpose()?,
 
         // Musical genres
-        genres: get_tag_str_array(&std_tags, tag_str_matcher!(Genre)),
+        genres: get_tag_str_array(&std_tags, tag_separators, tag_str_matcher!(Genre)),
     };
 
     if tags.album_artists.is_empty() {
@@ -134,13 +138,14 @@ fn get_tag_str(
 ///
 /// # Behavior
 ///
-/// * Values are split by `;` `,` and newlines
+/// * Values are split by the provided separators and newlines
 /// * Values are trimmed
 /// * Empty values are ignored
 /// * If the tag is provided multiple times, values are all combined into a single array.
 /// * Duplicate values are removed.
 fn get_tag_str_array(
     standard_tags: &[&StandardTag],
+    separators: &[String],
     matcher: impl Fn(&&StandardTag) -> Option<String>,
 ) -> Vec<String> {
     let mut already_seen = HashSet::new();
@@ -149,7 +154,7 @@ fn get_tag_str_array(
     for value in standard_tags.iter().filter_map(matcher) {
         for part in value
             .lines()
-            .flat_map(|line| line.split(&[';', ',']))
+            .flat_map(|line| split_by_any(line, separators))
             .map(str::trim)
             .filter(|entry| !entry.is_empty())
         {
@@ -162,6 +167,16 @@ fn get_tag_str_array(
     values
 }
 
+/// Split a string by all of the provided separators
+fn split_by_any<'a>(value: &'a str, separators: &[String]) -> Vec<&'a str> {
+    separators.iter().fold(vec![value], |parts, separator| {
+        parts
+            .into_iter()
+            .flat_map(|part| part.split(separator.as_str()))
+            .collect()
+    })
+}
+
 /// Find the provided integer tag
 ///
 /// # Errors
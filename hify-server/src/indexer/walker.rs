@@ -1,9 +1,12 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::{Context, Result};
 use log::warn;
 
-use crate::{index::TrackMetadata, utils::TaskRunner};
+use crate::{index::TrackMetadata, userdata::PlaylistFileFormat, utils::TaskRunner};
 
 use super::{analyzer::analyze_file, tags::TrackStrTags};
 
@@ -11,14 +14,18 @@ use super::{analyzer::analyze_file, tags::TrackStrTags};
 pub fn analyze_audio_files(
     files: impl Iterator<Item = PathBuf>,
     dir: &Path,
+    tag_separators: &[String],
 ) -> Result<Vec<(PathBuf, (TrackMetadata, TrackStrTags))>> {
     let mut tasks = TaskRunner::new();
 
+    let tag_separators = Arc::<[String]>::from(tag_separators);
+
     for file in files {
         let dir = dir.to_owned();
+        let tag_separators = Arc::clone(&tag_separators);
 
         tasks.spawn(move || {
-            analyze_file(&dir.join(&file))
+            analyze_file(&dir.join(&file), &tag_separators)
                 .with_context(|| {
                     format!("Failed to analyze audio file at path: {}", file.display())
                 })
@@ -30,7 +37,7 @@ pub fn analyze_audio_files(
 }
 
 /// Determine if a file may be an audio file based on its extension
-pub fn may_be_audio_file(path: impl AsRef<Path>) -> bool {
+pub fn may_be_audio_file(path: impl AsRef<Path>, audio_extensions: &[String]) -> bool {
     let path = path.as_ref();
 
     let audio_ext = match path.extension().and_then(|ext| ext.to_str()) {
@@ -38,6 +45,13 @@ pub fn may_be_audio_file(path: impl AsRef<Path>) -> bool {
         None => return false,
     };
 
+    if audio_extensions
+        .iter()
+        .any(|ext| ext.eq_ignore_ascii_case(&audio_ext))
+    {
+        return true;
+    }
+
     if matches!(
         audio_ext.as_str(),
         "mpeg" | "mp4" | "webm" | "alac" | "aiff" | "dsf"
@@ -46,8 +60,6 @@ pub fn may_be_audio_file(path: impl AsRef<Path>) -> bool {
             "Warning: in file '{}': file format unsupported by web players: {audio_ext}",
             path.to_string_lossy()
         );
-
-        return false;
     }
 
     if matches!(audio_ext.as_str(), "aac" | "wav") {
@@ -55,9 +67,15 @@ pub fn may_be_audio_file(path: impl AsRef<Path>) -> bool {
             "Warning: in file '{}': file format explicitly unsupported: {audio_ext}",
             path.to_string_lossy()
         );
-
-        return false;
     }
 
-    matches!(audio_ext.as_str(), "mp3" | "flac" | "m4a" | "ogg" | "opus")
+    false
+}
+
+/// Determine if a file is a playlist that can be imported, based on its extension
+pub fn is_playlist_file(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| PlaylistFileFormat::from_extension(ext).is_some())
 }
@@ -40,16 +40,28 @@ mod indexer;
 mod logger;
 mod manager;
 mod server;
+mod settings;
+mod userdata;
 mod utils;
 
-use std::process::ExitCode;
+use std::{path::Path, process::ExitCode};
 
 use anyhow::{Context, Result, bail};
 use clap::Parser;
-use log::{error, warn};
+use colored::Colorize;
+use log::{error, info, warn};
 use tokio::{fs, task::spawn_blocking};
 
-use self::{cmd::CmdArgs, logger::Logger, manager::DataManager};
+use self::{
+    arts::ArtSettings,
+    cmd::{CmdArgs, Command, UsersCommand},
+    logger::Logger,
+    manager::{DataManager, load_users, write_users},
+    server::{TlsSettings, UnixSocketSettings},
+    settings::Settings,
+    userdata::{User, UserRole},
+    utils::set_default_max_concurrent,
+};
 
 #[tokio::main]
 async fn main() -> ExitCode {
@@ -67,16 +79,49 @@ async fn main() -> ExitCode {
     }
 }
 
+#[allow(clippy::too_many_lines)]
 async fn inner_main(args: CmdArgs) -> Result<()> {
     let CmdArgs {
+        command,
         music_dir,
         data_dir,
         verbosity: _,
         just_update_index,
         addr,
         port,
+        genres_art_dir,
+        cover_stems,
+        only_cover_stems,
+        resized_arts_cache_mib,
+        art_quality,
+        art_sizes,
+        no_auto_playlists,
+        import_playlists,
+        cors_origins,
+        base_path,
+        trust_forwarded_headers,
+        max_concurrent_tasks,
+        tls_cert,
+        tls_key,
+        http_redirect_port,
+        unix_socket,
+        unix_socket_permissions,
     } = args;
 
+    if let Some(Command::Users(command)) = command {
+        if !fs::try_exists(&data_dir).await.is_ok_and(|b| b) {
+            fs::create_dir_all(&data_dir).await.with_context(|| {
+                format!("Failed to create data directory '{}'", data_dir.display())
+            })?;
+        }
+
+        return spawn_blocking(move || manage_users(&data_dir, command))
+            .await
+            .unwrap();
+    }
+
+    let music_dir = music_dir.context("No music directory was provided")?;
+
     if !fs::try_exists(&music_dir).await.is_ok_and(|b| b) {
         bail!(
             "Music directory '{}' is not a valid directory",
@@ -90,7 +135,140 @@ async fn inner_main(args: CmdArgs) -> Result<()> {
             .with_context(|| format!("Failed to create data directory '{}'", data_dir.display()))?;
     }
 
-    let data_manager = spawn_blocking(move || DataManager::load(&data_dir, music_dir))
+    let mut settings = Settings::load(&data_dir)?;
+
+    // Apply command-line overrides
+    if let Some(addr) = addr {
+        settings.server.addr = addr;
+    }
+
+    if let Some(port) = port {
+        settings.server.port = port;
+    }
+
+    if no_auto_playlists {
+        settings.server.auto_playlists = false;
+    }
+
+    if let Some(cors_origins) = cors_origins {
+        settings.server.cors_origins = cors_origins;
+    }
+
+    if let Some(base_path) = base_path {
+        settings.server.base_path = base_path;
+    }
+
+    if trust_forwarded_headers {
+        settings.server.trust_forwarded_headers = true;
+    }
+
+    match (tls_cert, tls_key) {
+        (Some(cert_path), Some(key_path)) => {
+            settings.server.tls = Some(TlsSettings {
+                cert_path,
+                key_path,
+                http_redirect_port: settings
+                    .server
+                    .tls
+                    .as_ref()
+                    .and_then(|tls| tls.http_redirect_port),
+            });
+        }
+
+        (None, None) => {}
+
+        (Some(_), None) | (None, Some(_)) => {
+            bail!("Serving HTTPS requires both a TLS certificate and a private key")
+        }
+    }
+
+    if let Some(http_redirect_port) = http_redirect_port {
+        settings
+            .server
+            .tls
+            .as_mut()
+            .context("Redirecting HTTP requests requires TLS to be enabled")?
+            .http_redirect_port = Some(http_redirect_port);
+    }
+
+    if let Some(path) = unix_socket {
+        settings.server.unix_socket = Some(UnixSocketSettings {
+            path,
+            permissions: settings
+                .server
+                .unix_socket
+                .as_ref()
+                .and_then(|unix_socket| unix_socket.permissions),
+        });
+    }
+
+    if let Some(permissions) = unix_socket_permissions {
+        settings
+            .server
+            .unix_socket
+            .as_mut()
+            .context("Setting socket permissions requires listening on a Unix socket")?
+            .permissions = Some(permissions);
+    }
+
+    if import_playlists {
+        settings.indexer.import_playlists = true;
+    }
+
+    if let Some(max_concurrent_tasks) = max_concurrent_tasks {
+        settings.max_concurrent_tasks = max_concurrent_tasks;
+    }
+
+    if let Some(genres_art_dir) = genres_art_dir {
+        settings.genres_art_dir = Some(genres_art_dir);
+    }
+
+    if let Some(cover_stems) = cover_stems {
+        settings.covers.stems = cover_stems;
+    }
+
+    if only_cover_stems {
+        settings.covers.stems_only = true;
+    }
+
+    if let Some(resized_arts_cache_mib) = resized_arts_cache_mib {
+        settings.resized_arts_cache_mib = resized_arts_cache_mib;
+    }
+
+    if let Some(art_quality) = art_quality {
+        settings.arts.quality = art_quality;
+    }
+
+    match art_sizes.as_deref() {
+        None => {}
+
+        Some(&[large_side_px, medium_side_px, small_side_px, tiny_side_px]) => {
+            settings.arts = ArtSettings {
+                large_side_px,
+                medium_side_px,
+                small_side_px,
+                tiny_side_px,
+                ..settings.arts
+            };
+        }
+
+        Some(_) => bail!("Exactly four art sizes must be provided"),
+    }
+
+    settings.validate().context("Invalid settings")?;
+
+    if let Some(genres_art_dir) = &settings.genres_art_dir
+        && !fs::try_exists(genres_art_dir).await.is_ok_and(|b| b)
+    {
+        bail!(
+            "Genres art directory '{}' is not a valid directory",
+            genres_art_dir.display()
+        );
+    }
+
+    set_default_max_concurrent(settings.max_concurrent_tasks);
+
+    let data_manager = spawn_blocking(move || DataManager::load(&data_dir, music_dir, settings))
         .await
         .unwrap()?;
 
@@ -104,5 +282,82 @@ async fn inner_main(args: CmdArgs) -> Result<()> {
         return Ok(());
     }
 
-    server::launch((addr, port).into(), data_manager).await
+    server::launch(data_manager).await
+}
+
+fn manage_users(data_dir: &Path, command: UsersCommand) -> Result<()> {
+    let mut users = load_users(data_dir)?;
+
+    match command {
+        UsersCommand::List => {
+            for user in users.values() {
+                let role = match user.role {
+                    UserRole::Admin => "admin",
+                    UserRole::Listener => "listener",
+                };
+
+                info!("{} ({role})", user.name.bright_green());
+            }
+        }
+
+        UsersCommand::Create { name, admin } => {
+            let role = if admin {
+                UserRole::Admin
+            } else {
+                UserRole::Listener
+            };
+
+            let user = User::new(&name, role)?;
+
+            if users.values().any(|other| other.name == user.name) {
+                bail!("User '{}' already exists", user.name);
+            }
+
+            info!(
+                "Created user '{}' with password '{}' and API key '{}'",
+                user.name.bright_green(),
+                user.password.bright_yellow(),
+                user.api_key.bright_yellow()
+            );
+
+            users.insert(user.id, user);
+            write_users(data_dir, &users)?;
+        }
+
+        UsersCommand::ResetPassword { name } => {
+            let user = users
+                .values_mut()
+                .find(|user| user.name == name)
+                .with_context(|| format!("User '{name}' was not found"))?;
+
+            user.reset_password();
+
+            info!(
+                "New password of user '{}' is '{}'",
+                user.name.bright_green(),
+                user.password.bright_yellow()
+            );
+
+            write_users(data_dir, &users)?;
+        }
+
+        UsersCommand::ResetApiKey { name } => {
+            let user = users
+                .values_mut()
+                .find(|user| user.name == name)
+                .with_context(|| format!("User '{name}' was not found"))?;
+
+            user.reset_api_key();
+
+            info!(
+                "New API key of user '{}' is '{}'",
+                user.name.bright_green(),
+                user.api_key.bright_yellow()
+            );
+
+            write_users(data_dir, &users)?;
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,982 @@
+mod users;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Instant,
+};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use colored::Colorize;
+use log::{debug, info, trace, warn};
+use tokio::{
+    sync::{RwLock, RwLockReadGuard},
+    task::spawn_blocking,
+};
+
+use crate::{
+    arts::{
+        ArtColors, ArtFormat, ArtSettings, ArtSize, ArtSources, ArtsGarbageReport, ArtsManager,
+        RequestedArtSize, ResizedArtsCache, generate_album_arts, generate_artists_art,
+        generate_genres_art, generate_track_arts,
+    },
+    index::{
+        AlbumID, ArtistID, GenreID, INDEX_FORMAT_VERSION, IdType, Index, IndexCache, Track,
+        TrackID, assert_index_correctness,
+    },
+    indexer::{self, LibraryAnalysis},
+    settings::Settings,
+    userdata::{
+        DeviceToken, DeviceTokens, Playlist, PlaylistFile, PlaylistFileFormat, PlaylistID,
+        PlaylistSource, User, UserID, Users,
+    },
+};
+
+pub use self::users::{UserDataManager, load_users, write_users};
+
+use self::users::{UserData, load_device_tokens, user_dir, write_private_file};
+
+pub struct DataManager {
+    music_dir: PathBuf,
+    settings: Settings,
+
+    index_path: PathBuf,
+    index: RwLock<Index>,
+    index_cache: RwLock<IndexCache>,
+    index_update_barrier: Mutex<()>,
+
+    users: Users,
+    users_data: HashMap<UserID, UserData>,
+
+    device_tokens_path: PathBuf,
+    device_tokens: RwLock<DeviceTokens>,
+
+    album_arts: ArtsManager<AlbumID>,
+    artist_arts: ArtsManager<ArtistID>,
+    genre_arts: ArtsManager<GenreID>,
+    track_arts: ArtsManager<TrackID>,
+    playlist_arts: ArtsManager<PlaylistID>,
+    resized_arts: ResizedArtsCache,
+
+    /// Sources found during the last arts generation, to regenerate single arts without
+    /// looking through the whole music directory
+    art_sources: Mutex<Option<ArtSources>>,
+}
+
+impl DataManager {
+    // TODO: rename to 'load_blocking'?
+    #[allow(clippy::too_many_lines)]
+    pub fn load(data_dir: &Path, music_dir: PathBuf, settings: Settings) -> Result<Self> {
+        info!("Starting up...");
+
+        ensure!(
+            music_dir.is_dir(),
+            "Provided music directory is not a directory"
+        );
+
+        // Absolute paths are required to resolve and write paths in playlist files
+        let music_dir =
+            fs::canonicalize(music_dir).context("Failed to resolve the music directory's path")?;
+
+        if data_dir.exists() {
+            ensure!(
+                data_dir.is_dir(),
+                "Provided data directory is not a directory"
+            );
+        } else {
+            fs::create_dir_all(data_dir).context("Failed to create the data directory")?;
+        }
+
+        let index_path = data_dir.join("index.json");
+
+        let index = if index_path.exists() {
+            info!("> Loading library file...");
+
+            let library_str =
+                fs::read_to_string(&index_path).context("Failed to read library file")?;
+
+            serde_json::from_str::<Index>(&library_str).context("Failed to parse library file")?
+        } else {
+            info!("> No library file found");
+
+            Index::default()
+        };
+
+        assert_index_correctness(&index);
+
+        let users = load_users(data_dir)?;
+
+        let users_data = users
+            .values()
+            .map(|user| {
+                debug!("> Loading data of user '{}'...", user.name);
+
+                UserData::load(&user_dir(data_dir, user.id))
+                    .with_context(|| format!("Failed to load data of user '{}'", user.name))
+                    .map(|user_data| (user.id, user_data))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        let device_tokens_path = data_dir.join("device_tokens.json");
+        let device_tokens = load_device_tokens(&device_tokens_path)?;
+
+        debug!("> Building index cache...");
+        let index_cache = IndexCache::build(&index)?;
+
+        info!("> Successfully loaded all data!");
+
+        let generated_dir = data_dir.join("generated");
+
+        if !generated_dir.exists() {
+            fs::create_dir(&generated_dir).context("Failed to create data generation directory")?;
+        }
+
+        let generated_arts_dir = generated_dir.join("arts");
+
+        if !generated_arts_dir.exists() {
+            fs::create_dir(&generated_arts_dir)
+                .context("Failed to create arts generation directory")?;
+        }
+
+        // Overrides are user data, so they are not stored alongside generated files
+        let art_overrides_dir = data_dir.join("overrides").join("arts");
+
+        Ok(Self {
+            music_dir,
+
+            index_path,
+            index: RwLock::new(index),
+            index_cache: RwLock::new(index_cache),
+            index_update_barrier: Mutex::new(()),
+
+            users,
+            users_data,
+
+            device_tokens_path,
+            device_tokens: RwLock::new(device_tokens),
+
+            // TODO: check if some arts are missing
+            album_arts: ArtsManager::open(
+                generated_arts_dir.join("albums"),
+                art_overrides_dir.join("albums"),
+                settings.arts,
+            )?,
+            artist_arts: ArtsManager::open(
+                generated_arts_dir.join("artists"),
+                art_overrides_dir.join("artists"),
+                settings.arts,
+            )?,
+            genre_arts: ArtsManager::open(
+                generated_arts_dir.join("genres"),
+                art_overrides_dir.join("genres"),
+                settings.arts,
+            )?,
+            track_arts: ArtsManager::open(
+                generated_arts_dir.join("tracks"),
+                art_overrides_dir.join("tracks"),
+                settings.arts,
+            )?,
+            playlist_arts: ArtsManager::open(
+                generated_arts_dir.join("playlists"),
+                art_overrides_dir.join("playlists"),
+                settings.arts,
+            )?,
+            resized_arts: ResizedArtsCache::open(
+                generated_arts_dir.join("resized"),
+                settings.resized_arts_cache_mib * 1024 * 1024,
+            )?,
+
+            art_sources: Mutex::new(None),
+
+            settings,
+        })
+    }
+
+    pub fn music_dir(&self) -> &Path {
+        &self.music_dir
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn art_settings(&self) -> &ArtSettings {
+        &self.settings.arts
+    }
+
+    // TODO: warn if dangling ratings
+    // TODO: rename to 'update_index_blocking'?
+    pub fn update_index(&self) -> Result<()> {
+        let _permit = self
+            .index_update_barrier
+            .try_lock()
+            .map_err(|_| anyhow!("An index update is already pending"))?;
+
+        let start = Instant::now();
+
+        info!("Updating index...");
+
+        // Indexes generated with an older format lack some data, and tracks analyzed with
+        // different settings may have been split differently, so all tracks are analyzed again
+        let (outdated_format, outdated_settings) = {
+            let index = self.index.blocking_read();
+
+            (
+                index.format_version < INDEX_FORMAT_VERSION,
+                index.settings_hash != self.settings.indexer.analysis_hash(),
+            )
+        };
+
+        if outdated_format {
+            info!("-> Index format is outdated, all tracks will be analyzed again");
+        } else if outdated_settings {
+            info!("-> Indexer settings changed, all tracks will be analyzed again");
+        }
+
+        let analyze_all = outdated_format || outdated_settings;
+
+        let index_cache = self.index_cache.blocking_read();
+
+        let LibraryAnalysis {
+            index: new_index,
+            playlist_files,
+        } = indexer::analyze_tracks_in(
+            &self.music_dir,
+            (!analyze_all).then_some(&*index_cache),
+            &self.settings.indexer,
+        )
+        .context("Failed to analyze tracks")?;
+
+        drop(index_cache);
+
+        let index_updated = new_index.is_some();
+
+        let index = new_index.unwrap_or_else(|| self.index.blocking_read().clone());
+        let index_cache = IndexCache::build(&index)?;
+
+        if index_updated {
+            assert_index_correctness(&index);
+
+            info!("--> Serializing...");
+
+            let index_str = serde_json::to_string_pretty(&index)
+                .context("Failed to serialize index")
+                .unwrap();
+
+            info!("--> Writing to disk...");
+
+            fs::write(&self.index_path, index_str).context("Failed to write index file")?;
+
+            trace!("-> Updating memory...");
+
+            *self.index_cache.blocking_write() = index_cache.clone();
+            *self.index.blocking_write() = index.clone();
+
+            self.refresh_playlists_snapshots(&index_cache)?;
+        }
+
+        if self.settings.indexer.import_playlists {
+            self.import_discovered_playlists(&playlist_files, &index_cache)?;
+        }
+
+        self.generate_arts(&index_cache)?;
+
+        if index_updated {
+            info!(
+                "-> Index was successfully updated, took {}",
+                format!(
+                    "{}m {}s",
+                    start.elapsed().as_secs() / 60,
+                    start.elapsed().as_secs() % 60
+                )
+                .bright_yellow()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Generate the arts that are missing or outdated, then remove orphaned ones
+    fn generate_arts(&self, index_cache: &IndexCache) -> Result<()> {
+        let sources = ArtSources::find(
+            index_cache,
+            &self.music_dir,
+            &self.settings.covers,
+            self.settings.genres_art_dir.as_deref(),
+        )?;
+
+        generate_album_arts(
+            index_cache,
+            &self.music_dir,
+            &sources.album_covers,
+            &self.album_arts,
+            None,
+        )?;
+
+        generate_artists_art(
+            index_cache,
+            &sources,
+            &self.album_arts,
+            &self.artist_arts,
+            None,
+        )?;
+
+        generate_genres_art(
+            index_cache,
+            &sources,
+            &self.album_arts,
+            &self.genre_arts,
+            None,
+        )?;
+
+        generate_track_arts(index_cache, &self.music_dir, &self.track_arts, None)?;
+
+        self.collect_arts_garbage_with(index_cache)?;
+
+        *self.art_sources.lock().unwrap() = Some(sources);
+
+        Ok(())
+    }
+
+    /// Wait for any pending index update to complete
+    pub fn wait_for_index_update(&self) {
+        let _permit = self.index_update_barrier.lock().unwrap();
+    }
+
+    /// Regenerate an entity's art after its override changed, alongside the mosaics made of it
+    fn regenerate_art(&self, entity: Entity) -> Result<()> {
+        // Wait for any pending index update to complete, as it generates arts as well
+        let _permit = self.index_update_barrier.lock().unwrap();
+
+        let index_cache = self.index_cache.blocking_read();
+
+        let art_sources = self.art_sources.lock().unwrap();
+
+        // Sources are only known once arts were generated since the server started
+        let Some(sources) = art_sources.as_ref() else {
+            drop(art_sources);
+            return self.generate_arts(&index_cache);
+        };
+
+        match entity {
+            Entity::Album(album_id) => {
+                generate_album_arts(
+                    &index_cache,
+                    &self.music_dir,
+                    &sources.album_covers,
+                    &self.album_arts,
+                    Some(album_id),
+                )?;
+
+                // Only the mosaics the album is part of will see their source change
+                generate_artists_art(
+                    &index_cache,
+                    sources,
+                    &self.album_arts,
+                    &self.artist_arts,
+                    None,
+                )?;
+
+                generate_genres_art(
+                    &index_cache,
+                    sources,
+                    &self.album_arts,
+                    &self.genre_arts,
+                    None,
+                )
+            }
+
+            Entity::Artist(artist_id) => generate_artists_art(
+                &index_cache,
+                sources,
+                &self.album_arts,
+                &self.artist_arts,
+                Some(artist_id),
+            ),
+
+            Entity::Genre(genre_id) => generate_genres_art(
+                &index_cache,
+                sources,
+                &self.album_arts,
+                &self.genre_arts,
+                Some(genre_id),
+            ),
+
+            Entity::Track(track_id) => generate_track_arts(
+                &index_cache,
+                &self.music_dir,
+                &self.track_arts,
+                Some(track_id),
+            ),
+
+            Entity::Playlist(_) => bail!("Playlist covers are not overrides"),
+        }
+    }
+
+    fn ensure_entity_exists(&self, entity: Entity) -> Result<()> {
+        let index = self.index_cache.blocking_read();
+
+        let exists = match entity {
+            Entity::Artist(artist_id) => index.artists.contains_key(&artist_id),
+            Entity::Album(album_id) => index.albums.contains_key(&album_id),
+            Entity::Genre(genre_id) => index.genres.contains_key(&genre_id),
+            Entity::Track(track_id) => index.tracks.contains_key(&track_id),
+            Entity::Playlist(playlist_id) => self.users_data.values().any(|user_data| {
+                user_data
+                    .playlists
+                    .blocking_read()
+                    .contains_key(&playlist_id)
+            }),
+        };
+
+        ensure!(exists, "Provided {} was not found", entity.kind());
+
+        Ok(())
+    }
+
+    /// Replace an art with an uploaded image
+    pub fn set_art_override_image(&self, entity: Entity, data: &[u8]) -> Result<()> {
+        self.ensure_entity_exists(entity)?;
+
+        match entity {
+            Entity::Artist(artist_id) => self.artist_arts.overrides().set_image(artist_id, data),
+            Entity::Album(album_id) => self.album_arts.overrides().set_image(album_id, data),
+            Entity::Genre(genre_id) => self.genre_arts.overrides().set_image(genre_id, data),
+            Entity::Track(track_id) => self.track_arts.overrides().set_image(track_id, data),
+            Entity::Playlist(_) => bail!("Playlist covers are not overrides"),
+        }?;
+
+        self.regenerate_art(entity)
+    }
+
+    /// Pin the albums an artist's or genre's mosaic is made of
+    pub fn set_art_override_mosaic(&self, entity: Entity, albums: Vec<AlbumID>) -> Result<()> {
+        self.ensure_entity_exists(entity)?;
+
+        {
+            let index = self.index_cache.blocking_read();
+
+            if let Some(album_id) = albums
+                .iter()
+                .find(|album_id| !index.albums.contains_key(*album_id))
+            {
+                bail!("Provided album {} was not found", album_id.encode());
+            }
+        }
+
+        match entity {
+            Entity::Artist(artist_id) => self.artist_arts.overrides().set_mosaic(artist_id, albums),
+            Entity::Album(_) | Entity::Track(_) | Entity::Playlist(_) => {
+                bail!("Only artist and genre arts can be mosaics")
+            }
+            Entity::Genre(genre_id) => self.genre_arts.overrides().set_mosaic(genre_id, albums),
+        }?;
+
+        self.regenerate_art(entity)
+    }
+
+    /// Remove an art's override, reverting to the generated art
+    pub fn remove_art_override(&self, entity: Entity) -> Result<()> {
+        self.ensure_entity_exists(entity)?;
+
+        let removed = match entity {
+            Entity::Artist(artist_id) => self.artist_arts.overrides().remove(artist_id),
+            Entity::Album(album_id) => self.album_arts.overrides().remove(album_id),
+            Entity::Genre(genre_id) => self.genre_arts.overrides().remove(genre_id),
+            Entity::Track(track_id) => self.track_arts.overrides().remove(track_id),
+            Entity::Playlist(_) => bail!("Playlist covers are not overrides"),
+        }?;
+
+        ensure!(removed, "Provided {} has no art override", entity.kind());
+
+        self.regenerate_art(entity)
+    }
+
+    /// Remove generated arts for items that are not in the index anymore
+    pub fn collect_arts_garbage(&self) -> Result<ArtsGarbageReport> {
+        let _permit = self
+            .index_update_barrier
+            .try_lock()
+            .map_err(|_| anyhow!("An index update is pending"))?;
+
+        self.collect_arts_garbage_with(&self.index_cache.blocking_read())
+    }
+
+    fn collect_arts_garbage_with(&self, index_cache: &IndexCache) -> Result<ArtsGarbageReport> {
+        debug!("-> Collecting orphaned arts...");
+
+        let mut report = ArtsGarbageReport::default();
+
+        report += self
+            .album_arts
+            .collect_garbage(|album_id| index_cache.albums.contains_key(&album_id))?;
+
+        report += self
+            .artist_arts
+            .collect_garbage(|artist_id| index_cache.artists.contains_key(&artist_id))?;
+
+        report += self
+            .genre_arts
+            .collect_garbage(|genre_id| index_cache.genres.contains_key(&genre_id))?;
+
+        report += self
+            .track_arts
+            .collect_garbage(|track_id| index_cache.tracks.contains_key(&track_id))?;
+
+        {
+            // Playlists of all users
+            let playlists_ids = self
+                .users_data
+                .values()
+                .flat_map(|user_data| {
+                    user_data
+                        .playlists
+                        .blocking_read()
+                        .keys()
+                        .copied()
+                        .collect::<Vec<_>>()
+                })
+                .collect::<HashSet<_>>();
+
+            report += self
+                .playlist_arts
+                .collect_garbage(|playlist_id| playlists_ids.contains(&playlist_id))?;
+        }
+
+        let live_keys = ResizedArtsCache::live_keys(&self.album_arts)
+            .chain(ResizedArtsCache::live_keys(&self.artist_arts))
+            .chain(ResizedArtsCache::live_keys(&self.genre_arts))
+            .chain(ResizedArtsCache::live_keys(&self.track_arts))
+            .chain(ResizedArtsCache::live_keys(&self.playlist_arts))
+            .collect::<HashSet<_>>();
+
+        report += self.resized_arts.collect_garbage(&live_keys);
+
+        let ArtsGarbageReport {
+            removed_arts,
+            removed_resized_arts,
+            reclaimed_bytes,
+        } = report;
+
+        if removed_arts > 0 || removed_resized_arts > 0 {
+            info!(
+                "-> Removed {} orphaned arts and {} resized arts, reclaimed {}",
+                removed_arts.to_string().bright_yellow(),
+                removed_resized_arts.to_string().bright_yellow(),
+                format!("{:.1} MiB", bytes_to_mib(reclaimed_bytes)).bright_yellow()
+            );
+        }
+
+        Ok(report)
+    }
+
+    pub fn get_art(&self, entity: Entity, size: ArtSize) -> Result<PathBuf> {
+        match entity {
+            Entity::Artist(artist_id) => self.artist_arts.get_art_path(artist_id, size),
+            Entity::Album(album_id) => self.album_arts.get_art_path(album_id, size),
+            Entity::Genre(genre_id) => self.genre_arts.get_art_path(genre_id, size),
+            Entity::Track(track_id) => self.track_arts.get_art_path(track_id, size),
+            Entity::Playlist(playlist_id) => self.playlist_arts.get_art_path(playlist_id, size),
+        }
+    }
+
+    /// Get the entity whose art should be displayed for a track
+    ///
+    /// Tracks only have their own art if their embedded art differs from their album's.
+    pub fn track_art_entity(&self, track: &Track) -> Entity {
+        if self.track_arts.has(track.id) {
+            Entity::Track(track.id)
+        } else {
+            Entity::Album(track.tags.album_id)
+        }
+    }
+
+    /// Get the entity whose art should be displayed for a playlist
+    ///
+    /// Playlists without a cover use the art of their first track that is still in the index.
+    pub fn playlist_art_entity(&self, playlist: &Playlist, index: &IndexCache) -> Option<Entity> {
+        if self.playlist_arts.has(playlist.id) {
+            return Some(Entity::Playlist(playlist.id));
+        }
+
+        playlist
+            .entries
+            .iter()
+            .find_map(|entry| index.tracks.get(&entry.track_id))
+            .map(|track| self.track_art_entity(track))
+    }
+
+    pub fn get_art_colors(&self, entity: Entity) -> Option<ArtColors> {
+        match entity {
+            Entity::Artist(artist_id) => self.artist_arts.get_art_colors(artist_id),
+            Entity::Album(album_id) => self.album_arts.get_art_colors(album_id),
+            Entity::Genre(genre_id) => self.genre_arts.get_art_colors(genre_id),
+            Entity::Track(track_id) => self.track_arts.get_art_colors(track_id),
+            Entity::Playlist(playlist_id) => self.playlist_arts.get_art_colors(playlist_id),
+        }
+    }
+
+    /// Get an art with any size and format, rendering it on demand if it isn't a pre-generated one
+    pub async fn get_art_with_size(
+        &self,
+        entity: Entity,
+        size: RequestedArtSize,
+        format: ArtFormat,
+    ) -> Result<PathBuf> {
+        let side_px = match (size.normalize(&self.settings.arts), format) {
+            (RequestedArtSize::Preset(size), ArtFormat::Webp) => return self.get_art(entity, size),
+            (RequestedArtSize::Preset(size), _) => self.settings.arts.side_px(size),
+            (RequestedArtSize::Px(side_px), _) => ResizedArtsCache::round_side_px(side_px),
+        };
+
+        let resized_arts = self.resized_arts.clone();
+
+        match entity {
+            Entity::Artist(artist_id) => {
+                render_resized_art(
+                    resized_arts,
+                    self.artist_arts.clone(),
+                    artist_id,
+                    side_px,
+                    format,
+                )
+                .await
+            }
+            Entity::Album(album_id) => {
+                render_resized_art(
+                    resized_arts,
+                    self.album_arts.clone(),
+                    album_id,
+                    side_px,
+                    format,
+                )
+                .await
+            }
+            Entity::Genre(genre_id) => {
+                render_resized_art(
+                    resized_arts,
+                    self.genre_arts.clone(),
+                    genre_id,
+                    side_px,
+                    format,
+                )
+                .await
+            }
+            Entity::Track(track_id) => {
+                render_resized_art(
+                    resized_arts,
+                    self.track_arts.clone(),
+                    track_id,
+                    side_px,
+                    format,
+                )
+                .await
+            }
+            Entity::Playlist(playlist_id) => {
+                render_resized_art(
+                    resized_arts,
+                    self.playlist_arts.clone(),
+                    playlist_id,
+                    side_px,
+                    format,
+                )
+                .await
+            }
+        }
+    }
+
+    //
+    // Async-friendly getters
+    //
+
+    pub async fn index(&self) -> RwLockReadGuard<'_, IndexCache> {
+        self.index_cache.read().await
+    }
+
+    pub fn users(&self) -> &Users {
+        &self.users
+    }
+
+    pub fn find_user(&self, name: &str) -> Option<&User> {
+        self.users.values().find(|user| user.name == name)
+    }
+
+    /// Access the data manager on behalf of a user
+    ///
+    /// Accounts are only managed while the server is stopped, so the provided user must exist.
+    pub fn user_data(&self, user_id: UserID) -> UserDataManager<'_> {
+        UserDataManager::new(
+            self,
+            self.users.get(&user_id).unwrap(),
+            self.users_data.get(&user_id).unwrap(),
+        )
+    }
+
+    /// Find the user a device token belongs to
+    pub async fn find_device_token_user(&self, token: &str) -> Option<&User> {
+        let user_id = self.device_tokens.read().await.get(token)?.user_id;
+        self.users.get(&user_id)
+    }
+
+    /// Create a long-lived token for one of a user's devices, and return its value
+    pub async fn create_device_token(
+        &self,
+        user_id: UserID,
+        device_name: Option<String>,
+    ) -> Result<String> {
+        self.update_device_tokens(|device_tokens| {
+            let (token, device_token) = DeviceToken::generate(user_id, device_name);
+            device_tokens.insert(token.clone(), device_token);
+            token
+        })
+        .await
+    }
+
+    pub async fn revoke_device_token(&self, token: &str) -> Result<()> {
+        self.update_device_tokens(|device_tokens| {
+            device_tokens.shift_remove(token);
+        })
+        .await
+    }
+
+    /// Apply changes to the device tokens, then persist them
+    async fn update_device_tokens<T>(
+        &self,
+        update: impl FnOnce(&mut DeviceTokens) -> T,
+    ) -> Result<T> {
+        let mut device_tokens = self.device_tokens.write().await;
+
+        let result = update(&mut device_tokens);
+
+        let device_tokens_str =
+            serde_json::to_string(&*device_tokens).context("Failed to serialize device tokens")?;
+
+        drop(device_tokens);
+
+        write_private_file(&self.device_tokens_path, &device_tokens_str)
+            .context("Failed to write device tokens file")?;
+
+        trace!(
+            "> Wrote to device tokens file (~ {} Kb)",
+            device_tokens_str.len() / 1024
+        );
+
+        Ok(result)
+    }
+
+    /// Import the playlist files found in the music directory into the owner's playlists
+    ///
+    /// Files that were already imported are skipped, unless they were modified since.
+    /// Files whose playlist was deleted by the owner are never imported again.
+    fn import_discovered_playlists(
+        &self,
+        playlist_files: &[PathBuf],
+        index_cache: &IndexCache,
+    ) -> Result<()> {
+        let (owner_id, _) = self.users.first().unwrap();
+        let owner_data = self.users_data.get(owner_id).unwrap();
+
+        let imported_mtimes = owner_data
+            .playlists
+            .blocking_read()
+            .values()
+            .filter_map(|playlist| playlist.source.as_ref())
+            .map(|source| (source.relative_path.clone(), source.mtime))
+            .collect::<HashMap<_, _>>();
+
+        let ignored_playlist_files = owner_data.ignored_playlist_files.blocking_read().clone();
+
+        // Parse the files first to avoid holding the lock across filesystem accesses
+        let mut parsed = vec![];
+
+        for relative_path in playlist_files {
+            if ignored_playlist_files.contains(relative_path) {
+                continue;
+            }
+
+            let path = self.music_dir.join(relative_path);
+
+            let mtime = match fs::metadata(&path).and_then(|mt| mt.modified()) {
+                Ok(mtime) => mtime,
+                Err(err) => {
+                    warn!(
+                        "Failed to get metadata of playlist file '{}': {err}",
+                        relative_path.display()
+                    );
+
+                    continue;
+                }
+            };
+
+            if imported_mtimes.get(relative_path) == Some(&mtime) {
+                continue;
+            }
+
+            let (name, track_ids) = match self.read_discovered_playlist(relative_path, index_cache)
+            {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    warn!(
+                        "Failed to import playlist file '{}': {err:?}",
+                        relative_path.display()
+                    );
+
+                    continue;
+                }
+            };
+
+            let source = PlaylistSource {
+                relative_path: relative_path.clone(),
+                mtime,
+            };
+
+            parsed.push((source, name, track_ids));
+        }
+
+        if parsed.is_empty() {
+            return Ok(());
+        }
+
+        let mut playlists = owner_data.playlists.blocking_write();
+
+        for (source, name, track_ids) in &parsed {
+            let existing = playlists.values_mut().find(|playlist| {
+                playlist
+                    .source
+                    .as_ref()
+                    .is_some_and(|existing| existing.relative_path == source.relative_path)
+            });
+
+            if let Some(playlist) = existing {
+                playlist.replace_tracks(track_ids, index_cache)?;
+                playlist.source = Some(source.clone());
+            } else {
+                let mut playlist = Playlist::new(name, None)?;
+                playlist.replace_tracks(track_ids, index_cache)?;
+                playlist.source = Some(source.clone());
+
+                playlists.insert(playlist.id, playlist);
+            }
+        }
+
+        let playlists_str =
+            serde_json::to_string(&*playlists).context("Failed to serialize playlists")?;
+
+        drop(playlists);
+
+        info!(
+            "-> Imported {} playlist files",
+            parsed.len().to_string().bright_yellow()
+        );
+
+        owner_data.write_playlists_file(&playlists_str)
+    }
+
+    /// Parse a playlist file from the music directory, then match its entries against the index
+    ///
+    /// Returns the playlist's name and the matched tracks.
+    fn read_discovered_playlist(
+        &self,
+        relative_path: &Path,
+        index_cache: &IndexCache,
+    ) -> Result<(String, Vec<TrackID>)> {
+        let format = relative_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(PlaylistFileFormat::from_extension)
+            .unwrap();
+
+        let content =
+            fs::read(self.music_dir.join(relative_path)).context("Failed to read playlist file")?;
+
+        let playlist_file = PlaylistFile::parse(&content, format)?;
+
+        let matching = playlist_file.match_tracks(
+            relative_path.parent().unwrap_or(Path::new("")),
+            &self.music_dir,
+            index_cache,
+        );
+
+        if !matching.unresolved.is_empty() {
+            warn!(
+                "-> {} entries of playlist file '{}' could not be matched to a track",
+                matching.unresolved.len().to_string().bright_yellow(),
+                relative_path.display()
+            );
+        }
+
+        let name = playlist_file.title.unwrap_or_else(|| {
+            relative_path
+                .file_stem()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        });
+
+        Ok((name, matching.track_ids))
+    }
+
+    /// Update the informations about the tracks of all users' playlists after an index update
+    ///
+    /// The users' average ratings, which depend on the index, are computed again as well.
+    fn refresh_playlists_snapshots(&self, index_cache: &IndexCache) -> Result<()> {
+        for user_data in self.users_data.values() {
+            user_data.invalidate_ratings_averages();
+
+            let mut playlists = user_data.playlists.blocking_write();
+
+            for playlist in playlists.values_mut() {
+                playlist.refresh_snapshots(index_cache);
+            }
+
+            let playlists_str =
+                serde_json::to_string(&*playlists).context("Failed to serialize playlists")?;
+
+            drop(playlists);
+
+            user_data.write_playlists_file(&playlists_str)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(clippy::cast_precision_loss, clippy::as_conversions)]
+fn bytes_to_mib(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
+}
+
+async fn render_resized_art<I: IdType>(
+    resized_arts: ResizedArtsCache,
+    arts: ArtsManager<I>,
+    item_id: I,
+    side_px: u32,
+    format: ArtFormat,
+) -> Result<PathBuf> {
+    spawn_blocking(move || resized_arts.get_or_render(&arts, item_id, side_px, format))
+        .await
+        .context("Art rendering task panicked")?
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Entity {
+    Artist(ArtistID),
+    Album(AlbumID),
+    Genre(GenreID),
+    Track(TrackID),
+    Playlist(PlaylistID),
+}
+
+impl Entity {
+    pub fn kind(self) -> &'static str {
+        match self {
+            Self::Artist(_) => "artist",
+            Self::Album(_) => "album",
+            Self::Genre(_) => "genre",
+            Self::Track(_) => "track",
+            Self::Playlist(_) => "playlist",
+        }
+    }
+}
@@ -0,0 +1,935 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync,
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use colored::Colorize;
+use log::{debug, info, trace, warn};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use crate::{
+    index::{IdType, IndexCache, Rating, TrackID},
+    stable_hash,
+    userdata::{
+        Bookmark, Bookmarks, DeviceTokens, IgnoredPlaylistFiles, Listen, PlayQueue, PlayStats,
+        Playlist, PlaylistFile, PlaylistFileFormat, PlaylistID, Playlists, RatedItem, Ratings,
+        SmartPlaylist, SmartPlaylistID, SmartPlaylistRules, SmartPlaylists, StarredItem, Stars,
+        TrackPlayStats, User, UserID, UserRole, Users,
+    },
+};
+
+use super::DataManager;
+
+/// Name of the account created for the server's owner on first start
+static OWNER_USERNAME: &str = "admin";
+
+/// Files containing user data, which were stored at the root of the data directory
+/// before multiple users were supported
+static LEGACY_USER_DATA_FILES: &[&str] = &[
+    "ratings.json",
+    "playlists.json",
+    "smart_playlists.json",
+    "history.jsonl",
+    "stars.json",
+    "bookmarks.json",
+    "play_queue.json",
+];
+
+/// Data belonging to a single user, stored in its own directory
+pub struct UserData {
+    ratings_path: PathBuf,
+    ratings: RwLock<Ratings>,
+
+    playlists_path: PathBuf,
+    pub playlists: RwLock<Playlists>,
+
+    ignored_playlist_files_path: PathBuf,
+    pub ignored_playlist_files: RwLock<IgnoredPlaylistFiles>,
+
+    smart_playlists_path: PathBuf,
+    smart_playlists: RwLock<SmartPlaylists>,
+
+    history_path: PathBuf,
+    // Synchronous lock so play stats can be read when building DTOs
+    play_stats: sync::RwLock<PlayStats>,
+
+    stars_path: PathBuf,
+    // Synchronous lock so stars can be read when building DTOs
+    stars: sync::RwLock<Stars>,
+
+    bookmarks_path: PathBuf,
+    bookmarks: RwLock<Bookmarks>,
+
+    play_queue_path: PathBuf,
+    play_queue: RwLock<Option<PlayQueue>>,
+}
+
+impl UserData {
+    #[allow(clippy::too_many_lines)]
+    pub fn load(user_dir: &Path) -> Result<Self> {
+        if !user_dir.exists() {
+            fs::create_dir_all(user_dir).context("Failed to create the user's data directory")?;
+        }
+
+        let ratings_path = user_dir.join("ratings.json");
+
+        let ratings = if ratings_path.exists() {
+            debug!("> Loading ratings file...");
+
+            let ratings_str =
+                fs::read_to_string(&ratings_path).context("Failed to read ratings file")?;
+
+            serde_json::from_str::<Ratings>(&ratings_str)
+                .or_else(|_| {
+                    // Ratings files used to only contain track ratings
+                    serde_json::from_str::<HashMap<TrackID, Rating>>(&ratings_str)
+                        .map(Ratings::from)
+                })
+                .context("Failed to parse ratings file")?
+        } else {
+            debug!("> No ratings file found, starting with empty ratings.");
+
+            Ratings::default()
+        };
+
+        let playlists_path = user_dir.join("playlists.json");
+
+        let playlists = if playlists_path.exists() {
+            debug!("> Loading playlists file...");
+
+            let playlists_str =
+                fs::read_to_string(&playlists_path).context("Failed to read playlists file")?;
+
+            serde_json::from_str::<Playlists>(&playlists_str)
+                .context("Failed to parse playlists file")?
+        } else {
+            debug!("> No playlists file found, starting with no playlist.");
+
+            Playlists::new()
+        };
+
+        let ignored_playlist_files_path = user_dir.join("ignored_playlist_files.json");
+
+        let ignored_playlist_files = if ignored_playlist_files_path.exists() {
+            debug!("> Loading ignored playlist files file...");
+
+            let ignored_playlist_files_str = fs::read_to_string(&ignored_playlist_files_path)
+                .context("Failed to read ignored playlist files file")?;
+
+            serde_json::from_str::<IgnoredPlaylistFiles>(&ignored_playlist_files_str)
+                .context("Failed to parse ignored playlist files file")?
+        } else {
+            debug!("> No ignored playlist files file found, starting with no ignored file.");
+
+            IgnoredPlaylistFiles::new()
+        };
+
+        let smart_playlists_path = user_dir.join("smart_playlists.json");
+
+        let smart_playlists = if smart_playlists_path.exists() {
+            debug!("> Loading smart playlists file...");
+
+            let smart_playlists_str = fs::read_to_string(&smart_playlists_path)
+                .context("Failed to read smart playlists file")?;
+
+            serde_json::from_str::<SmartPlaylists>(&smart_playlists_str)
+                .context("Failed to parse smart playlists file")?
+        } else {
+            debug!("> No smart playlists file found, starting with no smart playlist.");
+
+            SmartPlaylists::new()
+        };
+
+        let history_path = user_dir.join("history.jsonl");
+
+        let play_stats = if history_path.exists() {
+            debug!("> Loading play history file...");
+
+            let history_str =
+                fs::read_to_string(&history_path).context("Failed to read play history file")?;
+
+            let mut play_stats = PlayStats::new();
+
+            for (i, line) in history_str.lines().enumerate() {
+                if line.is_empty() {
+                    continue;
+                }
+
+                // Skip invalid lines instead of failing, as the last one may have been truncated
+                match serde_json::from_str::<Listen>(line) {
+                    Ok(listen) => play_stats
+                        .entry(listen.track_id)
+                        .or_default()
+                        .record(&listen),
+                    Err(err) => warn!("Skipping invalid line {} in play history: {err}", i + 1),
+                }
+            }
+
+            play_stats
+        } else {
+            debug!("> No play history file found, starting with an empty history.");
+
+            PlayStats::new()
+        };
+
+        let stars_path = user_dir.join("stars.json");
+
+        let stars = if stars_path.exists() {
+            debug!("> Loading stars file...");
+
+            let stars_str = fs::read_to_string(&stars_path).context("Failed to read stars file")?;
+
+            serde_json::from_str::<Stars>(&stars_str).context("Failed to parse stars file")?
+        } else {
+            debug!("> No stars file found, starting with no starred item.");
+
+            Stars::default()
+        };
+
+        let bookmarks_path = user_dir.join("bookmarks.json");
+
+        let bookmarks = if bookmarks_path.exists() {
+            debug!("> Loading bookmarks file...");
+
+            let bookmarks_str =
+                fs::read_to_string(&bookmarks_path).context("Failed to read bookmarks file")?;
+
+            serde_json::from_str::<Bookmarks>(&bookmarks_str)
+                .context("Failed to parse bookmarks file")?
+        } else {
+            debug!("> No bookmarks file found, starting with no bookmark.");
+
+            Bookmarks::new()
+        };
+
+        let play_queue_path = user_dir.join("play_queue.json");
+
+        let play_queue = if play_queue_path.exists() {
+            debug!("> Loading play queue file...");
+
+            let play_queue_str =
+                fs::read_to_string(&play_queue_path).context("Failed to read play queue file")?;
+
+            serde_json::from_str::<Option<PlayQueue>>(&play_queue_str)
+                .context("Failed to parse play queue file")?
+        } else {
+            debug!("> No play queue file found, starting with no saved queue.");
+
+            None
+        };
+
+        Ok(Self {
+            ratings_path,
+            ratings: RwLock::new(ratings),
+
+            playlists_path,
+            playlists: RwLock::new(playlists),
+
+            ignored_playlist_files_path,
+            ignored_playlist_files: RwLock::new(ignored_playlist_files),
+
+            smart_playlists_path,
+            smart_playlists: RwLock::new(smart_playlists),
+
+            history_path,
+            play_stats: sync::RwLock::new(play_stats),
+
+            stars_path,
+            stars: sync::RwLock::new(stars),
+
+            bookmarks_path,
+            bookmarks: RwLock::new(bookmarks),
+
+            play_queue_path,
+            play_queue: RwLock::new(play_queue),
+        })
+    }
+
+    /// Move the user data files found at the root of the data directory to a user's directory
+    pub fn migrate_legacy_files(data_dir: &Path, user_dir: &Path) -> Result<()> {
+        fs::create_dir_all(user_dir).context("Failed to create the user's data directory")?;
+
+        for file_name in LEGACY_USER_DATA_FILES {
+            let legacy_path = data_dir.join(file_name);
+
+            if !legacy_path.exists() {
+                continue;
+            }
+
+            info!("> Moving legacy {file_name} file to the owner's data directory...");
+
+            fs::rename(&legacy_path, user_dir.join(file_name))
+                .with_context(|| format!("Failed to move legacy {file_name} file"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Forget the average ratings computed from the previous index
+    pub fn invalidate_ratings_averages(&self) {
+        self.ratings.blocking_write().invalidate_tracks_averages();
+    }
+
+    pub fn write_playlists_file(&self, playlists_str: &str) -> Result<()> {
+        fs::write(&self.playlists_path, playlists_str).context("Failed to write playlists file")?;
+
+        trace!(
+            "> Wrote to playlists file (~ {} Kb)",
+            playlists_str.len() / 1024
+        );
+
+        Ok(())
+    }
+
+    pub fn write_ignored_playlist_files_file(
+        &self,
+        ignored_playlist_files_str: &str,
+    ) -> Result<()> {
+        fs::write(
+            &self.ignored_playlist_files_path,
+            ignored_playlist_files_str,
+        )
+        .context("Failed to write ignored playlist files file")?;
+
+        trace!(
+            "> Wrote to ignored playlist files file (~ {} Kb)",
+            ignored_playlist_files_str.len() / 1024
+        );
+
+        Ok(())
+    }
+}
+
+/// Access to the data manager on behalf of a user
+///
+/// Shared data (e.g. the index or the arts) are accessible through [`Deref`],
+/// while per-user data is read from and written to the user's own files.
+#[derive(Clone, Copy)]
+pub struct UserDataManager<'a> {
+    data: &'a DataManager,
+    user: &'a User,
+    user_data: &'a UserData,
+}
+
+impl Deref for UserDataManager<'_> {
+    type Target = DataManager;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<'a> UserDataManager<'a> {
+    pub fn new(data: &'a DataManager, user: &'a User, user_data: &'a UserData) -> Self {
+        Self {
+            data,
+            user,
+            user_data,
+        }
+    }
+
+    pub fn user(&self) -> &'a User {
+        self.user
+    }
+
+    pub async fn ratings(&self) -> RwLockReadGuard<'a, Ratings> {
+        self.user_data.ratings.read().await
+    }
+
+    /// Set or remove the rating of an item, then persist the ratings
+    pub async fn set_rating(&self, item: RatedItem, rating: Option<Rating>) -> Result<()> {
+        {
+            let index = self.index_cache.read().await;
+
+            let exists = match item {
+                RatedItem::Track(track_id) => index.tracks.contains_key(&track_id),
+                RatedItem::Album(album_id) => index.albums.contains_key(&album_id),
+                RatedItem::Artist(artist_id) => index.artists.contains_key(&artist_id),
+            };
+
+            ensure!(exists, "Provided item {item:?} was not found");
+        }
+
+        let mut ratings = self.user_data.ratings.write().await;
+
+        ratings.set(item, rating);
+
+        let ratings_str =
+            serde_json::to_string(&*ratings).context("Failed to serialize ratings")?;
+
+        // Drop the lock to avoid holding it across a filesystem access
+        drop(ratings);
+
+        fs::write(&self.user_data.ratings_path, &ratings_str)
+            .context("Failed to write ratings file")?;
+
+        trace!(
+            "> Wrote to ratings file (~ {} Kb)",
+            ratings_str.len() / 1024
+        );
+
+        Ok(())
+    }
+
+    pub fn play_stats(&self) -> sync::RwLockReadGuard<'a, PlayStats> {
+        self.user_data.play_stats.read().unwrap()
+    }
+
+    pub fn track_play_stats(&self, track_id: TrackID) -> TrackPlayStats {
+        self.play_stats()
+            .get(&track_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Append a listen to the play history
+    pub async fn record_listen(&self, listen: Listen) -> Result<()> {
+        if !self
+            .index_cache
+            .read()
+            .await
+            .tracks
+            .contains_key(&listen.track_id)
+        {
+            bail!("Provided track ID was not found");
+        }
+
+        ensure!(
+            (0.0..=1.0).contains(&listen.completion),
+            "Completion must be between 0 and 1"
+        );
+
+        let mut line = serde_json::to_string(&listen).context("Failed to serialize listen")?;
+        line.push('\n');
+
+        // Write the whole line at once so concurrent appends don't interleave
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.user_data.history_path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .context("Failed to write to play history file")?;
+
+        trace!("> Appended a listen to the play history file");
+
+        self.user_data
+            .play_stats
+            .write()
+            .unwrap()
+            .entry(listen.track_id)
+            .or_default()
+            .record(&listen);
+
+        Ok(())
+    }
+
+    pub fn stars(&self) -> sync::RwLockReadGuard<'a, Stars> {
+        self.user_data.stars.read().unwrap()
+    }
+
+    pub fn starred_at(&self, item: StarredItem) -> Option<SystemTime> {
+        self.stars().starred_at(item)
+    }
+
+    /// Star or unstar items, then persist the stars
+    ///
+    /// Items that are not in the index can be unstarred, to clean up dangling stars.
+    pub async fn set_starred(&self, items: &[StarredItem], starred: bool) -> Result<()> {
+        if starred {
+            let index = self.index_cache.read().await;
+
+            for item in items {
+                let exists = match item {
+                    StarredItem::Track(track_id) => index.tracks.contains_key(track_id),
+                    StarredItem::Album(album_id) => index.albums.contains_key(album_id),
+                    StarredItem::Artist(artist_id) => index.artists.contains_key(artist_id),
+                };
+
+                ensure!(exists, "Provided item {item:?} was not found");
+            }
+        }
+
+        let mut stars = self.user_data.stars.write().unwrap();
+
+        let now = SystemTime::now();
+
+        for item in items {
+            if starred {
+                stars.star(*item, now);
+            } else {
+                stars.unstar(*item);
+            }
+        }
+
+        let stars_str = serde_json::to_string(&*stars).context("Failed to serialize stars")?;
+
+        // Drop the lock to avoid holding it across a filesystem access
+        drop(stars);
+
+        fs::write(&self.user_data.stars_path, &stars_str).context("Failed to write stars file")?;
+
+        trace!("> Wrote to stars file (~ {} Kb)", stars_str.len() / 1024);
+
+        Ok(())
+    }
+
+    pub async fn playlists(&self) -> RwLockReadGuard<'a, Playlists> {
+        self.user_data.playlists.read().await
+    }
+
+    /// Apply changes to the playlists, then persist them
+    async fn update_playlists<T>(
+        &self,
+        update: impl FnOnce(&mut Playlists) -> Result<T>,
+    ) -> Result<T> {
+        let mut playlists = self.user_data.playlists.write().await;
+
+        let result = update(&mut playlists)?;
+
+        let playlists_str =
+            serde_json::to_string(&*playlists).context("Failed to serialize playlists")?;
+
+        // Drop the lock to avoid holding it across a filesystem access
+        drop(playlists);
+
+        self.user_data.write_playlists_file(&playlists_str)?;
+
+        Ok(result)
+    }
+
+    pub async fn create_playlist(
+        &self,
+        name: &str,
+        description: Option<String>,
+        track_ids: &[TrackID],
+    ) -> Result<Playlist> {
+        let mut playlist = Playlist::new(name, description)?;
+
+        if !track_ids.is_empty() {
+            playlist.add_tracks(track_ids, None, &*self.index_cache.read().await)?;
+        }
+
+        self.update_playlists(|playlists| {
+            playlists.insert(playlist.id, playlist.clone());
+            Ok(())
+        })
+        .await?;
+
+        Ok(playlist)
+    }
+
+    /// Apply changes to a single playlist, then persist them
+    pub async fn update_playlist<T>(
+        &self,
+        playlist_id: PlaylistID,
+        update: impl FnOnce(&mut Playlist, &IndexCache) -> Result<T>,
+    ) -> Result<T> {
+        let index = self.index_cache.read().await;
+
+        self.update_playlists(|playlists| {
+            let playlist = playlists
+                .get_mut(&playlist_id)
+                .context("Provided playlist ID was not found")?;
+
+            update(playlist, &index)
+        })
+        .await
+    }
+
+    /// Delete a playlist
+    ///
+    /// If it was imported from a playlist file, the file is ignored by later imports.
+    pub async fn delete_playlist(&self, playlist_id: PlaylistID) -> Result<()> {
+        let playlist = self
+            .update_playlists(|playlists| {
+                playlists
+                    .shift_remove(&playlist_id)
+                    .context("Provided playlist ID was not found")
+            })
+            .await?;
+
+        if let Some(source) = playlist.source {
+            let mut ignored_playlist_files = self.user_data.ignored_playlist_files.write().await;
+
+            ignored_playlist_files.insert(source.relative_path);
+
+            let ignored_playlist_files_str = serde_json::to_string(&*ignored_playlist_files)
+                .context("Failed to serialize ignored playlist files")?;
+
+            // Drop the lock to avoid holding it across a filesystem access
+            drop(ignored_playlist_files);
+
+            self.user_data
+                .write_ignored_playlist_files_file(&ignored_playlist_files_str)?;
+        }
+
+        if self.playlist_arts.has(playlist_id) {
+            self.playlist_arts.delete(playlist_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set a playlist's cover from an uploaded image
+    pub fn set_playlist_cover(&self, playlist_id: PlaylistID, data: &[u8]) -> Result<()> {
+        ensure!(
+            self.user_data
+                .playlists
+                .blocking_read()
+                .contains_key(&playlist_id),
+            "Provided playlist ID was not found"
+        );
+
+        let img = image::load_from_memory(data).context("Failed to decode the uploaded image")?;
+
+        self.playlist_arts
+            .register(playlist_id, stable_hash!("cover", data), &img.into_rgb8())?;
+
+        Ok(())
+    }
+
+    pub fn remove_playlist_cover(&self, playlist_id: PlaylistID) -> Result<()> {
+        ensure!(
+            self.user_data
+                .playlists
+                .blocking_read()
+                .contains_key(&playlist_id),
+            "Provided playlist ID was not found"
+        );
+
+        ensure!(
+            self.playlist_arts.has(playlist_id),
+            "Provided playlist has no cover"
+        );
+
+        self.playlist_arts.delete(playlist_id)
+    }
+
+    pub async fn smart_playlists(&self) -> RwLockReadGuard<'a, SmartPlaylists> {
+        self.user_data.smart_playlists.read().await
+    }
+
+    /// Apply changes to the smart playlists, then persist them
+    async fn update_smart_playlists<T>(
+        &self,
+        update: impl FnOnce(&mut SmartPlaylists) -> Result<T>,
+    ) -> Result<T> {
+        let mut smart_playlists = self.user_data.smart_playlists.write().await;
+
+        let result = update(&mut smart_playlists)?;
+
+        let smart_playlists_str = serde_json::to_string(&*smart_playlists)
+            .context("Failed to serialize smart playlists")?;
+
+        // Drop the lock to avoid holding it across a filesystem access
+        drop(smart_playlists);
+
+        fs::write(&self.user_data.smart_playlists_path, &smart_playlists_str)
+            .context("Failed to write smart playlists file")?;
+
+        trace!(
+            "> Wrote to smart playlists file (~ {} Kb)",
+            smart_playlists_str.len() / 1024
+        );
+
+        Ok(result)
+    }
+
+    pub async fn create_smart_playlist(
+        &self,
+        name: &str,
+        description: Option<String>,
+        rules: SmartPlaylistRules,
+    ) -> Result<SmartPlaylist> {
+        let smart_playlist = SmartPlaylist::new(name, description, rules)?;
+
+        self.update_smart_playlists(|smart_playlists| {
+            smart_playlists.insert(smart_playlist.id, smart_playlist.clone());
+            Ok(())
+        })
+        .await?;
+
+        Ok(smart_playlist)
+    }
+
+    /// Apply changes to a single smart playlist, then persist them
+    pub async fn update_smart_playlist<T>(
+        &self,
+        smart_playlist_id: SmartPlaylistID,
+        update: impl FnOnce(&mut SmartPlaylist) -> Result<T>,
+    ) -> Result<T> {
+        self.update_smart_playlists(|smart_playlists| {
+            let smart_playlist = smart_playlists
+                .get_mut(&smart_playlist_id)
+                .context("Provided smart playlist ID was not found")?;
+
+            update(smart_playlist)
+        })
+        .await
+    }
+
+    pub async fn delete_smart_playlist(&self, smart_playlist_id: SmartPlaylistID) -> Result<()> {
+        self.update_smart_playlists(|smart_playlists| {
+            smart_playlists
+                .shift_remove(&smart_playlist_id)
+                .context("Provided smart playlist ID was not found")
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn bookmarks(&self) -> RwLockReadGuard<'a, Bookmarks> {
+        self.user_data.bookmarks.read().await
+    }
+
+    /// Apply changes to the bookmarks, then persist them
+    async fn update_bookmarks<T>(
+        &self,
+        update: impl FnOnce(&mut Bookmarks) -> Result<T>,
+    ) -> Result<T> {
+        let mut bookmarks = self.user_data.bookmarks.write().await;
+
+        let result = update(&mut bookmarks)?;
+
+        let bookmarks_str =
+            serde_json::to_string(&*bookmarks).context("Failed to serialize bookmarks")?;
+
+        // Drop the lock to avoid holding it across a filesystem access
+        drop(bookmarks);
+
+        fs::write(&self.user_data.bookmarks_path, &bookmarks_str)
+            .context("Failed to write bookmarks file")?;
+
+        trace!(
+            "> Wrote to bookmarks file (~ {} Kb)",
+            bookmarks_str.len() / 1024
+        );
+
+        Ok(result)
+    }
+
+    /// Create a bookmark for a track, or update its existing one
+    pub async fn set_bookmark(
+        &self,
+        track_id: TrackID,
+        position_ms: u64,
+        comment: Option<String>,
+    ) -> Result<Bookmark> {
+        if !self.index_cache.read().await.tracks.contains_key(&track_id) {
+            bail!("Provided track ID was not found");
+        }
+
+        self.update_bookmarks(|bookmarks| {
+            let bookmark = bookmarks
+                .entry(track_id)
+                .and_modify(|bookmark| bookmark.update(position_ms, comment.clone()))
+                .or_insert_with(|| Bookmark::new(track_id, position_ms, comment));
+
+            Ok(bookmark.clone())
+        })
+        .await
+    }
+
+    pub async fn delete_bookmark(&self, track_id: TrackID) -> Result<()> {
+        self.update_bookmarks(|bookmarks| {
+            bookmarks
+                .shift_remove(&track_id)
+                .context("No bookmark exists for the provided track ID")
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn play_queue(&self) -> RwLockReadGuard<'a, Option<PlayQueue>> {
+        self.user_data.play_queue.read().await
+    }
+
+    /// Replace the saved play queue, or clear it if it is empty
+    pub async fn save_play_queue(&self, play_queue: PlayQueue) -> Result<()> {
+        {
+            let index = self.index_cache.read().await;
+
+            for track_id in &play_queue.track_ids {
+                ensure!(
+                    index.tracks.contains_key(track_id),
+                    "Provided track ID {track_id:?} was not found"
+                );
+            }
+        }
+
+        let play_queue = if play_queue.track_ids.is_empty() {
+            None
+        } else {
+            Some(play_queue)
+        };
+
+        let play_queue_str =
+            serde_json::to_string(&play_queue).context("Failed to serialize play queue")?;
+
+        *self.user_data.play_queue.write().await = play_queue;
+
+        fs::write(&self.user_data.play_queue_path, &play_queue_str)
+            .context("Failed to write play queue file")?;
+
+        trace!(
+            "> Wrote to play queue file (~ {} Kb)",
+            play_queue_str.len() / 1024
+        );
+
+        Ok(())
+    }
+
+    /// Create a playlist from the content of a playlist file
+    ///
+    /// Paths in the file are resolved relatively to the music directory. Returns the created
+    /// playlist alongside the entries no track could be found for.
+    pub async fn import_playlist(
+        &self,
+        name: Option<&str>,
+        content: &[u8],
+        format: PlaylistFileFormat,
+    ) -> Result<(Playlist, Vec<String>)> {
+        let playlist_file = PlaylistFile::parse(content, format)?;
+
+        let index = self.index_cache.read().await;
+
+        let matching = playlist_file.match_tracks(Path::new(""), self.music_dir(), &index);
+
+        let name = name
+            .map(str::to_owned)
+            .or(playlist_file.title)
+            .context("No name was provided for the playlist")?;
+
+        let mut playlist = Playlist::new(&name, None)?;
+        playlist.replace_tracks(&matching.track_ids, &index)?;
+
+        drop(index);
+
+        self.update_playlists(|playlists| {
+            playlists.insert(playlist.id, playlist.clone());
+            Ok(())
+        })
+        .await?;
+
+        Ok((playlist, matching.unresolved))
+    }
+}
+
+/// Get the directory a user's data is stored in
+pub fn user_dir(data_dir: &Path, user_id: UserID) -> PathBuf {
+    data_dir.join("users").join(user_id.encode())
+}
+
+/// Load the user accounts
+///
+/// If no account exists yet, the owner's account is created with a random password,
+/// and the data that was stored before multiple users were supported is moved to it.
+pub fn load_users(data_dir: &Path) -> Result<Users> {
+    let users_path = data_dir.join("users.json");
+
+    if users_path.exists() {
+        debug!("> Loading users file...");
+
+        let users_str = fs::read_to_string(&users_path).context("Failed to read users file")?;
+
+        let mut users =
+            serde_json::from_str::<Users>(&users_str).context("Failed to parse users file")?;
+
+        ensure!(!users.is_empty(), "Users file does not contain any user");
+
+        // Fields added to existing accounts (e.g. API keys) are filled when parsing the file,
+        // so it is written again to keep them
+        let stored = serde_json::from_str::<serde_json::Value>(&users_str)
+            .context("Failed to parse users file")?;
+
+        // Accounts created before roles existed are listeners, except for the owner's one
+        let (owner_id, owner) = users.first_mut().unwrap();
+
+        if stored
+            .get(owner_id.encode())
+            .is_some_and(|stored_owner| stored_owner.get("role").is_none())
+        {
+            owner.role = UserRole::Admin;
+        }
+
+        if serde_json::to_value(&users).context("Failed to serialize users")? != stored {
+            info!("> Upgrading users file with the missing fields...");
+            write_users(data_dir, &users)?;
+        }
+
+        return Ok(users);
+    }
+
+    info!("> No users file found, creating the owner's account...");
+
+    let owner = User::new(OWNER_USERNAME, UserRole::Admin)?;
+    let owner_dir = user_dir(data_dir, owner.id);
+
+    // Secrets are not logged, as logs may be persisted (e.g. by journald)
+    warn!(
+        "Created user '{}' with a random password, run the '{}' command to set a new one and display it",
+        owner.name.bright_green(),
+        format!("users reset-password {}", owner.name).bright_yellow()
+    );
+
+    let users = Users::from([(owner.id, owner)]);
+
+    // The account must be persisted first, otherwise an interrupted migration would leave
+    // the legacy files in the directory of an owner that is created again on next start
+    write_users(data_dir, &users)?;
+
+    UserData::migrate_legacy_files(data_dir, &owner_dir)?;
+
+    Ok(users)
+}
+
+/// Persist the user accounts
+///
+/// As passwords are stored as-is, the file is only made readable by its owner.
+pub fn write_users(data_dir: &Path, users: &Users) -> Result<()> {
+    let users_str = serde_json::to_string_pretty(users).context("Failed to serialize users")?;
+
+    write_private_file(&data_dir.join("users.json"), &users_str)
+        .context("Failed to write users file")?;
+
+    trace!("> Wrote to users file (~ {} Kb)", users_str.len() / 1024);
+
+    Ok(())
+}
+
+/// Load the tokens of the devices users logged in from
+pub fn load_device_tokens(device_tokens_path: &Path) -> Result<DeviceTokens> {
+    if !device_tokens_path.exists() {
+        debug!("> No device tokens file found, starting with no device token.");
+        return Ok(DeviceTokens::new());
+    }
+
+    debug!("> Loading device tokens file...");
+
+    let device_tokens_str =
+        fs::read_to_string(device_tokens_path).context("Failed to read device tokens file")?;
+
+    serde_json::from_str::<DeviceTokens>(&device_tokens_str)
+        .context("Failed to parse device tokens file")
+}
+
+/// Write a file that contains secrets, which is only made readable by its owner
+pub fn write_private_file(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+}
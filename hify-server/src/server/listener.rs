@@ -0,0 +1,213 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use anyhow::{Context, Result, bail, ensure};
+use listenfd::ListenFd;
+use log::info;
+use serde::Deserialize;
+use tokio::net::TcpListener;
+
+#[cfg(unix)]
+use log::warn;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+use super::ServerSettings;
+
+/// Settings of a Unix domain socket to listen on
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnixSocketSettings {
+    /// Path of the socket file
+    pub path: PathBuf,
+
+    /// Permissions of the socket file (e.g. `0o660`)
+    #[serde(default)]
+    pub permissions: Option<u32>,
+}
+
+impl UnixSocketSettings {
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            cfg!(unix),
+            "Unix sockets are not supported on this platform"
+        );
+
+        if let Some(permissions) = self.permissions {
+            ensure!(
+                permissions <= 0o777,
+                "Invalid socket permissions '{permissions:o}', expected an octal mode like 0o660"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Listener the server accepts connections from
+pub enum ServerListener {
+    Tcp(TcpListener),
+
+    /// The socket file is only present if it was created by the server, and not by systemd
+    #[cfg(unix)]
+    Unix(UnixListener, Option<UnixSocketFile>),
+}
+
+impl ServerListener {
+    /// Open the listener described by the settings
+    ///
+    /// When the server is started through systemd socket activation,
+    /// the inherited socket is used instead.
+    pub async fn open(settings: &ServerSettings) -> Result<Self> {
+        if let Some(listener) = Self::inherit_from_systemd()? {
+            return Ok(listener);
+        }
+
+        match &settings.unix_socket {
+            #[cfg(unix)]
+            Some(unix_socket) => {
+                let (listener, file) = bind_unix_socket(unix_socket)?;
+                Ok(Self::Unix(listener, Some(file)))
+            }
+
+            #[cfg(not(unix))]
+            Some(_) => bail!("Unix sockets are not supported on this platform"),
+
+            None => {
+                let addr = SocketAddr::new(settings.addr, settings.port);
+
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("Failed to listen on '{addr}'"))?;
+
+                Ok(Self::Tcp(listener))
+            }
+        }
+    }
+
+    /// Take the socket passed by systemd, if any
+    fn inherit_from_systemd() -> Result<Option<Self>> {
+        let mut fds = ListenFd::from_env();
+
+        match fds.len() {
+            0 => return Ok(None),
+            1 => {}
+            count => bail!("Expected a single socket from systemd, got {count}"),
+        }
+
+        if let Ok(Some(listener)) = fds.take_tcp_listener(0) {
+            info!("> Using the TCP socket passed by systemd");
+
+            listener.set_nonblocking(true)?;
+            return Ok(Some(Self::Tcp(TcpListener::from_std(listener)?)));
+        }
+
+        #[cfg(unix)]
+        if let Ok(Some(listener)) = fds.take_unix_listener(0) {
+            info!("> Using the Unix socket passed by systemd");
+
+            listener.set_nonblocking(true)?;
+            return Ok(Some(Self::Unix(UnixListener::from_std(listener)?, None)));
+        }
+
+        bail!("Socket passed by systemd is neither a TCP nor a Unix stream listener")
+    }
+
+    /// Human-readable address the listener is bound to
+    pub fn describe(&self) -> Result<String> {
+        match self {
+            Self::Tcp(listener) => Ok(listener.local_addr()?.to_string()),
+
+            #[cfg(unix)]
+            Self::Unix(_, Some(file)) => Ok(file.0.display().to_string()),
+
+            #[cfg(unix)]
+            Self::Unix(listener, None) => Ok(listener.local_addr()?.as_pathname().map_or_else(
+                || "<unnamed socket>".to_owned(),
+                |path| path.display().to_string(),
+            )),
+        }
+    }
+}
+
+/// Socket file created by the server, removed once the server stops
+#[cfg(unix)]
+pub struct UnixSocketFile(PathBuf);
+
+#[cfg(unix)]
+impl Drop for UnixSocketFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.0) {
+            warn!("Failed to remove socket '{}': {err}", self.0.display());
+        }
+    }
+}
+
+/// Bind a Unix socket at the configured path
+///
+/// The socket is created in a private directory first, so it is never reachable
+/// before its permissions are set, then moved (atomically) to its final path.
+#[cfg(unix)]
+fn bind_unix_socket(settings: &UnixSocketSettings) -> Result<(UnixListener, UnixSocketFile)> {
+    use std::{
+        fs,
+        os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+    };
+
+    let UnixSocketSettings { path, permissions } = settings;
+
+    // A socket left behind by a previous run will be replaced, but never a regular file
+    if let Ok(mt) = fs::symlink_metadata(path) {
+        ensure!(
+            mt.file_type().is_socket(),
+            "Path '{}' already exists and is not a socket",
+            path.display()
+        );
+    }
+
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Socket path '{}' has no file name", path.display()))?;
+
+    // The private directory must be on the same filesystem for the socket to be moved
+    let private_dir = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private_dir)
+        .with_context(|| format!("Failed to create directory '{}'", private_dir.display()))?;
+
+    let bind_privately = || -> Result<UnixListener> {
+        let private_path = private_dir.join(file_name);
+
+        let listener = UnixListener::bind(&private_path)
+            .with_context(|| format!("Failed to listen on socket '{}'", path.display()))?;
+
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&private_path, fs::Permissions::from_mode(*permissions))
+                .with_context(|| {
+                    format!("Failed to set permissions of socket '{}'", path.display())
+                })?;
+        }
+
+        fs::rename(&private_path, path)
+            .with_context(|| format!("Failed to move socket to '{}'", path.display()))?;
+
+        Ok(listener)
+    };
+
+    let listener = bind_privately();
+
+    // Don't leave the directory behind, whether the socket could be moved out of it or not
+    if let Err(err) = fs::remove_dir_all(&private_dir) {
+        warn!(
+            "Failed to remove directory '{}': {err}",
+            private_dir.display()
+        );
+    }
+
+    Ok((listener?, UnixSocketFile(path.clone())))
+}
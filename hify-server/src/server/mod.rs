@@ -1,10 +1,20 @@
-use std::{net::SocketAddr, ops::Deref, sync::Arc};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::Deref,
+    sync::Arc,
+};
 
-use anyhow::Result;
-use axum::middleware;
+use anyhow::{Context, Result, bail, ensure};
+use axum::{
+    Router,
+    http::{HeaderValue, Uri, header::HOST, request::Parts, uri::Authority},
+    middleware,
+    serve::Listener,
+};
 use colored::Colorize;
 use log::info;
-use tokio::net::TcpListener;
+use serde::Deserialize;
+use tokio::task::spawn_blocking;
 use tower_http::{
     compression::CompressionLayer,
     cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer},
@@ -12,31 +22,193 @@ use tower_http::{
 
 use crate::manager::DataManager;
 
-use self::{routes::router, utils::logging::log_errors};
+use self::{
+    listener::ServerListener,
+    routes::router,
+    tls::{TlsListener, serve_https_redirect},
+    utils::logging::log_errors,
+};
 
+mod listener;
 mod opensubsonic;
 mod routes;
+mod tls;
 mod utils;
 
+pub use self::{listener::UnixSocketSettings, tls::TlsSettings};
+
 pub static OPENSUBSONIC_BASE_URI: &str = "/rest";
 
-pub async fn launch(addr: SocketAddr, data_manager: DataManager) -> Result<()> {
-    // TODO: improve this
+/// Port the bundled web client is served on
+pub static WEB_CLIENT_PORT: u16 = 8892;
+
+/// Settings of the HTTP server
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    /// Address to listen on
+    pub addr: IpAddr,
+
+    /// Port to listen on
+    pub port: u16,
+
+    /// Expose playlists generated from ratings to `OpenSubsonic` clients
+    pub auto_playlists: bool,
+
+    /// Path all routes are served under (e.g. `/hify`), for deployments behind a reverse proxy
+    pub base_path: String,
+
+    /// Build the URIs handed out to clients from the `X-Forwarded-*` headers
+    ///
+    /// Only enable this behind a reverse proxy that sets (or strips) these headers.
+    /// A forwarded prefix takes precedence over the base path in the handed out URIs.
+    pub trust_forwarded_headers: bool,
+
+    /// Origins allowed to make cross-origin requests, `*` allowing all of them
+    ///
+    /// When empty, only the bundled web client is allowed, i.e. origins on the same host
+    /// as the server and on the web client's port ([`WEB_CLIENT_PORT`]).
+    pub cors_origins: Vec<String>,
+
+    /// Serve HTTPS instead of plain HTTP
+    pub tls: Option<TlsSettings>,
+
+    /// Listen on a Unix socket instead of a TCP address
+    pub unix_socket: Option<UnixSocketSettings>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 8891,
+            auto_playlists: true,
+            base_path: "/".to_owned(),
+            trust_forwarded_headers: false,
+            cors_origins: vec![],
+            tls: None,
+            unix_socket: None,
+        }
+    }
+}
+
+impl ServerSettings {
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.base_path.starts_with('/'),
+            "Base path '{}' must start with a '/'",
+            self.base_path
+        );
+
+        ensure!(
+            !self.base_path.contains(['?', '#', '{', '}']),
+            "Base path '{}' contains invalid characters",
+            self.base_path
+        );
+
+        // Only ensure the origins are valid, the CORS layer itself is built when launching
+        drop(self.allowed_origins()?);
+
+        if let Some(tls) = &self.tls {
+            tls.validate(self.port).context("Invalid TLS settings")?;
+        }
+
+        if let Some(unix_socket) = &self.unix_socket {
+            ensure!(
+                self.tls.is_none(),
+                "TLS cannot be used when listening on a Unix socket"
+            );
+
+            unix_socket
+                .validate()
+                .context("Invalid Unix socket settings")?;
+        }
+
+        Ok(())
+    }
+
+    /// Origins allowed to make cross-origin requests, parsed from [`Self::cors_origins`]
+    pub fn allowed_origins(&self) -> Result<AllowOrigin> {
+        if self.cors_origins.is_empty() {
+            return Ok(AllowOrigin::predicate(is_web_client_origin));
+        }
+
+        if self.cors_origins.iter().any(|origin| origin == "*") {
+            return Ok(AllowOrigin::any());
+        }
+
+        let origins = self
+            .cors_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .with_context(|| format!("Invalid CORS origin '{origin}'"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(AllowOrigin::list(origins))
+    }
+
+    /// Base path without its trailing slash, empty when serving from the root
+    pub fn base_path_prefix(&self) -> &str {
+        self.base_path.trim_end_matches('/')
+    }
+}
+
+/// Check if an origin is the bundled web client's, served on the same host as the server
+fn is_web_client_origin(origin: &HeaderValue, parts: &Parts) -> bool {
+    let Some(origin) = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.parse::<Uri>().ok())
+    else {
+        return false;
+    };
+
+    let server_host = parts
+        .headers
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok())
+        .or_else(|| parts.uri.authority().cloned());
+
+    let (Some(server_host), Some(origin_host)) = (server_host, origin.host()) else {
+        return false;
+    };
+
+    matches!(origin.scheme_str(), Some("http" | "https"))
+        && origin.port_u16() == Some(WEB_CLIENT_PORT)
+        && origin_host.eq_ignore_ascii_case(server_host.host())
+}
+
+pub async fn launch(data_manager: DataManager) -> Result<()> {
+    let settings = data_manager.settings().server.clone();
+
     let cors = CorsLayer::new()
         .allow_methods(AllowMethods::any())
-        .allow_origin(AllowOrigin::any())
+        .allow_origin(settings.allowed_origins()?)
         .allow_headers(AllowHeaders::any());
 
     // Add compression
     let compression = CompressionLayer::new().gzip(true);
 
-    let state = HttpState(Arc::new(data_manager));
+    let state = HttpState {
+        data_manager: Arc::new(data_manager),
+    };
 
-    let app = router()
+    let app = router(state.clone())
         // Set up OpenSubsonic routes
-        .nest(OPENSUBSONIC_BASE_URI, opensubsonic::router())
+        .nest(OPENSUBSONIC_BASE_URI, opensubsonic::router(state.clone()))
         // Set up shared state
-        .with_state(state)
+        .with_state(state.clone());
+
+    // Serve all routes under the base path
+    let app = match settings.base_path_prefix() {
+        "" => app,
+        base_path => Router::new().nest(base_path, app),
+    };
+
+    let app = app
         // Set up CORS headers
         .layer(cors)
         // Set up compression (TODO: don't apply to media files)
@@ -44,25 +216,147 @@ pub async fn launch(addr: SocketAddr, data_manager: DataManager) -> Result<()> {
         // Set up errors logging
         .layer(middleware::from_fn(log_errors));
 
+    let listener = ServerListener::open(&settings).await?;
+
     info!(
-        "> Server is being launched on {}",
-        addr.to_string().bright_green()
+        "> Server is being launched on {}{}",
+        listener.describe()?.bright_green(),
+        if settings.tls.is_some() {
+            " (HTTPS)"
+        } else {
+            ""
+        }
     );
 
-    let listener = TcpListener::bind(addr).await?;
+    if !settings.base_path_prefix().is_empty() {
+        info!(
+            "> Serving routes under {}",
+            settings.base_path_prefix().bright_green()
+        );
+    }
+
+    match (listener, &settings.tls) {
+        (ServerListener::Tcp(listener), None) => serve(listener, app).await?,
+
+        (ServerListener::Tcp(listener), Some(tls)) => {
+            let https = serve(TlsListener::new(listener, tls)?, app);
+
+            match tls.http_redirect_port {
+                Some(http_port) => {
+                    let http_addr = SocketAddr::new(settings.addr, http_port);
+                    tokio::try_join!(https, serve_https_redirect(http_addr, settings.port))?;
+                }
+
+                None => https.await?,
+            }
+        }
+
+        #[cfg(unix)]
+        (ServerListener::Unix(listener, socket_file), None) => {
+            serve(listener, app).await?;
+
+            // Remove the socket file now that nothing listens on it anymore
+            drop(socket_file);
+        }
+
+        #[cfg(unix)]
+        (ServerListener::Unix(..), Some(_)) => {
+            bail!("TLS cannot be used when listening on a Unix socket")
+        }
+    }
+
+    // Don't exit in the middle of an index update, as it would leave partially-written files
+    info!("> Server stopped, waiting for pending tasks to complete...");
 
-    axum::serve(listener, app).await?;
+    spawn_blocking(move || state.wait_for_index_update())
+        .await
+        .unwrap();
 
     Ok(())
 }
 
+/// Serve the application until a shutdown signal is received
+///
+/// Pending requests are allowed to complete before returning.
+async fn serve<L>(listener: L, app: Router) -> Result<()>
+where
+    L: Listener,
+    L::Addr: std::fmt::Debug,
+{
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    Ok(())
+}
+
+/// Wait for either Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
 #[derive(Clone)]
-struct HttpState(Arc<DataManager>);
+struct HttpState {
+    data_manager: Arc<DataManager>,
+}
 
 impl Deref for HttpState {
     type Target = DataManager;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.data_manager
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    #[test]
+    fn test_web_client_origin() {
+        for (host, origin, expected) in [
+            ("192.168.1.10:8891", "http://192.168.1.10:8892", true),
+            ("music.local:8891", "https://Music.local:8892", true),
+            ("[::1]:8891", "http://[::1]:8892", true),
+            ("192.168.1.10:8891", "http://192.168.1.10:8893", false),
+            ("192.168.1.10:8891", "http://192.168.1.10", false),
+            ("192.168.1.10:8891", "http://evil.example:8892", false),
+            ("192.168.1.10:8891", "ftp://192.168.1.10:8892", false),
+            ("192.168.1.10:8891", "null", false),
+        ] {
+            let (parts, ()) = Request::builder()
+                .header(HOST, host)
+                .body(())
+                .unwrap()
+                .into_parts();
+
+            assert_eq!(
+                is_web_client_origin(&HeaderValue::from_static(origin), &parts),
+                expected,
+                "Unexpected result for origin '{origin}' on host '{host}'"
+            );
+        }
     }
 }
@@ -0,0 +1,372 @@
+//!
+//! Authentication of `OpenSubsonic` requests
+//!
+//! Three mechanisms are supported, as described by the specification:
+//!
+//! * Plain password, provided as-is or hex-encoded with an `enc:` prefix (`u` and `p` parameters)
+//! * Salted token, which is the MD5 hash of the password followed by a random salt (`u`, `t` and `s` parameters)
+//! * API key, from the API key authentication extension (`apiKey` parameter)
+//!
+
+use axum::{
+    extract::{FromRequestParts, Query, Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{
+    server::HttpState,
+    userdata::{User, UserID, Users},
+    utils::secrets_eq,
+};
+
+use super::{OSError, error_response};
+
+/// User an `OpenSubsonic` request was authenticated as
+///
+/// Only available on routes behind the [`authenticate`] middleware.
+#[derive(Clone, Copy)]
+pub struct OSUser(pub UserID);
+
+impl FromRequestParts<HttpState> for OSUser {
+    type Rejection = OSError;
+
+    async fn from_request_parts(parts: &mut Parts, _: &HttpState) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Self>()
+            .copied()
+            .ok_or(OSError("Request was not authenticated"))
+    }
+}
+
+/// Credentials of the user an `OpenSubsonic` request was authenticated as
+///
+/// Clients fetch some of the URIs they are handed out as-is (e.g. the images of `getArtistInfo2`),
+/// so these URIs need to authenticate as the same user. They always use the user's API key,
+/// as the credentials the request was made with may contain the plain password.
+#[derive(Clone)]
+pub struct OSCredentials(String);
+
+impl OSCredentials {
+    fn new(user: &User) -> Self {
+        Self(serde_urlencoded::to_string([("apiKey", &user.api_key)]).unwrap())
+    }
+
+    /// Credentials encoded as query parameters (e.g. `apiKey=...`)
+    pub fn query(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromRequestParts<HttpState> for OSCredentials {
+    type Rejection = OSError;
+
+    async fn from_request_parts(parts: &mut Parts, _: &HttpState) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Self>()
+            .cloned()
+            .ok_or(OSError("Request was not authenticated"))
+    }
+}
+
+/// Mechanism an `OpenSubsonic` request was authenticated with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OSAuthMethod {
+    Password,
+    Token,
+    ApiKey,
+}
+
+impl FromRequestParts<HttpState> for OSAuthMethod {
+    type Rejection = OSError;
+
+    async fn from_request_parts(parts: &mut Parts, _: &HttpState) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Self>()
+            .copied()
+            .ok_or(OSError("Request was not authenticated"))
+    }
+}
+
+/// Authentication failure, with its error code from the specification
+///
+/// Code 41 (token authentication not supported for LDAP users) is never returned,
+/// as every account supports all authentication mechanisms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OSAuthError {
+    MissingParameter,
+    WrongCredentials,
+    UnsupportedMechanism,
+    ConflictingMechanisms,
+    InvalidApiKey,
+}
+
+impl OSAuthError {
+    fn code_and_message(self) -> (u16, &'static str) {
+        match self {
+            Self::MissingParameter => (10, "Required parameter is missing"),
+            Self::WrongCredentials => (40, "Wrong username or password"),
+            Self::UnsupportedMechanism => (42, "Provided authentication mechanism not supported"),
+            Self::ConflictingMechanisms => (
+                43,
+                "Multiple conflicting authentication mechanisms provided",
+            ),
+            Self::InvalidApiKey => (44, "Invalid API key"),
+        }
+    }
+}
+
+impl IntoResponse for OSAuthError {
+    fn into_response(self) -> Response {
+        let (code, message) = self.code_and_message();
+        error_response(code, message)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthParams {
+    u: Option<String>,
+    p: Option<String>,
+    t: Option<String>,
+    s: Option<String>,
+    api_key: Option<String>,
+}
+
+/// Middleware rejecting requests that are not properly authenticated
+///
+/// The authenticated user, mechanism and credentials are made available to handlers through
+/// the [`OSUser`], [`OSAuthMethod`] and [`OSCredentials`] extractors.
+pub async fn authenticate(
+    State(state): State<HttpState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Ok(Query(params)) = Query::<AuthParams>::try_from_uri(request.uri()) else {
+        return OSAuthError::MissingParameter.into_response();
+    };
+
+    match check_credentials(state.users(), params) {
+        Ok((user, method)) => {
+            request.extensions_mut().insert(OSUser(user.id));
+            request.extensions_mut().insert(method);
+            request.extensions_mut().insert(OSCredentials::new(user));
+
+            next.run(request).await
+        }
+
+        Err(err) => err.into_response(),
+    }
+}
+
+fn check_credentials(
+    users: &Users,
+    params: AuthParams,
+) -> Result<(&User, OSAuthMethod), OSAuthError> {
+    let AuthParams {
+        u,
+        p,
+        t,
+        s,
+        api_key,
+    } = params;
+
+    if let Some(api_key) = api_key {
+        if u.is_some() || p.is_some() || t.is_some() || s.is_some() {
+            return Err(OSAuthError::ConflictingMechanisms);
+        }
+
+        let user = users
+            .values()
+            .find(|user| secrets_eq(&user.api_key, &api_key))
+            .ok_or(OSAuthError::InvalidApiKey)?;
+
+        return Ok((user, OSAuthMethod::ApiKey));
+    }
+
+    let username = u.ok_or(OSAuthError::MissingParameter)?;
+
+    // Don't tell apart unknown users from wrong passwords
+    let user = users
+        .values()
+        .find(|user| user.name == username)
+        .ok_or(OSAuthError::WrongCredentials)?;
+
+    let (valid, method) = match (p, t, s) {
+        (Some(password), None, None) => (
+            decode_password(&password)
+                .is_some_and(|password| secrets_eq(&password, &user.password)),
+            OSAuthMethod::Password,
+        ),
+
+        (None, Some(token), Some(salt)) => (
+            secrets_eq(
+                &token.to_ascii_lowercase(),
+                &salted_token(&user.password, &salt),
+            ),
+            OSAuthMethod::Token,
+        ),
+
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+            return Err(OSAuthError::ConflictingMechanisms);
+        }
+
+        (None, _, _) => return Err(OSAuthError::MissingParameter),
+    };
+
+    if valid {
+        Ok((user, method))
+    } else {
+        Err(OSAuthError::WrongCredentials)
+    }
+}
+
+/// Decode a password provided either as-is or hex-encoded with an `enc:` prefix
+fn decode_password(password: &str) -> Option<String> {
+    match password.strip_prefix("enc:") {
+        Some(encoded) => String::from_utf8(hex::decode(encoded).ok()?).ok(),
+        None => Some(password.to_owned()),
+    }
+}
+
+/// Compute the token a client is expected to send for a given password and salt
+fn salted_token(password: &str, salt: &str) -> String {
+    format!("{:x}", md5::compute(format!("{password}{salt}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Uri;
+
+    use super::*;
+    use crate::userdata::UserRole;
+
+    fn users() -> Users {
+        let mut alice = User::new("alice", UserRole::Admin).unwrap();
+        alice.password = "sesame".to_owned();
+        alice.api_key = "alice-key".to_owned();
+
+        let mut bob = User::new("bob", UserRole::Listener).unwrap();
+        bob.password = "hunter2".to_owned();
+        bob.api_key = "bob-key".to_owned();
+
+        Users::from([(alice.id, alice), (bob.id, bob)])
+    }
+
+    fn check<'a>(users: &'a Users, query: &str) -> Result<(&'a str, OSAuthMethod), u16> {
+        let uri = format!("/rest/ping?{query}").parse::<Uri>().unwrap();
+        let Query(params) = Query::<AuthParams>::try_from_uri(&uri).unwrap();
+
+        check_credentials(users, params)
+            .map(|(user, method)| (user.name.as_str(), method))
+            .map_err(|err| err.code_and_message().0)
+    }
+
+    #[test]
+    fn test_check_credentials() {
+        let users = users();
+
+        // MD5 of 'sesame' followed by 'c19b2d'
+        let alice_token = salted_token("sesame", "c19b2d");
+
+        let cases = [
+            // Plain passwords
+            ("u=alice&p=sesame", Ok(("alice", OSAuthMethod::Password))),
+            ("u=bob&p=hunter2", Ok(("bob", OSAuthMethod::Password))),
+            ("u=alice&p=hunter2", Err(40)),
+            ("u=carol&p=sesame", Err(40)),
+            // Hex-encoded passwords
+            (
+                "u=alice&p=enc:736573616d65",
+                Ok(("alice", OSAuthMethod::Password)),
+            ),
+            (
+                "u=alice&p=enc:736573616D65",
+                Ok(("alice", OSAuthMethod::Password)),
+            ),
+            ("u=alice&p=enc:7365", Err(40)),
+            ("u=alice&p=enc:not-hex", Err(40)),
+            // Salted tokens
+            (
+                &format!("u=alice&t={alice_token}&s=c19b2d"),
+                Ok(("alice", OSAuthMethod::Token)),
+            ),
+            (
+                &format!("u=alice&t={}&s=c19b2d", alice_token.to_uppercase()),
+                Ok(("alice", OSAuthMethod::Token)),
+            ),
+            (&format!("u=alice&t={alice_token}&s=other"), Err(40)),
+            (&format!("u=bob&t={alice_token}&s=c19b2d"), Err(40)),
+            // API keys
+            ("apiKey=alice-key", Ok(("alice", OSAuthMethod::ApiKey))),
+            ("apiKey=bob-key", Ok(("bob", OSAuthMethod::ApiKey))),
+            ("apiKey=sesame", Err(44)),
+            // Missing parameters
+            ("", Err(10)),
+            ("p=sesame", Err(10)),
+            ("u=alice", Err(10)),
+            (&format!("u=alice&t={alice_token}"), Err(10)),
+            ("u=alice&s=c19b2d", Err(10)),
+            // Conflicting mechanisms
+            ("apiKey=alice-key&u=alice", Err(43)),
+            ("apiKey=alice-key&p=sesame", Err(43)),
+            ("apiKey=alice-key&t=abc&s=def", Err(43)),
+            ("u=alice&p=sesame&t=abc&s=def", Err(43)),
+            ("u=alice&p=sesame&s=def", Err(43)),
+        ];
+
+        for (query, expected) in cases {
+            assert_eq!(check(&users, query), expected, "query: {query}");
+        }
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let cases = [
+            (OSAuthError::MissingParameter, 10),
+            (OSAuthError::WrongCredentials, 40),
+            (OSAuthError::UnsupportedMechanism, 42),
+            (OSAuthError::ConflictingMechanisms, 43),
+            (OSAuthError::InvalidApiKey, 44),
+        ];
+
+        for (err, code) in cases {
+            assert_eq!(err.code_and_message().0, code, "error: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_credentials_in_generated_uris() {
+        let users = users();
+
+        // URIs handed out to clients must authenticate as the same user,
+        // without ever containing their password
+        for query in [
+            "u=alice&p=enc:736573616d65",
+            "u=bob&p=hunter2",
+            "apiKey=bob-key",
+            &format!("u=alice&t={}&s=c%2Bd%20e", salted_token("sesame", "c+d e")),
+        ] {
+            let (user, _) = check(&users, query).unwrap();
+            let user = users.values().find(|u| u.name == user).unwrap();
+
+            let generated = format!(
+                "/rest/getCoverArt?id=ar-abc&size=64&{}",
+                OSCredentials::new(user).query()
+            );
+
+            assert!(!generated.contains("p="), "query: {query}");
+            assert!(!generated.contains(&user.password), "query: {query}");
+            assert_eq!(
+                check(&users, &generated).map(|(name, _)| name),
+                check(&users, query).map(|(name, _)| name),
+                "query: {query}"
+            );
+        }
+    }
+}
@@ -4,15 +4,24 @@ use indexmap::IndexSet;
 
 use crate::{
     index::{Album, Artist, IdType, IndexCache, Rating, Track},
-    manager::Ratings,
+    manager::UserDataManager,
+    userdata::{Ratings, StarredItem},
 };
 
 use super::types::{AlbumID3WithSongs, ArtistID3, Child, CoverArtId, ItemGenre};
 
-pub fn track_to_child(track: &Track, index: &IndexCache, ratings: &Ratings) -> Child {
+pub fn track_to_child(
+    track: &Track,
+    index: &IndexCache,
+    ratings: &Ratings,
+    data: &UserDataManager,
+) -> Child {
     let tags = &track.tags;
 
+    let play_stats = data.track_play_stats(track.id);
+
     let rating = ratings
+        .tracks
         .get(&track.id)
         .map(|rating| Rating::get_zero_to_five(*rating));
 
@@ -34,7 +43,7 @@ pub fn track_to_child(track: &Track, index: &IndexCache, ratings: &Ratings) -> C
             .genres_id
             .first()
             .map(|genre_id| index.genres.get(genre_id).unwrap().name.clone()), // OK?
-        covert_art_id: None, // TODO
+        covert_art_id: Some(CoverArtId::Track(track.id)),
         size_bytes: Some(track.file_size_bytes),
         mime_type: None,      // TODO
         file_extension: None, // TODO
@@ -47,16 +56,18 @@ pub fn track_to_child(track: &Track, index: &IndexCache, ratings: &Ratings) -> C
         is_video: Some(false),
         user_rating_1_to_5: rating,
         average_rating_1_to_5: rating.map(f32::from),
-        play_count: None, // TODO (requires caching)
+        play_count: Some(usize::try_from(play_stats.play_count).unwrap()),
         disc_number: tags.disc_number.map(Into::into),
         created_iso_8601: Some(to_iso_8601(
             track.file_times.ctime.unwrap_or(track.file_times.mtime),
         )),
-        starred_iso_8601: None, // TODO?
+        starred_iso_8601: data
+            .starred_at(StarredItem::Track(track.id))
+            .map(to_iso_8601),
         album_id: Some(tags.album_id.encode()),
         artist_id: tags.artists_id.first().map(IdType::encode), // OK?
         typ: Some("music"),
-        last_played_iso_8601: None, // TODO: (requires caching)
+        last_played_iso_8601: play_stats.last_played_at.map(to_iso_8601),
         bpm: None,
         comment: None,
         sort_name: None,
@@ -76,14 +87,14 @@ pub fn track_to_child(track: &Track, index: &IndexCache, ratings: &Ratings) -> C
         artists: Some(
             tags.artists_id
                 .iter()
-                .map(|artist_id| artist_to_id3(index.artists.get(artist_id).unwrap(), index))
+                .map(|artist_id| artist_to_id3(index.artists.get(artist_id).unwrap(), index, data))
                 .collect(),
         ),
         album_artists: Some(
             album
                 .artists_id
                 .iter()
-                .map(|artist_id| artist_to_id3(index.artists.get(artist_id).unwrap(), index))
+                .map(|artist_id| artist_to_id3(index.artists.get(artist_id).unwrap(), index, data))
                 .collect(),
         ),
         contributors: None, // TODO?
@@ -94,6 +105,7 @@ pub fn album_to_id3_with_songs(
     album: &Album,
     index: &IndexCache,
     ratings: &Ratings,
+    data: &UserDataManager,
 ) -> AlbumID3WithSongs {
     let album_tracks = index.albums_tracks.get(&album.id).unwrap();
     let album_tracks = album_tracks
@@ -101,6 +113,11 @@ pub fn album_to_id3_with_songs(
         .map(|track| index.tracks.get(track).unwrap())
         .collect::<Vec<_>>();
 
+    let play_stats = album_tracks
+        .iter()
+        .map(|track| data.track_play_stats(track.id))
+        .collect::<Vec<_>>();
+
     // TODO: good idea?
     let first_artist = index
         .artists
@@ -119,7 +136,12 @@ pub fn album_to_id3_with_songs(
             .iter()
             .map(|track| track.metadata.duration_s)
             .sum(),
-        play_count: None, // TODO (possible to compute?)
+        play_count: Some(
+            play_stats
+                .iter()
+                .map(|stats| usize::try_from(stats.play_count).unwrap())
+                .sum(),
+        ),
         created_iso_8601: to_iso_8601(
             album_tracks
                 .iter()
@@ -127,11 +149,20 @@ pub fn album_to_id3_with_songs(
                 .min()
                 .unwrap(),
         ),
-        starred_iso_8601: None,
+        starred_iso_8601: data
+            .starred_at(StarredItem::Album(album.id))
+            .map(to_iso_8601),
         year: None,
         genre: None,
-        last_played_iso_8601: None, // TODO (requires caching)
-        user_rating_1_to_5: None,
+        last_played_iso_8601: play_stats
+            .iter()
+            .filter_map(|stats| stats.last_played_at)
+            .max()
+            .map(to_iso_8601),
+        user_rating_1_to_5: ratings
+            .albums
+            .get(&album.id)
+            .map(|rating| rating.get_zero_to_five()),
         genres: Some(
             album_tracks
                 .iter()
@@ -148,7 +179,7 @@ pub fn album_to_id3_with_songs(
             album
                 .artists_id
                 .iter()
-                .map(|artist| artist_to_id3(index.artists.get(artist).unwrap(), index))
+                .map(|artist| artist_to_id3(index.artists.get(artist).unwrap(), index, data))
                 .collect(),
         ),
         display_artist_name: None,
@@ -159,12 +190,17 @@ pub fn album_to_id3_with_songs(
         explicit_status: None,
         tracks: album_tracks
             .iter()
-            .map(|track| track_to_child(track, index, ratings))
+            .map(|track| track_to_child(track, index, ratings, data))
             .collect(),
     }
 }
 
-pub fn album_to_child(album: &Album, index: &IndexCache) -> Child {
+pub fn album_to_child(
+    album: &Album,
+    index: &IndexCache,
+    ratings: &Ratings,
+    data: &UserDataManager,
+) -> Child {
     let album_tracks = index.albums_tracks.get(&album.id).unwrap();
 
     let album_tracks = album_tracks
@@ -204,9 +240,12 @@ pub fn album_to_child(album: &Album, index: &IndexCache) -> Child {
         channel_count: None,
         path: None, // TODO?
         is_video: Some(false),
-        user_rating_1_to_5: None,
-        average_rating_1_to_5: None, // TODO (requires caching)
-        play_count: None,            // TODO (possible to compute?)
+        user_rating_1_to_5: ratings
+            .albums
+            .get(&album.id)
+            .map(|rating| rating.get_zero_to_five()),
+        average_rating_1_to_5: ratings.album_tracks_average(album.id, index),
+        play_count: None, // TODO (possible to compute?)
         disc_number: None,
         // TODO: optimize
         created_iso_8601: Some(to_iso_8601(
@@ -216,7 +255,9 @@ pub fn album_to_child(album: &Album, index: &IndexCache) -> Child {
                 .min()
                 .unwrap(),
         )),
-        starred_iso_8601: None,
+        starred_iso_8601: data
+            .starred_at(StarredItem::Album(album.id))
+            .map(to_iso_8601),
         album_id: Some(album.id.encode()),
         artist_id: Some(first_artist.id.encode()),
         typ: Some("music"),
@@ -246,7 +287,7 @@ pub fn album_to_child(album: &Album, index: &IndexCache) -> Child {
             album
                 .artists_id
                 .iter()
-                .map(|artist| artist_to_id3(index.artists.get(artist).unwrap(), index))
+                .map(|artist| artist_to_id3(index.artists.get(artist).unwrap(), index, data))
                 .collect(),
         ),
 
@@ -254,7 +295,7 @@ pub fn album_to_child(album: &Album, index: &IndexCache) -> Child {
             album
                 .artists_id
                 .iter()
-                .map(|artist| artist_to_id3(index.artists.get(artist).unwrap(), index))
+                .map(|artist| artist_to_id3(index.artists.get(artist).unwrap(), index, data))
                 .collect(),
         ),
 
@@ -264,14 +305,16 @@ pub fn album_to_child(album: &Album, index: &IndexCache) -> Child {
     }
 }
 
-pub fn artist_to_id3(artist: &Artist, index: &IndexCache) -> ArtistID3 {
+pub fn artist_to_id3(artist: &Artist, index: &IndexCache, data: &UserDataManager) -> ArtistID3 {
     ArtistID3 {
         id: artist.id,
         name: artist.name.clone(),
         covert_art_id: Some(CoverArtId::Artist(artist.id)),
         artist_image_url: None, // TODO
         album_count: index.artists_albums.get(&artist.id).map(IndexSet::len),
-        starred_iso_8601: None,
+        starred_iso_8601: data
+            .starred_at(StarredItem::Artist(artist.id))
+            .map(to_iso_8601),
         music_brainz_id: None,
         sort_name: None,
     }
@@ -5,6 +5,7 @@
 //!
 //! Note that some features are explicitly out of scope, such as XML support.
 
+mod auth;
 mod convert;
 mod routes;
 mod types;
@@ -18,6 +19,8 @@ use serde_json::json;
 
 pub use self::routes::router;
 
+use self::auth::{OSAuthError, OSAuthMethod, OSCredentials, OSUser};
+
 /// Result of an `OpenSubsonic` request, which is either a successful response or a formatted error.
 type OSResult<T> = Result<T, OSError>;
 
@@ -29,30 +32,34 @@ pub struct OSError(&'static str);
 impl IntoResponse for OSError {
     fn into_response(self) -> Response {
         let Self(message) = self;
+        error_response(0, message)
+    }
+}
 
-        let response = json!({
-            "subsonic-response": {
-                "status": "failed",
-                "version": "1.16.1",
-                "type": "HifyServer",
-                "serverVersion": env!("CARGO_PKG_VERSION"),
-                "openSubsonic": true,
-                "error": {
-                    "code": 0,
-                    "message": message,
-                }
+/// Build a failed `OpenSubsonic` response with the provided error code
+fn error_response(code: u16, message: &str) -> Response {
+    let response = json!({
+        "subsonic-response": {
+            "status": "failed",
+            "version": "1.16.1",
+            "type": "HifyServer",
+            "serverVersion": env!("CARGO_PKG_VERSION"),
+            "openSubsonic": true,
+            "error": {
+                "code": code,
+                "message": message,
             }
-        });
+        }
+    });
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "Content-Type",
-            HeaderValue::from_str("application/json").unwrap(),
-        );
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Content-Type",
+        HeaderValue::from_str("application/json").unwrap(),
+    );
 
-        let body = serde_json::to_string(&response).unwrap();
-        (headers, body).into_response()
-    }
+    let body = serde_json::to_string(&response).unwrap();
+    (headers, body).into_response()
 }
 
 impl From<&'static str> for OSError {
@@ -1,73 +1,200 @@
+use std::time::{Duration, SystemTime};
+
 use axum::extract::{Query, State};
 use serde::Deserialize;
 
 use crate::{
-    index::Rating,
+    index::{AlbumID, ArtistID, IdType, IndexCache, Rating, TrackID},
+    manager::UserDataManager,
     server::{
         HttpState,
-        opensubsonic::{OSEmptyResponse, OSError, OSResult, types::CoverArtId},
+        opensubsonic::{OSEmptyResponse, OSError, OSResult, OSUser, types::CoverArtId},
     },
+    userdata::{Listen, RatedItem, StarredItem},
 };
 
-use super::OpenSubsonicRouter;
+use super::{MultiParams, OpenSubsonicRouter};
 
 pub fn router() -> OpenSubsonicRouter {
     OpenSubsonicRouter::new()
         .route("/setRating", set_rating)
         .route("/scrobble", scrobble)
+        .route("/star", star)
+        .route("/unstar", unstar)
 }
 
 #[derive(Deserialize)]
 pub struct SetRatingParams {
-    id: CoverArtId, // May be an album or artist (folder) ID
+    id: String, // May be a track, album or artist ID
     rating: u8,
 }
 
 async fn set_rating(
     Query(SetRatingParams { id, rating }): Query<SetRatingParams>,
     State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
 ) -> OSResult<OSEmptyResponse> {
+    let data = state.user_data(user_id);
+
     let rating = if rating != 0 {
         Some(Rating::try_from(rating).map_err(|()| "Invalid rating provided")?)
     } else {
         None
     };
 
-    let index = state.index().await;
-
-    match id {
-        CoverArtId::Track(track_id) => {
-            if !index.tracks.contains_key(&track_id) {
-                return Err(OSError("Provided ID was not found"));
-            }
-
-            match rating {
-                Some(rating) => {
-                    state
-                        .set_track_rating(track_id, rating)
-                        // TODO: pass error message to returner
-                        .await
-                        .map_err(|_| "Failed to set rating")?;
-                }
-
-                None => {
-                    state
-                        .remove_track_rating(track_id)
-                        .await
-                        .map_err(|_| "Failed to remove rating")?;
-                }
-            }
-
-            Ok(OSEmptyResponse)
-        }
+    let item = match resolve_id(&id, &*data.index().await).ok_or("Provided ID was not found")? {
+        CoverArtId::Track(track_id) => RatedItem::Track(track_id),
+        CoverArtId::Album(album_id) => RatedItem::Album(album_id),
+        CoverArtId::Artist(artist_id) => RatedItem::Artist(artist_id),
+        CoverArtId::Playlist(_) => return Err(OSError("Playlists cannot be rated")),
+    };
 
-        CoverArtId::Album(_) => Err(OSError("TODO: albums")),
+    data.set_rating(item, rating)
+        // TODO: pass error message to returner
+        .await
+        .map_err(|_| "Failed to update rating")?;
 
-        CoverArtId::Artist(_) => Err(OSError("TODO: artists")),
+    Ok(OSEmptyResponse)
+}
+
+/// Find what an ID provided by a client refers to
+///
+/// Clients usually provide plain IDs, which may refer to a track, an album or an artist.
+fn resolve_id(id: &str, index: &IndexCache) -> Option<CoverArtId> {
+    if let Ok(id) = CoverArtId::decode(id) {
+        return Some(id);
+    }
+
+    if let Ok(track_id) = TrackID::decode(id)
+        && index.tracks.contains_key(&track_id)
+    {
+        Some(CoverArtId::Track(track_id))
+    } else if let Ok(album_id) = AlbumID::decode(id)
+        && index.albums.contains_key(&album_id)
+    {
+        Some(CoverArtId::Album(album_id))
+    } else if let Ok(artist_id) = ArtistID::decode(id)
+        && index.artists.contains_key(&artist_id)
+    {
+        Some(CoverArtId::Artist(artist_id))
+    } else {
+        None
     }
 }
 
-// (TODO?) Scrobbling is not supported, so a placeholder handler is put here
-async fn scrobble() -> OSEmptyResponse {
-    OSEmptyResponse
+// NOTE: "now playing" notifications (`submission=false`) are accepted but not recorded
+async fn scrobble(
+    Query(params): Query<MultiParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResult<OSEmptyResponse> {
+    let data = state.user_data(user_id);
+
+    let submission = match params.get("submission") {
+        None | Some("true") => true,
+        Some("false") => false,
+        Some(_) => return Err(OSError("Invalid submission parameter")),
+    };
+
+    let track_ids = params
+        .get_all("id")
+        .map(TrackID::decode)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Invalid track ID provided")?;
+
+    if track_ids.is_empty() {
+        return Err(OSError("No track ID provided"));
+    }
+
+    // Times are provided in milliseconds since the Unix epoch, one for each track
+    let times = params
+        .get_all("time")
+        .map(|time| time.parse::<u64>().map(Duration::from_millis))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Invalid time provided")?;
+
+    if !times.is_empty() && times.len() != track_ids.len() {
+        return Err(OSError(
+            "Number of times does not match the number of track IDs",
+        ));
+    }
+
+    if !submission {
+        return Ok(OSEmptyResponse);
+    }
+
+    let client = params.get("c").map(str::to_owned);
+
+    for (i, track_id) in track_ids.into_iter().enumerate() {
+        let listened_at = times
+            .get(i)
+            .map_or_else(SystemTime::now, |time| SystemTime::UNIX_EPOCH + *time);
+
+        data.record_listen(Listen {
+            track_id,
+            listened_at,
+            client: client.clone(),
+            // Submissions are only sent for tracks that were (mostly) listened to
+            completion: 1.0,
+        })
+        .await
+        .map_err(|_| "Failed to record scrobble")?;
+    }
+
+    Ok(OSEmptyResponse)
+}
+
+async fn star(
+    Query(params): Query<MultiParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResult<OSEmptyResponse> {
+    set_starred(&params, &state.user_data(user_id), true).await
+}
+
+async fn unstar(
+    Query(params): Query<MultiParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResult<OSEmptyResponse> {
+    set_starred(&params, &state.user_data(user_id), false).await
+}
+
+async fn set_starred(
+    params: &MultiParams,
+    data: &UserDataManager<'_>,
+    starred: bool,
+) -> OSResult<OSEmptyResponse> {
+    let mut items = vec![];
+
+    {
+        let index = data.index().await;
+
+        for id in params.get_all("id") {
+            let item = match resolve_id(id, &index).ok_or("Provided ID was not found")? {
+                CoverArtId::Track(track_id) => StarredItem::Track(track_id),
+                CoverArtId::Album(album_id) => StarredItem::Album(album_id),
+                CoverArtId::Artist(artist_id) => StarredItem::Artist(artist_id),
+                CoverArtId::Playlist(_) => return Err(OSError("Playlists cannot be starred")),
+            };
+
+            items.push(item);
+        }
+    }
+
+    for album_id in params.get_all("albumId") {
+        let album_id = AlbumID::decode(album_id).map_err(|_| "Invalid album ID provided")?;
+        items.push(StarredItem::Album(album_id));
+    }
+
+    for artist_id in params.get_all("artistId") {
+        let artist_id = ArtistID::decode(artist_id).map_err(|_| "Invalid artist ID provided")?;
+        items.push(StarredItem::Artist(artist_id));
+    }
+
+    data.set_starred(&items, starred)
+        .await
+        .map_err(|_| "Failed to update stars")?;
+
+    Ok(OSEmptyResponse)
 }
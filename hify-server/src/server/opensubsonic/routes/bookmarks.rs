@@ -1,19 +1,30 @@
 use std::time::SystemTime;
 
-use serde::Serialize;
+use axum::extract::{Query, State};
+use serde::{Deserialize, Serialize};
 
-use crate::server::opensubsonic::{
-    OSNestedResponse,
-    convert::to_iso_8601,
-    types::{Bookmark, PlayQueue},
+use crate::{
+    index::{IdType, TrackID},
+    server::{
+        HttpState,
+        opensubsonic::{
+            OSEmptyResponse, OSNestedResponse, OSResult, OSUser,
+            convert::{to_iso_8601, track_to_child},
+            types::{Bookmark, PlayQueue},
+        },
+    },
+    userdata,
 };
 
-use super::OpenSubsonicRouter;
+use super::{MultiParams, OpenSubsonicRouter};
 
 pub fn router() -> OpenSubsonicRouter {
     OpenSubsonicRouter::new()
         .route("/getBookmarks", get_bookmarks)
+        .route("/createBookmark", create_bookmark)
+        .route("/deleteBookmark", delete_bookmark)
         .route("/getPlayQueue", get_play_queue)
+        .route("/savePlayQueue", save_play_queue)
 }
 
 #[derive(Serialize)]
@@ -21,20 +32,176 @@ pub struct GetBookmarksAnswer {
     pub bookmark: Vec<Bookmark>,
 }
 
-async fn get_bookmarks() -> OSNestedResponse<GetBookmarksAnswer> {
-    OSNestedResponse("bookmarks", GetBookmarksAnswer { bookmark: vec![] })
+async fn get_bookmarks(
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSNestedResponse<GetBookmarksAnswer> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let bookmarks = data.bookmarks().await.values().cloned().collect::<Vec<_>>();
+
+    let bookmark = bookmarks
+        .into_iter()
+        // Tracks may have disappeared from the index since the bookmark was created
+        .filter_map(|bookmark| {
+            let track = index.tracks.get(&bookmark.track_id)?;
+
+            Some(Bookmark {
+                position: bookmark.position_ms,
+                username: data.user().name.clone(),
+                comment: bookmark.comment,
+                created_iso_8601: to_iso_8601(bookmark.created_at),
+                changed_iso_8601: to_iso_8601(bookmark.changed_at),
+                entry: track_to_child(track, &index, &ratings, &data),
+            })
+        })
+        .collect();
+
+    OSNestedResponse("bookmarks", GetBookmarksAnswer { bookmark })
+}
+
+#[derive(Deserialize)]
+struct CreateBookmarkParams {
+    id: String,
+    position: u64,
+    comment: Option<String>,
+}
+
+async fn create_bookmark(
+    Query(CreateBookmarkParams {
+        id,
+        position,
+        comment,
+    }): Query<CreateBookmarkParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResult<OSEmptyResponse> {
+    let data = state.user_data(user_id);
+
+    let track_id = TrackID::decode(&id).map_err(|_| "Invalid track ID provided")?;
+
+    data.set_bookmark(track_id, position, comment)
+        .await
+        .map_err(|_| "Failed to create bookmark")?;
+
+    Ok(OSEmptyResponse)
 }
 
-async fn get_play_queue() -> OSNestedResponse<PlayQueue> {
+#[derive(Deserialize)]
+struct DeleteBookmarkParams {
+    id: String,
+}
+
+async fn delete_bookmark(
+    Query(DeleteBookmarkParams { id }): Query<DeleteBookmarkParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResult<OSEmptyResponse> {
+    let data = state.user_data(user_id);
+
+    let track_id = TrackID::decode(&id).map_err(|_| "Invalid track ID provided")?;
+
+    data.delete_bookmark(track_id)
+        .await
+        .map_err(|_| "Failed to delete bookmark")?;
+
+    Ok(OSEmptyResponse)
+}
+
+async fn get_play_queue(
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSNestedResponse<PlayQueue> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let Some(play_queue) = data.play_queue().await.clone() else {
+        return OSNestedResponse(
+            "playQueue",
+            PlayQueue {
+                current: None,
+                position: None,
+                username: data.user().name.clone(),
+                changed_iso_8601: to_iso_8601(SystemTime::UNIX_EPOCH),
+                changed_by_app: String::new(),
+                tracks: None,
+            },
+        );
+    };
+
+    // Tracks may have disappeared from the index since the queue was saved
+    let current = play_queue
+        .current_track_id()
+        .filter(|track_id| index.tracks.contains_key(track_id));
+
+    let tracks = play_queue
+        .track_ids
+        .iter()
+        .filter_map(|track_id| index.tracks.get(track_id))
+        .map(|track| track_to_child(track, &index, &ratings, &data))
+        .collect();
+
     OSNestedResponse(
         "playQueue",
         PlayQueue {
-            current: None,
-            position: None,
-            username: "admin".to_owned(), // TODO: constant?
-            changed_iso_8601: to_iso_8601(SystemTime::UNIX_EPOCH),
-            changed_by_app: String::new(),
-            tracks: None,
+            current,
+            position: current.map(|_| play_queue.position_ms),
+            username: data.user().name.clone(),
+            changed_iso_8601: to_iso_8601(play_queue.changed_at),
+            changed_by_app: play_queue.changed_by.unwrap_or_default(),
+            tracks: Some(tracks),
         },
     )
 }
+
+async fn save_play_queue(
+    Query(params): Query<MultiParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResult<OSEmptyResponse> {
+    let data = state.user_data(user_id);
+
+    let track_ids = params
+        .get_all("id")
+        .map(TrackID::decode)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Invalid track ID provided")?;
+
+    let current_index = params
+        .get("current")
+        .map(|current| {
+            let current = TrackID::decode(current).map_err(|_| "Invalid current track ID")?;
+
+            track_ids
+                .iter()
+                .position(|track_id| *track_id == current)
+                .ok_or("Current track is not part of the queue")
+        })
+        .transpose()?;
+
+    let position_ms = params
+        .get("position")
+        .map(str::parse::<u64>)
+        .transpose()
+        .map_err(|_| "Invalid position provided")?
+        .unwrap_or(0);
+
+    let play_queue = userdata::PlayQueue::new(
+        track_ids,
+        current_index,
+        position_ms,
+        params.get("c").map(str::to_owned),
+    )
+    .map_err(|_| "Invalid play queue provided")?;
+
+    data.save_play_queue(play_queue)
+        .await
+        .map_err(|_| "Failed to save play queue")?;
+
+    Ok(OSEmptyResponse)
+}
@@ -7,12 +7,13 @@ use crate::{
     server::{
         HttpState,
         opensubsonic::{
-            OSError,
+            OSCredentials, OSError, OSUser,
             convert::{album_to_id3_with_songs, track_to_child},
             types::{
                 AlbumInfo, ArtistInfo2, Child, CoverArtId, Genre, MUSIC_FOLDER_ID, MusicFolder,
             },
         },
+        utils::base_uri::BaseUri,
     },
 };
 
@@ -88,9 +89,12 @@ struct GetAlbumParams {
 async fn get_album(
     Query(GetAlbumParams { id }): Query<GetAlbumParams>,
     State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
 ) -> OSResultNested<AlbumID3WithSongs> {
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     let album = index
         .albums
@@ -99,7 +103,7 @@ async fn get_album(
 
     Ok(OSNestedResponse(
         "album",
-        album_to_id3_with_songs(album, &index, &ratings),
+        album_to_id3_with_songs(album, &index, &ratings, &data),
     ))
 }
 
@@ -111,19 +115,22 @@ struct GetSongParams {
 async fn get_song(
     Query(GetSongParams { id }): Query<GetSongParams>,
     State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
 ) -> OSResultNested<Child> {
-    let index = state.index().await;
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
 
     let track = index
         .tracks
         .get(&id)
         .ok_or(OSError("Provided track ID was not found"))?;
 
-    let ratings = state.ratings().await;
+    let ratings = data.ratings().await;
 
     Ok(OSNestedResponse(
         "song",
-        track_to_child(track, &index, &ratings),
+        track_to_child(track, &index, &ratings, &data),
     ))
 }
 
@@ -140,6 +147,8 @@ struct GetArtistInfo2Params {
 async fn get_artist_info2(
     Query(GetArtistInfo2Params { artist_id }): Query<GetArtistInfo2Params>,
     State(state): State<HttpState>,
+    base_uri: BaseUri,
+    credentials: OSCredentials,
 ) -> OSResultNested<ArtistInfo2> {
     let index = state.index().await;
 
@@ -147,8 +156,14 @@ async fn get_artist_info2(
         return Err(OSError("The provided artist ID was not found"));
     }
 
-    let get_image_uri =
-        |art_size: ArtSize| make_cover_art_uri(CoverArtId::Artist(artist_id), art_size);
+    let get_image_uri = |art_size: ArtSize| {
+        make_cover_art_uri(
+            &base_uri,
+            &credentials,
+            CoverArtId::Artist(artist_id),
+            state.art_settings().side_px(art_size),
+        )
+    };
 
     Ok(OSNestedResponse(
         "artistInfo2",
@@ -173,6 +188,8 @@ struct GetAlbumInfo2Params {
 async fn get_album_info2(
     Query(GetAlbumInfo2Params { album_id }): Query<GetAlbumInfo2Params>,
     State(state): State<HttpState>,
+    base_uri: BaseUri,
+    credentials: OSCredentials,
 ) -> OSResultNested<AlbumInfo> {
     let index = state.index().await;
 
@@ -180,8 +197,14 @@ async fn get_album_info2(
         return Err(OSError("The provided album ID was not found"));
     }
 
-    let get_image_uri =
-        |art_size: ArtSize| make_cover_art_uri(CoverArtId::Album(album_id), art_size);
+    let get_image_uri = |art_size: ArtSize| {
+        make_cover_art_uri(
+            &base_uri,
+            &credentials,
+            CoverArtId::Album(album_id),
+            state.art_settings().side_px(art_size),
+        )
+    };
 
     Ok(OSNestedResponse(
         "albumInfo",
@@ -1,26 +1,30 @@
-use std::collections::HashMap;
-
 use axum::extract::{Query, State};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    index::{Album, Genre, IndexCache},
+    index::{Album, Artist as IndexArtist, CmpIndex, Genre, IndexCache, Track},
+    manager::UserDataManager,
     server::{
         HttpState,
         opensubsonic::{
-            convert::{album_to_child, album_to_id3_with_songs, track_to_child},
-            types::{AlbumID3WithSongs, Artist, Child},
+            OSError, OSNestedResponse, OSResultNested, OSUser,
+            convert::{
+                album_to_child, album_to_id3_with_songs, artist_to_id3, to_iso_8601, track_to_child,
+            },
+            types::{AlbumID3WithSongs, Artist, ArtistID3, Child},
         },
     },
+    userdata::{PlayStats, Ratings, StarredItem, Stars, most_recent_first},
     utils::{Rng, deterministic_shuffle},
 };
 
-use super::{super::OSNestedResponse, OpenSubsonicRouter};
+use super::OpenSubsonicRouter;
 
 pub fn router() -> OpenSubsonicRouter {
     OpenSubsonicRouter::new()
         .route("/getAlbumList", get_album_list)
         .route("/getAlbumList2", get_album_list2)
+        .route("/getStarred", get_starred)
         .route("/getStarred2", get_starred2)
 }
 
@@ -52,33 +56,71 @@ enum AlbumListSort {
     ByGenre,
 }
 
-fn album_list(params: AlbumListParams, index: &IndexCache) -> impl Iterator<Item = &Album> {
+#[allow(clippy::too_many_lines)]
+fn album_list<'a>(
+    params: AlbumListParams,
+    index: &'a IndexCache,
+    ratings: &Ratings,
+    play_stats: &PlayStats,
+    stars: &Stars,
+) -> Result<Vec<&'a Album>, OSError> {
     let AlbumListParams {
         sort,
         size,
         offset,
-        #[allow(unused_variables)] // TODO
         from_year,
-        #[allow(unused_variables)] // TODO
         to_year,
         genre,
     } = params;
 
-    // TODO: take sort into consideration
-    // TODO: take from_year + to_year into consideration
+    let years = match (from_year, to_year) {
+        (Some(from_year), Some(to_year)) => Some((from_year, to_year)),
+        (None, None) => None,
+        _ => return Err(OSError("Both fromYear and toYear must be provided")),
+    };
 
     let genre = genre.map(|genre| Genre::new(genre).id);
 
+    match sort {
+        AlbumListSort::ByYear if years.is_none() => {
+            return Err(OSError("Missing fromYear and toYear parameters"));
+        }
+
+        AlbumListSort::ByGenre if genre.is_none() => {
+            return Err(OSError("Missing genre parameter"));
+        }
+
+        _ => {}
+    }
+
+    let album_year = |album: &Album| {
+        index
+            .albums_min_max_date
+            .get(&album.id)
+            .unwrap()
+            .map(|(min, _)| min.year)
+    };
+
+    let album_tracks = |album: &Album| index.albums_tracks.get(&album.id).unwrap().iter().copied();
+
     let mut albums = index
         .albums
-        .iter()
-        .filter(move |(album_id, _)| {
-            genre.is_none_or(|genre| index.albums_genres.get(album_id).unwrap().contains(&genre))
+        .values()
+        .filter(|album| {
+            genre.is_none_or(|genre| index.albums_genres.get(&album.id).unwrap().contains(&genre))
+        })
+        // Years may be provided in reverse order to get the most recent albums first
+        .filter(|album| {
+            years.is_none_or(|(from_year, to_year)| {
+                album_year(album).is_some_and(|year| {
+                    year >= from_year.min(to_year) && year <= from_year.max(to_year)
+                })
+            })
         })
         .collect::<Vec<_>>();
 
-    // TODO: Remove this allow
-    #[allow(clippy::match_same_arms)]
+    let cmp_index = CmpIndex::new(index);
+
     match sort {
         AlbumListSort::Random => {
             let mut rng = Rng::new();
@@ -86,54 +128,125 @@ fn album_list(params: AlbumListParams, index: &IndexCache) -> impl Iterator<Item
         }
 
         AlbumListSort::Newest => {
-            let album_recent_pos = index
-                .latest_added_albums
-                .iter()
-                .enumerate()
-                .map(|(pos, album_id)| (*album_id, pos))
-                .collect::<HashMap<_, _>>();
-
-            albums.sort_by_key(|(album_id, _)| album_recent_pos.get(album_id).unwrap());
+            albums.sort_by_key(|album| index.latest_added_albums.get_index_of(&album.id).unwrap());
         }
 
         AlbumListSort::Highest => {
-            // TODO
+            // Explicit album ratings come first, then the average rating of the album's tracks
+            let mut rated_albums = albums
+                .into_iter()
+                .map(|album| {
+                    let rating = (
+                        ratings.albums.get(&album.id).copied(),
+                        ratings.album_tracks_average(album.id, index),
+                    );
+
+                    (album, rating)
+                })
+                .filter(|(_, (rating, average))| rating.is_some() || average.is_some())
+                .collect::<Vec<_>>();
+
+            rated_albums.sort_by(|(a, (a_rating, a_average)), (b, (b_rating, b_average))| {
+                b_rating
+                    .cmp(a_rating)
+                    .then_with(|| {
+                        b_average
+                            .unwrap_or(-1.0)
+                            .total_cmp(&a_average.unwrap_or(-1.0))
+                    })
+                    .then_with(|| cmp_index.cmp_albums(a, b))
+            });
+
+            albums = rated_albums.into_iter().map(|(album, _)| album).collect();
         }
 
         AlbumListSort::Frequent => {
-            // TODO
+            let play_count = |album: &Album| {
+                album_tracks(album)
+                    .filter_map(|track_id| play_stats.get(&track_id))
+                    .map(|stats| stats.play_count)
+                    .sum::<u32>()
+            };
+
+            let mut played_albums = albums
+                .into_iter()
+                .map(|album| (album, play_count(album)))
+                .filter(|(_, play_count)| *play_count > 0)
+                .collect::<Vec<_>>();
+
+            played_albums.sort_by(|(a, a_count), (b, b_count)| {
+                b_count
+                    .cmp(a_count)
+                    .then_with(|| cmp_index.cmp_albums(a, b))
+            });
+
+            albums = played_albums.into_iter().map(|(album, _)| album).collect();
         }
 
         AlbumListSort::Recent => {
-            // TODO
+            let last_played_at = |album: &Album| {
+                album_tracks(album)
+                    .filter_map(|track_id| play_stats.get(&track_id))
+                    .filter_map(|stats| stats.last_played_at)
+                    .max()
+            };
+
+            let mut played_albums = albums
+                .into_iter()
+                .filter_map(|album| Some((album, last_played_at(album)?)))
+                .collect::<Vec<_>>();
+
+            played_albums.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+            albums = played_albums.into_iter().map(|(album, _)| album).collect();
         }
 
-        AlbumListSort::AlphabeticalByName => {
-            albums.sort_by_key(|(_, album)| &album.name);
+        AlbumListSort::AlphabeticalByName | AlbumListSort::ByGenre => {
+            albums.sort_by(|a, b| cmp_index.cmp_albums(a, b));
         }
 
         AlbumListSort::AlphabeticalByArtist => {
-            // TODO
+            albums.sort_by(|a, b| {
+                match (a.artists_id.first(), b.artists_id.first()) {
+                    (Some(a), Some(b)) => cmp_index.cmp_artists_by_id(*a, *b),
+                    (a, b) => a.is_some().cmp(&b.is_some()),
+                }
+                .then_with(|| cmp_index.cmp_albums(a, b))
+            });
         }
 
         AlbumListSort::Starred => {
-            // TODO
-        }
+            let mut starred_albums = albums
+                .into_iter()
+                .filter_map(|album| Some((album, *stars.albums.get(&album.id)?)))
+                .collect::<Vec<_>>();
 
-        AlbumListSort::ByYear => {
-            // TODO
+            starred_albums.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+            albums = starred_albums.into_iter().map(|(album, _)| album).collect();
         }
 
-        AlbumListSort::ByGenre => {
-            // TODO
+        AlbumListSort::ByYear => {
+            let (from_year, to_year) = years.unwrap();
+
+            albums.sort_by(|a, b| {
+                let ord = album_year(a).cmp(&album_year(b));
+                let ord = if from_year > to_year {
+                    ord.reverse()
+                } else {
+                    ord
+                };
+
+                ord.then_with(|| cmp_index.cmp_albums(a, b))
+            });
         }
     }
 
-    albums
+    Ok(albums
         .into_iter()
         .skip(offset.unwrap_or(0))
         .take(size.unwrap_or(50))
-        .map(|(_, album)| album)
+        .collect())
 }
 
 #[derive(Serialize)]
@@ -145,17 +258,24 @@ pub struct GetAlbumListAnswer {
 async fn get_album_list(
     Query(params): Query<AlbumListParams>,
     State(state): State<HttpState>,
-) -> OSNestedResponse<GetAlbumListAnswer> {
-    let index = state.index().await;
+    OSUser(user_id): OSUser,
+) -> OSResultNested<GetAlbumListAnswer> {
+    let data = state.user_data(user_id);
 
-    OSNestedResponse(
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let albums = album_list(params, &index, &ratings, &data.play_stats(), &data.stars())?;
+
+    Ok(OSNestedResponse(
         "albumList",
         GetAlbumListAnswer {
-            albums: album_list(params, &index)
-                .map(|album| album_to_child(album, &index))
+            albums: albums
+                .into_iter()
+                .map(|album| album_to_child(album, &index, &ratings, &data))
                 .collect(),
         },
-    )
+    ))
 }
 
 #[derive(Serialize)]
@@ -167,52 +287,133 @@ pub struct GetAlbumList2Answer {
 async fn get_album_list2(
     Query(params): Query<AlbumListParams>,
     State(state): State<HttpState>,
-) -> OSNestedResponse<GetAlbumList2Answer> {
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    OSUser(user_id): OSUser,
+) -> OSResultNested<GetAlbumList2Answer> {
+    let data = state.user_data(user_id);
 
-    OSNestedResponse(
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    // Play stats and stars are only locked while listing albums, as converting them locks them again
+    let albums = album_list(params, &index, &ratings, &data.play_stats(), &data.stars())?;
+
+    Ok(OSNestedResponse(
         "albumList2",
         GetAlbumList2Answer {
-            albums: album_list(params, &index)
-                .map(|album| album_to_id3_with_songs(album, &index, &ratings))
+            albums: albums
+                .into_iter()
+                .map(|album| album_to_id3_with_songs(album, &index, &ratings, &data))
                 .collect(),
         },
-    )
+    ))
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct GetStarred2Answer {
+pub struct GetStarredAnswer {
     pub artist: Vec<Artist>,
     pub album: Vec<Child>,
     pub song: Vec<Child>,
 }
 
+async fn get_starred(
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+    // TODO: query
+) -> OSNestedResponse<GetStarredAnswer> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let (artists, albums, tracks) = starred_items(&index, &data);
+
+    OSNestedResponse(
+        "starred",
+        GetStarredAnswer {
+            artist: artists
+                .into_iter()
+                .map(|artist| Artist {
+                    id: artist.id,
+                    name: artist.name.clone(),
+                    artist_image_url: None,
+                    starred_iso_8601: data
+                        .starred_at(StarredItem::Artist(artist.id))
+                        .map(to_iso_8601),
+                    user_rating_1_to_5: ratings
+                        .artists
+                        .get(&artist.id)
+                        .map(|rating| rating.get_zero_to_five()),
+                    average_rating_1_to_5: ratings.artist_tracks_average(artist.id, &index),
+                })
+                .collect(),
+
+            album: albums
+                .into_iter()
+                .map(|album| album_to_child(album, &index, &ratings, &data))
+                .collect(),
+
+            song: tracks
+                .into_iter()
+                .map(|track| track_to_child(track, &index, &ratings, &data))
+                .collect(),
+        },
+    )
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetStarred2Answer {
+    pub artist: Vec<ArtistID3>,
+    pub album: Vec<AlbumID3WithSongs>,
+    pub song: Vec<Child>,
+}
+
 async fn get_starred2(
     State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
     // TODO: query
 ) -> OSNestedResponse<GetStarred2Answer> {
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let (artists, albums, tracks) = starred_items(&index, &data);
 
     OSNestedResponse(
         "starred2",
         GetStarred2Answer {
-            // TODO: should return explicitly-starred artists, not artists who have starred songs
-            artist: vec![],
-
-            // TODO: should return explicitly-starred artists, not artists who have starred songs
-            album: vec![],
-
-            song: ratings
-                .keys()
-                // NOTE: required as the track may have been deleted, leaving a dangling rating
-                //       removing the rating on index update is not a good idea either, since the
-                //       song could have been removed by mistake. On re-add, the rating would be lost.
-                .filter_map(|track_id| index.tracks.get(track_id))
-                .map(|track| track_to_child(track, &index, &ratings))
+            artist: artists
+                .into_iter()
+                .map(|artist| artist_to_id3(artist, &index, &data))
+                .collect(),
+
+            album: albums
+                .into_iter()
+                .map(|album| album_to_id3_with_songs(album, &index, &ratings, &data))
+                .collect(),
+
+            song: tracks
+                .into_iter()
+                .map(|track| track_to_child(track, &index, &ratings, &data))
                 .collect(),
         },
     )
 }
+
+/// Get the starred artists, albums and tracks, most recently starred first
+///
+/// Stars are collected first and released, as converting the items locks them again.
+fn starred_items<'a>(
+    index: &'a IndexCache,
+    data: &UserDataManager,
+) -> (Vec<&'a IndexArtist>, Vec<&'a Album>, Vec<&'a Track>) {
+    let stars = data.stars();
+
+    (
+        most_recent_first(&stars.artists, &index.artists),
+        most_recent_first(&stars.albums, &index.albums),
+        most_recent_first(&stars.tracks, &index.tracks),
+    )
+}
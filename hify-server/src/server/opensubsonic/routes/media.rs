@@ -1,14 +1,18 @@
 use axum::extract::{Query, Request, State};
+use log::error;
 use serde::Deserialize;
 
 use crate::{
-    arts::{ArtSize, LARGE_ART_SIDE_PX, MEDIUM_ART_SIDE_PX, SMALL_ART_SIDE_PX, TINY_ART_SIDE_PX},
+    arts::{ArtFormat, ArtSize, RequestedArtSize},
     index::TrackID,
     manager::Entity,
     server::{
         HttpState, OPENSUBSONIC_BASE_URI,
-        opensubsonic::{OSError, OSResult, types::CoverArtId},
-        utils::files::{ServedFile, serve_file},
+        opensubsonic::{OSCredentials, OSError, OSResult, OSUser, types::CoverArtId},
+        utils::{
+            base_uri::BaseUri,
+            files::{ServedFile, accept_header, serve_art_file, serve_file},
+        },
     },
 };
 
@@ -60,64 +64,88 @@ async fn stream(
 
 static GET_COVER_ART_URI: &str = "/getCoverArt";
 
-pub fn make_cover_art_uri(id: CoverArtId, art_size: ArtSize) -> String {
-    format!(
-        "{OPENSUBSONIC_BASE_URI}{GET_COVER_ART_URI}?id={}&size={}",
+/// Build the URI of a cover art, which clients fetch as-is with the user's credentials
+pub fn make_cover_art_uri(
+    base_uri: &BaseUri,
+    credentials: &OSCredentials,
+    id: CoverArtId,
+    side_px: u32,
+) -> String {
+    base_uri.join(&format!(
+        "{OPENSUBSONIC_BASE_URI}{GET_COVER_ART_URI}?id={}&size={side_px}&{}",
         id.encode(),
-        match art_size {
-            ArtSize::Large => LARGE_ART_SIDE_PX,
-            ArtSize::Medium => MEDIUM_ART_SIDE_PX,
-            ArtSize::Small => SMALL_ART_SIDE_PX,
-            ArtSize::Tiny => TINY_ART_SIDE_PX,
-        }
-    )
+        credentials.query()
+    ))
 }
 
 #[derive(Deserialize)]
 pub struct GetCovertArtParams {
     id: String,
     size: Option<u16>,
+    // Not part of the OpenSubsonic specification
+    format: Option<ArtFormat>,
 }
 
 async fn get_cover_art(
     State(state): State<HttpState>,
-    Query(GetCovertArtParams {
-        id,
-        #[allow(unused_variables)] //TODO
-        size,
-    }): Query<GetCovertArtParams>,
+    OSUser(user_id): OSUser,
+    Query(GetCovertArtParams { id, size, format }): Query<GetCovertArtParams>,
     req: Request,
 ) -> OSResult<ServedFile> {
+    let data = state.user_data(user_id);
+
     let id = CoverArtId::decode(&id).map_err(|()| "Invalid ID provided")?;
 
     let art_size = match size {
-        None => ArtSize::Large,
-        Some(px) => {
-            if u32::from(px) < TINY_ART_SIDE_PX {
-                ArtSize::Tiny
-            } else if u32::from(px) < SMALL_ART_SIDE_PX {
-                ArtSize::Small
-            } else if u32::from(px) < MEDIUM_ART_SIDE_PX {
-                ArtSize::Medium
-            } else {
-                ArtSize::Large
-            }
-        }
+        None | Some(0) => RequestedArtSize::Preset(ArtSize::Large),
+        Some(px) => RequestedArtSize::Px(u32::from(px)),
     };
 
-    let index = state.index().await;
+    // Many Subsonic clients don't support WebP, so only use it when explicitly accepted
+    let format =
+        format.unwrap_or_else(|| ArtFormat::negotiate(accept_header(&req), ArtFormat::Jpeg));
+
+    let index = data.index().await;
 
     match id {
-        CoverArtId::Track(_) => Err(OSError("Track ID is not supported for cover art")),
+        CoverArtId::Track(id) => {
+            let track = index
+                .tracks
+                .get(&id)
+                .ok_or(OSError("Provided track ID was not found"))?;
+
+            // Tracks without their own art use their album's one
+            let entity = data.track_art_entity(track);
+
+            drop(index);
+
+            let art_path = data
+                .get_art_with_size(entity, art_size, format)
+                .await
+                .map_err(|err| {
+                    error!("Failed to get cover art: {err:?}");
+                    OSError("Failed to get cover art")
+                })?;
+
+            Ok(serve_art_file(&art_path, req).await)
+        }
 
         CoverArtId::Album(id) => {
             if !index.albums.contains_key(&id) {
                 return Err(OSError("Provided album ID was not found"));
             }
 
-            let art_path = state.get_art(Entity::Album(id), art_size).unwrap();
+            drop(index);
 
-            Ok(serve_file(&art_path, req).await)
+            let art_path = data
+                .get_art_with_size(Entity::Album(id), art_size, format)
+                .await
+                .map_err(|err| {
+                    error!("Failed to get cover art: {err:?}");
+                    OSError("Failed to get cover art")
+                })?;
+
+            Ok(serve_art_file(&art_path, req).await)
         }
 
         CoverArtId::Artist(id) => {
@@ -125,9 +153,43 @@ async fn get_cover_art(
                 return Err(OSError("Provided artist ID was not found"));
             }
 
-            let art_path = state.get_art(Entity::Artist(id), art_size).unwrap();
+            drop(index);
+
+            let art_path = data
+                .get_art_with_size(Entity::Artist(id), art_size, format)
+                .await
+                .map_err(|err| {
+                    error!("Failed to get cover art: {err:?}");
+                    OSError("Failed to get cover art")
+                })?;
+
+            Ok(serve_art_file(&art_path, req).await)
+        }
+
+        CoverArtId::Playlist(id) => {
+            let playlists = data.playlists().await;
+
+            let playlist = playlists
+                .get(&id)
+                .ok_or(OSError("Provided playlist ID was not found"))?;
+
+            // Playlists without a cover use the art of their first track
+            let entity = data
+                .playlist_art_entity(playlist, &index)
+                .ok_or(OSError("Provided playlist has no art"))?;
+
+            drop(playlists);
+            drop(index);
+
+            let art_path = data
+                .get_art_with_size(entity, art_size, format)
+                .await
+                .map_err(|err| {
+                    error!("Failed to get cover art: {err:?}");
+                    OSError("Failed to get cover art")
+                })?;
 
-            Ok(serve_file(&art_path, req).await)
+            Ok(serve_art_file(&art_path, req).await)
         }
     }
 }
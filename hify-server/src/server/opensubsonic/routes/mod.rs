@@ -1,7 +1,10 @@
-use axum::{Router, handler::Handler, routing::get};
+use axum::{Router, handler::Handler, middleware, routing::get};
+use serde::Deserialize;
 
 use crate::server::HttpState;
 
+use super::auth::authenticate;
+
 mod annotation;
 mod bookmarks;
 mod browsing;
@@ -11,7 +14,7 @@ mod playlists;
 mod searching;
 mod system;
 
-pub fn router() -> Router<HttpState> {
+pub fn router(state: HttpState) -> Router<HttpState> {
     Router::new()
         .merge(system::router().finish())
         .merge(browsing::router().finish())
@@ -21,6 +24,9 @@ pub fn router() -> Router<HttpState> {
         .merge(media::router().finish())
         .merge(annotation::router().finish())
         .merge(bookmarks::router().finish())
+        .route_layer(middleware::from_fn_with_state(state, authenticate))
+        // Routes which the specification requires to be reachable without authentication
+        .merge(system::public_router().finish())
 }
 
 struct OpenSubsonicRouter(Router<HttpState>);
@@ -48,3 +54,26 @@ impl OpenSubsonicRouter {
         self.0
     }
 }
+
+/// Query parameters whose keys may be repeated to provide multiple values
+///
+/// To be used with the [`axum::extract::Query`] extractor, as structured
+/// parameters cannot deserialize repeated keys.
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct MultiParams(Vec<(String, String)>);
+
+impl MultiParams {
+    /// Get the first value provided for a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.get_all(key).next()
+    }
+
+    /// Get all values provided for a key, in order
+    pub fn get_all(&self, key: &str) -> impl Iterator<Item = &str> {
+        self.0
+            .iter()
+            .filter(move |(param, _)| param == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
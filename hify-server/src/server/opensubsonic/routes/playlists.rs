@@ -1,50 +1,80 @@
 use std::{collections::HashMap, time::SystemTime};
 
+use anyhow::anyhow;
 use axum::extract::{Query, State};
+use log::error;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    index::{IndexCache, Rating, TrackID},
+    index::{ArtistID, GenreID, IdType, IndexCache, Rating, Track, TrackID},
+    manager::UserDataManager,
     server::{
         HttpState,
         opensubsonic::{
-            OSNestedResponse, OSResultNested,
+            OSEmptyResponse, OSError, OSNestedResponse, OSResult, OSResultNested, OSUser,
             convert::{to_iso_8601, track_to_child},
-            types::PlaylistWithSongs,
+            types::{CoverArtId, Playlist, PlaylistWithSongs},
         },
     },
-    stable_hash,
+    userdata::{self, PlaylistID, Ratings, SmartPlaylist, SmartPlaylistID},
 };
 
-use super::OpenSubsonicRouter;
+use super::{MultiParams, OpenSubsonicRouter};
 
 pub fn router() -> OpenSubsonicRouter {
     OpenSubsonicRouter::new()
         .route("/getPlaylists", get_playlists)
         .route("/getPlaylist", get_playlist)
+        .route("/createPlaylist", create_playlist)
+        .route("/updatePlaylist", update_playlist)
+        .route("/deletePlaylist", delete_playlist)
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetPlaylistsAnswer {
-    // TODO: spec says without songs
-    pub playlist: Vec<PlaylistWithSongs>,
+    pub playlist: Vec<Playlist>,
 }
 
 async fn get_playlists(
     State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
     // TODO: query parameters
 ) -> OSNestedResponse<GetPlaylistsAnswer> {
-    let index = state.index().await;
-    let ratings = state.ratings().await;
-
-    OSNestedResponse(
-        "playlists",
-        GetPlaylistsAnswer {
-            // TODO: this returns a 'empty' playlist for each artist and genre, whereas the server does not need the tracks.
-            playlist: auto_playlists(&index, &ratings),
-        },
-    )
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+    let playlists = data.playlists().await;
+    let smart_playlists = data.smart_playlists().await;
+
+    let mut playlist = playlists
+        .values()
+        .map(|playlist| stored_playlist_infos(playlist, &index, &data))
+        .collect::<Vec<_>>();
+
+    playlist.extend(smart_playlists.values().map(|smart_playlist| {
+        let track_ids = smart_playlist
+            .rules
+            .evaluate(&index, &ratings, &data.play_stats());
+        smart_playlist_infos(smart_playlist, &track_ids, &index)
+    }));
+
+    if state.settings().server.auto_playlists {
+        // Tracks are only computed when a playlist is requested, so only collect their statistics
+        let stats = AutoPlaylistStats::compute_all(&index, &ratings);
+
+        playlist.extend(
+            AutoPlaylist::list(&index)
+                .into_iter()
+                .filter_map(|auto_playlist| {
+                    let stats = stats.get(&auto_playlist).copied().unwrap_or_default();
+                    auto_playlist_infos(auto_playlist, stats, &index)
+                }),
+        );
+    }
+
+    OSNestedResponse("playlists", GetPlaylistsAnswer { playlist })
 }
 
 #[derive(Deserialize)]
@@ -55,130 +85,552 @@ struct GetPlaylistParams {
 async fn get_playlist(
     Query(GetPlaylistParams { id }): Query<GetPlaylistParams>,
     State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
 ) -> OSResultNested<PlaylistWithSongs> {
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let (playlist, track_ids) = match decode_playlist_id(&id, &state)? {
+        PlaylistKind::Stored(playlist_id) => {
+            let playlists = data.playlists().await;
+
+            let playlist = playlists
+                .get(&playlist_id)
+                .ok_or("Provided playlist ID was not found")?;
+
+            (
+                stored_playlist_infos(playlist, &index, &data),
+                available_track_ids(playlist, &index).collect(),
+            )
+        }
+
+        PlaylistKind::Smart(smart_playlist_id) => {
+            let smart_playlists = data.smart_playlists().await;
+
+            let smart_playlist = smart_playlists
+                .get(&smart_playlist_id)
+                .ok_or("Provided playlist ID was not found")?;
+
+            let track_ids = smart_playlist
+                .rules
+                .evaluate(&index, &ratings, &data.play_stats());
+
+            (
+                smart_playlist_infos(smart_playlist, &track_ids, &index),
+                track_ids,
+            )
+        }
 
-    auto_playlists(&index, &ratings)
-        .into_iter()
-        .find(|playlist| playlist.id == id)
-        .ok_or("Provided playlist ID was not found")?;
+        PlaylistKind::Auto(auto_playlist) => {
+            let track_ids = auto_playlist.track_ids(&index, &ratings);
+
+            let stats = AutoPlaylistStats::compute(&track_ids, &index);
+
+            (
+                // Artists and genres may have been removed from the index since the ID was provided
+                auto_playlist_infos(auto_playlist, stats, &index)
+                    .ok_or("Provided playlist ID was not found")?,
+                track_ids,
+            )
+        }
+    };
 
     Ok(OSNestedResponse(
         "playlist",
-        auto_playlists(&index, &ratings)
-            .into_iter()
-            .find(|playlist| playlist.id == id)
-            .unwrap(),
+        PlaylistWithSongs {
+            playlist,
+            tracks: track_ids
+                .into_iter()
+                .map(|track_id| {
+                    track_to_child(
+                        index.tracks.get(&track_id).unwrap(),
+                        &index,
+                        &ratings,
+                        &data,
+                    )
+                })
+                .collect(),
+        },
     ))
 }
 
-// TODO: make it configurable (opt-in or opt-out)
-#[allow(clippy::too_many_lines)]
-fn auto_playlists(
+async fn create_playlist(
+    Query(params): Query<MultiParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResultNested<PlaylistWithSongs> {
+    let data = state.user_data(user_id);
+
+    let track_ids = params
+        .get_all("songId")
+        .map(TrackID::decode)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Invalid song ID provided")?;
+
+    // Providing an existing playlist replaces its content
+    let playlist_id = if let Some(playlist_id) = params.get("playlistId") {
+        let PlaylistKind::Stored(playlist_id) = decode_playlist_id(playlist_id, &state)? else {
+            return Err(OSError("The tracks of this playlist cannot be modified"));
+        };
+
+        data.update_playlist(playlist_id, |playlist, index| {
+            if let Some(name) = params.get("name") {
+                playlist.rename(name)?;
+            }
+
+            playlist.replace_tracks(&track_ids, index)
+        })
+        .await
+        .map_err(|err| {
+            error!("Failed to update playlist: {err:?}");
+            OSError("Failed to update playlist")
+        })?;
+
+        playlist_id
+    } else {
+        let name = params
+            .get("name")
+            .ok_or("Either a playlist ID or a name must be provided")?;
+
+        let playlist = data
+            .create_playlist(name, None, &track_ids)
+            .await
+            .map_err(|err| {
+                error!("Failed to create playlist: {err:?}");
+                OSError("Failed to create playlist")
+            })?;
+
+        playlist.id
+    };
+
+    get_playlist(
+        Query(GetPlaylistParams {
+            id: playlist_id.encode(),
+        }),
+        State(state),
+        OSUser(user_id),
+    )
+    .await
+}
+
+async fn update_playlist(
+    Query(params): Query<MultiParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResult<OSEmptyResponse> {
+    let data = state.user_data(user_id);
+
+    let playlist_id = params.get("playlistId").ok_or("No playlist ID provided")?;
+
+    let playlist_id = match decode_playlist_id(playlist_id, &state)? {
+        PlaylistKind::Stored(playlist_id) => playlist_id,
+
+        PlaylistKind::Smart(smart_playlist_id) => {
+            return update_smart_playlist(smart_playlist_id, &params, &data).await;
+        }
+
+        PlaylistKind::Auto(_) => {
+            return Err(OSError("Auto-generated playlists cannot be modified"));
+        }
+    };
+
+    let track_ids_to_add = params
+        .get_all("songIdToAdd")
+        .map(TrackID::decode)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Invalid song ID provided")?;
+
+    let indexes_to_remove = params
+        .get_all("songIndexToRemove")
+        .map(str::parse::<usize>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Invalid song index provided")?;
+
+    data.update_playlist(playlist_id, |playlist, index| {
+        if let Some(name) = params.get("name") {
+            playlist.rename(name)?;
+        }
+
+        if let Some(comment) = params.get("comment") {
+            playlist.set_description(Some(comment.to_owned()));
+        }
+
+        if !indexes_to_remove.is_empty() {
+            // Clients only see the entries whose track is still in the index,
+            // so their indexes must be translated to positions in the playlist
+            let positions = available_positions(playlist, index).collect::<Vec<_>>();
+
+            let positions = indexes_to_remove
+                .iter()
+                .map(|index| positions.get(*index).copied())
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| anyhow!("Song index is out of bounds"))?;
+
+            playlist.remove_entries(&positions)?;
+        }
+
+        if !track_ids_to_add.is_empty() {
+            playlist.add_tracks(&track_ids_to_add, None, index)?;
+        }
+
+        Ok(())
+    })
+    .await
+    .map_err(|err| {
+        error!("Failed to update playlist: {err:?}");
+        OSError("Failed to update playlist")
+    })?;
+
+    Ok(OSEmptyResponse)
+}
+
+/// Update a smart playlist, whose tracks are determined by its rules
+async fn update_smart_playlist(
+    smart_playlist_id: SmartPlaylistID,
+    params: &MultiParams,
+    data: &UserDataManager<'_>,
+) -> OSResult<OSEmptyResponse> {
+    if params.get("songIdToAdd").is_some() || params.get("songIndexToRemove").is_some() {
+        return Err(OSError("The tracks of smart playlists cannot be modified"));
+    }
+
+    data.update_smart_playlist(smart_playlist_id, |smart_playlist| {
+        if let Some(name) = params.get("name") {
+            smart_playlist.rename(name)?;
+        }
+
+        if let Some(comment) = params.get("comment") {
+            smart_playlist.set_description(Some(comment.to_owned()));
+        }
+
+        Ok(())
+    })
+    .await
+    .map_err(|err| {
+        error!("Failed to update smart playlist: {err:?}");
+        OSError("Failed to update playlist")
+    })?;
+
+    Ok(OSEmptyResponse)
+}
+
+#[derive(Deserialize)]
+struct DeletePlaylistParams {
+    id: String,
+}
+
+async fn delete_playlist(
+    Query(DeletePlaylistParams { id }): Query<DeletePlaylistParams>,
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+) -> OSResult<OSEmptyResponse> {
+    let data = state.user_data(user_id);
+
+    let result = match decode_playlist_id(&id, &state)? {
+        PlaylistKind::Stored(playlist_id) => data.delete_playlist(playlist_id).await,
+
+        PlaylistKind::Smart(smart_playlist_id) => {
+            data.delete_smart_playlist(smart_playlist_id).await
+        }
+
+        PlaylistKind::Auto(_) => return Err(OSError("Auto-generated playlists cannot be deleted")),
+    };
+
+    result.map_err(|err| {
+        error!("Failed to delete playlist: {err:?}");
+        OSError("Failed to delete playlist")
+    })?;
+
+    Ok(OSEmptyResponse)
+}
+
+enum PlaylistKind {
+    Stored(PlaylistID),
+    Smart(SmartPlaylistID),
+    Auto(AutoPlaylist),
+}
+
+fn decode_playlist_id(id: &str, state: &HttpState) -> OSResult<PlaylistKind> {
+    match id.strip_prefix("auto:") {
+        Some(auto_id) if state.settings().server.auto_playlists => AutoPlaylist::decode(auto_id)
+            .map(PlaylistKind::Auto)
+            .ok_or(OSError("Provided playlist ID was not found")),
+
+        Some(_) => Err(OSError("Provided playlist ID was not found")),
+
+        None => match id.strip_prefix("smart:") {
+            Some(smart_id) => SmartPlaylistID::decode(smart_id).map(PlaylistKind::Smart),
+            None => PlaylistID::decode(id).map(PlaylistKind::Stored),
+        }
+        .map_err(|_| OSError("Invalid playlist ID provided")),
+    }
+}
+
+/// Positions of the playlist entries whose track is still in the index
+///
+/// Other entries cannot be represented in `OpenSubsonic`, so they are hidden from clients.
+fn available_positions<'a>(
+    playlist: &'a userdata::Playlist,
+    index: &'a IndexCache,
+) -> impl Iterator<Item = usize> + 'a {
+    playlist
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| index.tracks.contains_key(&entry.track_id))
+        .map(|(position, _)| position)
+}
+
+fn available_track_ids<'a>(
+    playlist: &'a userdata::Playlist,
+    index: &'a IndexCache,
+) -> impl Iterator<Item = TrackID> + 'a {
+    available_positions(playlist, index).map(|position| playlist.entries[position].track_id)
+}
+
+fn stored_playlist_infos(
+    playlist: &userdata::Playlist,
     index: &IndexCache,
-    ratings: &HashMap<TrackID, Rating>,
-) -> Vec<PlaylistWithSongs> {
-    let mut playlists: Vec<(String, Vec<TrackID>)> = vec![];
-
-    // Add score-based playlists
-    playlists.push((
-        "Top Rated".to_string(),
-        index
-            .tracks
-            .values()
-            .filter(|track| {
-                ratings
-                    .get(&track.id)
-                    .is_some_and(|rating| *rating == Rating::Five)
-            })
-            .map(|track| track.id)
-            .collect(),
-    ));
-
-    playlists.push((
-        "Great Tracks".to_string(),
-        index
-            .tracks
-            .values()
-            .filter(|track| {
+    data: &UserDataManager,
+) -> Playlist {
+    let tracks = available_track_ids(playlist, index)
+        .map(|track_id| index.tracks.get(&track_id).unwrap())
+        .collect::<Vec<_>>();
+
+    Playlist {
+        id: playlist.id.encode(),
+        name: playlist.name.clone(),
+        comment: playlist.description.clone(),
+        owner: Some(data.user().name.clone()),
+        public: Some(false),
+        song_count: tracks.len(),
+        duration_s: tracks.iter().map(|track| track.metadata.duration_s).sum(),
+        created_iso_8601: to_iso_8601(playlist.created_at),
+        changed_iso_8601: to_iso_8601(playlist.updated_at),
+        cover_art_id: data
+            .playlist_art_entity(playlist, index)
+            .map(|_| CoverArtId::Playlist(playlist.id)),
+        readonly: Some(false),
+        valid_until_iso_8601: None,
+    }
+}
+
+fn smart_playlist_infos(
+    smart_playlist: &SmartPlaylist,
+    track_ids: &[TrackID],
+    index: &IndexCache,
+) -> Playlist {
+    Playlist {
+        id: format!("smart:{}", smart_playlist.id.encode()),
+        name: smart_playlist.name.clone(),
+        comment: smart_playlist.description.clone(),
+        owner: None,
+        public: Some(false),
+        song_count: track_ids.len(),
+        duration_s: track_ids
+            .iter()
+            .map(|track_id| index.tracks.get(track_id).unwrap().metadata.duration_s)
+            .sum(),
+        created_iso_8601: to_iso_8601(smart_playlist.created_at),
+        changed_iso_8601: to_iso_8601(smart_playlist.updated_at),
+        cover_art_id: None,
+        readonly: Some(true),
+        valid_until_iso_8601: None,
+    }
+}
+
+/// Get an auto playlist's informations, or `None` if its artist or genre doesn't exist
+fn auto_playlist_infos(
+    auto_playlist: AutoPlaylist,
+    stats: AutoPlaylistStats,
+    index: &IndexCache,
+) -> Option<Playlist> {
+    let AutoPlaylistStats {
+        song_count,
+        duration_s,
+    } = stats;
+
+    Some(Playlist {
+        id: format!("auto:{}", auto_playlist.encode()),
+        name: auto_playlist.name(index)?,
+        comment: Some("Auto-generated".to_owned()),
+        owner: None,
+        public: Some(false),
+        song_count,
+        duration_s,
+        created_iso_8601: to_iso_8601(SystemTime::UNIX_EPOCH),
+        changed_iso_8601: to_iso_8601(SystemTime::now()),
+        cover_art_id: None,
+        readonly: Some(true),
+        valid_until_iso_8601: None,
+    })
+}
+
+/// Read-only playlist generated from the ratings
+///
+/// Tracks are only computed when the playlist is requested.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum AutoPlaylist {
+    TopRated,
+    GreatTracks,
+    BestOfArtist(ArtistID),
+    BestOfGenre(GenreID),
+}
+
+impl AutoPlaylist {
+    fn list(index: &IndexCache) -> Vec<Self> {
+        let mut playlists = vec![Self::TopRated, Self::GreatTracks];
+
+        let mut artists = index.artists.values().collect::<Vec<_>>();
+        artists.sort_by(|a, b| a.name.cmp(&b.name));
+
+        playlists.extend(artists.iter().map(|artist| Self::BestOfArtist(artist.id)));
+
+        let mut genres = index.genres.values().collect::<Vec<_>>();
+        genres.sort_by(|a, b| a.name.cmp(&b.name));
+
+        playlists.extend(genres.iter().map(|genre| Self::BestOfGenre(genre.id)));
+
+        playlists
+    }
+
+    fn encode(self) -> String {
+        match self {
+            Self::TopRated => "top-rated".to_owned(),
+            Self::GreatTracks => "great-tracks".to_owned(),
+            Self::BestOfArtist(artist_id) => format!("artist:{}", artist_id.encode()),
+            Self::BestOfGenre(genre_id) => format!("genre:{}", genre_id.encode()),
+        }
+    }
+
+    fn decode(str: &str) -> Option<Self> {
+        match str.split_once(':') {
+            None => match str {
+                "top-rated" => Some(Self::TopRated),
+                "great-tracks" => Some(Self::GreatTracks),
+                _ => None,
+            },
+
+            Some(("artist", id)) => ArtistID::decode(id).ok().map(Self::BestOfArtist),
+            Some(("genre", id)) => GenreID::decode(id).ok().map(Self::BestOfGenre),
+
+            Some(_) => None,
+        }
+    }
+
+    /// Get the playlist's name, or `None` if its artist or genre doesn't exist
+    fn name(self, index: &IndexCache) -> Option<String> {
+        match self {
+            Self::TopRated => Some("Top Rated".to_owned()),
+            Self::GreatTracks => Some("Great Tracks".to_owned()),
+            Self::BestOfArtist(artist_id) => index
+                .artists
+                .get(&artist_id)
+                .map(|artist| format!("Best Of Artist: {}", artist.name)),
+            Self::BestOfGenre(genre_id) => index
+                .genres
+                .get(&genre_id)
+                .map(|genre| format!("Best Of Genre: {}", genre.name)),
+        }
+    }
+
+    fn min_rating(self) -> Rating {
+        match self {
+            Self::TopRated => Rating::Five,
+            Self::GreatTracks | Self::BestOfArtist(_) | Self::BestOfGenre(_) => Rating::Four,
+        }
+    }
+
+    fn track_ids(self, index: &IndexCache, ratings: &Ratings) -> Vec<TrackID> {
+        let candidates: Box<dyn Iterator<Item = &TrackID>> = match self {
+            Self::TopRated | Self::GreatTracks => Box::new(index.tracks.keys()),
+
+            Self::BestOfArtist(artist_id) => {
+                Box::new(index.artists_tracks.get(&artist_id).into_iter().flatten())
+            }
+
+            Self::BestOfGenre(genre_id) => {
+                Box::new(index.genres_tracks.get(&genre_id).into_iter().flatten())
+            }
+        };
+
+        let min_rating = self.min_rating();
+
+        candidates
+            .filter(|track_id| {
                 ratings
-                    .get(&track.id)
-                    .is_some_and(|rating| *rating >= Rating::Four)
+                    .tracks
+                    .get(track_id)
+                    .is_some_and(|rating| *rating >= min_rating)
             })
-            .map(|track| track.id)
-            .collect(),
-    ));
+            .copied()
+            .collect()
+    }
+}
 
-    // Add artist-based playlists
-    let mut artists = index.artists.values().collect::<Vec<_>>();
-    artists.sort_by(|a, b| a.name.cmp(&b.name));
+/// Number of tracks and total duration of an auto playlist
+#[derive(Default, Clone, Copy)]
+struct AutoPlaylistStats {
+    song_count: usize,
+    duration_s: u32,
+}
 
-    for artist in artists {
-        let track_ids = index.artists_tracks.get(&artist.id).unwrap();
+impl AutoPlaylistStats {
+    fn compute(track_ids: &[TrackID], index: &IndexCache) -> Self {
+        let mut stats = Self::default();
 
-        playlists.push((
-            format!("Best Of Artist: {}", artist.name),
-            track_ids
-                .iter()
-                .filter(|track_id| {
-                    ratings
-                        .get(track_id)
-                        .is_some_and(|rating| *rating >= Rating::Four)
-                })
-                .copied()
-                .collect(),
-        ));
+        for track_id in track_ids {
+            stats.add(index.tracks.get(track_id).unwrap());
+        }
+
+        stats
     }
 
-    // Add genre-based playlists
-    let mut genres = index.genres.values().collect::<Vec<_>>();
-    genres.sort_by(|a, b| a.name.cmp(&b.name));
+    /// Compute the statistics of all auto playlists in a single pass over the rated tracks
+    fn compute_all(index: &IndexCache, ratings: &Ratings) -> HashMap<AutoPlaylist, Self> {
+        let mut all_stats = HashMap::<AutoPlaylist, Self>::new();
 
-    for genre in genres {
-        let track_ids = index.genres_tracks.get(&genre.id).unwrap();
+        for (track_id, rating) in &ratings.tracks {
+            // Ratings may refer to tracks that were removed from the index
+            let Some(track) = index.tracks.get(track_id) else {
+                continue;
+            };
 
-        playlists.push((
-            format!("Best Of Genre: {}", genre.name),
-            track_ids
-                .iter()
-                .filter(|track_id| {
-                    ratings
-                        .get(track_id)
-                        .is_some_and(|rating| *rating >= Rating::Four)
-                })
-                .copied()
-                .collect(),
-        ));
-    }
+            let album = index.albums.get(&track.tags.album_id).unwrap();
 
-    // Convert playlists to OpenSubsonic format
-    playlists
-        .into_iter()
-        .map(|(name, track_ids)| {
-            let tracks: Vec<_> = track_ids
+            // Same candidates as in [`AutoPlaylist::track_ids`]
+            let playlists = [AutoPlaylist::TopRated, AutoPlaylist::GreatTracks]
                 .into_iter()
-                .map(|track_id| index.tracks.get(&track_id).unwrap())
-                .collect();
-
-            PlaylistWithSongs {
-                id: format!("{:X}", stable_hash!(name)),
-                name,
-                comment: Some("Auto-generated".to_owned()),
-                owner: None,
-                public: Some(false),
-                song_count: tracks.len(),
-                duration_s: tracks.iter().map(|track| track.metadata.duration_s).sum(),
-                created_iso_8601: to_iso_8601(SystemTime::UNIX_EPOCH),
-                changed_iso_8601: to_iso_8601(SystemTime::now()),
-                cover_art_id: None,
-                readonly: Some(true),
-                valid_until_iso_8601: None,
-                tracks: tracks
-                    .into_iter()
-                    .map(|track| track_to_child(track, index, ratings))
-                    .collect(),
+                .chain(
+                    album
+                        .artists_id
+                        .iter()
+                        .copied()
+                        .map(AutoPlaylist::BestOfArtist),
+                )
+                .chain(
+                    track
+                        .tags
+                        .genres_id
+                        .iter()
+                        .copied()
+                        .map(AutoPlaylist::BestOfGenre),
+                );
+
+            for playlist in playlists {
+                if *rating >= playlist.min_rating() {
+                    all_stats.entry(playlist).or_default().add(track);
+                }
             }
-        })
-        .collect()
+        }
+
+        all_stats
+    }
+
+    fn add(&mut self, track: &Track) {
+        self.song_count += 1;
+        self.duration_s += track.metadata.duration_s;
+    }
 }
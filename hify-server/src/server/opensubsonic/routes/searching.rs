@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use crate::server::{
     HttpState,
     opensubsonic::{
-        OSNestedResponse,
+        OSNestedResponse, OSUser,
         convert::{album_to_id3_with_songs, artist_to_id3, track_to_child},
         types::{AlbumID3WithSongs, ArtistID3, Child, MUSIC_FOLDER_ID},
     },
@@ -45,7 +45,10 @@ pub struct Search3Answer {
 async fn search3(
     Query(params): Query<Search3Params>,
     State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
 ) -> OSNestedResponse<Search3Answer> {
+    let data = state.user_data(user_id);
+
     let Search3Params {
         query,
         artist_count,
@@ -68,8 +71,8 @@ async fn search3(
         );
     }
 
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     let tracks = search(
         &query,
@@ -110,19 +113,19 @@ async fn search3(
             artist: artists
                 .results
                 .iter()
-                .map(|artist| artist_to_id3(artist, &index))
+                .map(|artist| artist_to_id3(artist, &index, &data))
                 .collect(),
 
             album: albums
                 .results
                 .iter()
-                .map(|album| album_to_id3_with_songs(album, &index, &ratings))
+                .map(|album| album_to_id3_with_songs(album, &index, &ratings, &data))
                 .collect(),
 
             song: tracks
                 .results
                 .iter()
-                .map(|track| track_to_child(track, &index, &ratings))
+                .map(|track| track_to_child(track, &index, &ratings, &data))
                 .collect(),
         },
     )
@@ -1,6 +1,10 @@
+use axum::extract::State;
 use serde::Serialize;
 
-use crate::server::opensubsonic::{OSEmptyResponse, OSNestedResponse};
+use crate::server::{
+    HttpState,
+    opensubsonic::{OSAuthError, OSAuthMethod, OSEmptyResponse, OSNestedResponse, OSUser},
+};
 
 use super::OpenSubsonicRouter;
 
@@ -8,8 +12,11 @@ pub fn router() -> OpenSubsonicRouter {
     OpenSubsonicRouter::new()
         .route("/ping", ping)
         .route("/license", license)
-    // .route("/getOpenSubsonicExtensions", get_open_subsonic_extensions) // TODO
-    // .route("/tokenInfo", token_info) // TODO
+        .route("/tokenInfo", token_info)
+}
+
+pub fn public_router() -> OpenSubsonicRouter {
+    OpenSubsonicRouter::new().route("/getOpenSubsonicExtensions", get_open_subsonic_extensions)
 }
 
 async fn ping() -> OSEmptyResponse {
@@ -24,3 +31,43 @@ pub struct LicenseAnswer {
 async fn license() -> OSNestedResponse<LicenseAnswer> {
     OSNestedResponse("license", LicenseAnswer { valid: true })
 }
+
+#[derive(Serialize)]
+pub struct OpenSubsonicExtension {
+    pub name: &'static str,
+    pub versions: Vec<u32>,
+}
+
+async fn get_open_subsonic_extensions() -> OSNestedResponse<Vec<OpenSubsonicExtension>> {
+    OSNestedResponse(
+        "openSubsonicExtensions",
+        vec![OpenSubsonicExtension {
+            name: "apiKeyAuthentication",
+            versions: vec![1],
+        }],
+    )
+}
+
+#[derive(Serialize)]
+pub struct TokenInfoAnswer {
+    pub username: String,
+}
+
+async fn token_info(
+    State(state): State<HttpState>,
+    OSUser(user_id): OSUser,
+    method: OSAuthMethod,
+) -> Result<OSNestedResponse<TokenInfoAnswer>, OSAuthError> {
+    if method != OSAuthMethod::ApiKey {
+        return Err(OSAuthError::UnsupportedMechanism);
+    }
+
+    let data = state.user_data(user_id);
+
+    Ok(OSNestedResponse(
+        "tokenInfo",
+        TokenInfoAnswer {
+            username: data.user().name.clone(),
+        },
+    ))
+}
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use crate::index::{AlbumID, ArtistID, IdType, TrackID};
+use crate::{
+    index::{AlbumID, ArtistID, IdType, TrackID},
+    userdata::PlaylistID,
+};
 
 pub const MUSIC_FOLDER_ID: u64 = 1;
 
@@ -307,11 +310,24 @@ pub struct Genre {
 }
 
 #[derive(Serialize)]
-pub struct Bookmark {}
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    /// Position in milliseconds
+    pub position: u64,
+    pub username: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(rename = "created")]
+    pub created_iso_8601: String,
+    #[serde(rename = "changed")]
+    pub changed_iso_8601: String,
+    pub entry: Child,
+}
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct PlaylistWithSongs {
+pub struct Playlist {
     pub id: String,
     pub name: String,
 
@@ -338,6 +354,12 @@ pub struct PlaylistWithSongs {
     pub readonly: Option<bool>,
     #[serde(rename = "validUntil", skip_serializing_if = "Option::is_none")]
     pub valid_until_iso_8601: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PlaylistWithSongs {
+    #[serde(flatten)]
+    pub playlist: Playlist,
     #[serde(rename = "entry")]
     pub tracks: Vec<Child>,
 }
@@ -348,8 +370,9 @@ pub struct PlayQueue {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub current: Option<TrackID>,
 
+    /// Position in the current track, in milliseconds
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub position: Option<u32>,
+    pub position: Option<u64>,
     pub username: String,
     #[serde(rename = "changed")]
     pub changed_iso_8601: String,
@@ -364,6 +387,7 @@ pub enum CoverArtId {
     Track(TrackID),
     Album(AlbumID),
     Artist(ArtistID),
+    Playlist(PlaylistID),
 }
 
 impl CoverArtId {
@@ -380,6 +404,10 @@ impl CoverArtId {
             Self::Artist(artist_id) => {
                 format!("artist:{}", artist_id.encode())
             }
+
+            Self::Playlist(playlist_id) => {
+                format!("playlist:{}", playlist_id.encode())
+            }
         }
     }
 
@@ -397,6 +425,7 @@ impl CoverArtId {
             "track" => TrackID::decode(id).map(Self::Track).map_err(|_| ()),
             "album" => AlbumID::decode(id).map(Self::Album).map_err(|_| ()),
             "artist" => ArtistID::decode(id).map(Self::Artist).map_err(|_| ()),
+            "playlist" => PlaylistID::decode(id).map(Self::Playlist).map_err(|_| ()),
 
             _ => Err(()),
         }
@@ -0,0 +1,98 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, Uri},
+    response::Response,
+    routing::{get, post},
+};
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    server::{
+        HttpState,
+        utils::{
+            auth::{CurrentUser, request_token},
+            dtos::UserInfos,
+            response::{ApiResponse, ApiResult, error_response},
+        },
+    },
+    utils::secrets_eq,
+};
+
+/// Routes that require the user to be authenticated
+pub fn router() -> Router<HttpState> {
+    Router::new()
+        .route("/auth/user", get(current_user))
+        .route("/auth/logout", post(logout))
+}
+
+/// Routes that can be accessed without being authenticated
+pub fn public_router() -> Router<HttpState> {
+    Router::new().route("/auth/login", post(login))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginPayload {
+    username: String,
+    password: String,
+
+    /// Name of the device logging in, to tell tokens apart
+    device_name: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginInfos {
+    token: String,
+    user: UserInfos,
+}
+
+async fn login(
+    State(state): State<HttpState>,
+    Json(payload): Json<LoginPayload>,
+) -> Result<ApiResponse<LoginInfos>, Response> {
+    let LoginPayload {
+        username,
+        password,
+        device_name,
+    } = payload;
+
+    let user = state
+        .find_user(&username)
+        .filter(|user| secrets_eq(&user.password, &password))
+        .ok_or_else(|| error_response(StatusCode::UNAUTHORIZED, "Wrong username or password"))?;
+
+    let token = state
+        .create_device_token(user.id, device_name)
+        .await
+        .map_err(|err| {
+            error!("Failed to create device token: {err:?}");
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to create device token",
+            )
+        })?;
+
+    Ok(ApiResponse(LoginInfos {
+        token,
+        user: UserInfos::new(user),
+    }))
+}
+
+async fn current_user(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+) -> ApiResponse<UserInfos> {
+    ApiResponse(UserInfos::new(state.user_data(user_id).user()))
+}
+
+/// Revoke the device token the request was authenticated with
+async fn logout(State(state): State<HttpState>, headers: HeaderMap, uri: Uri) -> ApiResult<()> {
+    if let Some(token) = request_token(&headers, &uri) {
+        state.revoke_device_token(&token).await?;
+    }
+
+    Ok(ApiResponse(()))
+}
@@ -0,0 +1,147 @@
+use anyhow::Context;
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    routing::{get, put},
+};
+use serde::Deserialize;
+
+use crate::{
+    index::TrackID,
+    server::{
+        HttpState,
+        utils::{
+            auth::CurrentUser,
+            dtos::{BookmarkInfos, PlayQueueInfos},
+            response::{ApiResponse, ApiResult},
+        },
+    },
+    userdata::PlayQueue,
+};
+
+#[rustfmt::skip]
+pub fn router() -> Router<HttpState> {
+    Router::new()
+        .route("/bookmarks", get(bookmarks))
+        .route("/tracks/{id}/bookmark", put(set_bookmark).delete(delete_bookmark))
+        .route("/play-queue", get(play_queue).put(save_play_queue))
+}
+
+async fn bookmarks(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+) -> ApiResponse<Vec<BookmarkInfos>> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let bookmarks = data.bookmarks().await.values().cloned().collect::<Vec<_>>();
+
+    ApiResponse(
+        bookmarks
+            .into_iter()
+            .filter_map(|bookmark| BookmarkInfos::new(bookmark, &index, &ratings, &data))
+            .collect(),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetBookmarkPayload {
+    position_ms: u64,
+    comment: Option<String>,
+}
+
+async fn set_bookmark(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(track_id): Path<TrackID>,
+    Json(payload): Json<SetBookmarkPayload>,
+) -> ApiResult<BookmarkInfos> {
+    let data = state.user_data(user_id);
+
+    let SetBookmarkPayload {
+        position_ms,
+        comment,
+    } = payload;
+
+    let bookmark = data
+        .set_bookmark(track_id, position_ms, comment)
+        .await
+        .with_context(|| format!("Failed to set bookmark for track ID {track_id:?}"))?;
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    // The track may have been removed by an index update since the bookmark was set
+    let bookmark = BookmarkInfos::new(bookmark, &index, &ratings, &data)
+        .context("Bookmarked track was removed from the index")?;
+
+    Ok(ApiResponse(bookmark))
+}
+
+async fn delete_bookmark(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(track_id): Path<TrackID>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    data.delete_bookmark(track_id)
+        .await
+        .with_context(|| format!("Failed to delete bookmark for track ID {track_id:?}"))?;
+
+    Ok(ApiResponse(()))
+}
+
+async fn play_queue(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+) -> ApiResponse<Option<PlayQueueInfos>> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let play_queue = data.play_queue().await.clone();
+
+    ApiResponse(
+        play_queue.map(|play_queue| PlayQueueInfos::new(play_queue, &index, &ratings, &data)),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavePlayQueuePayload {
+    track_ids: Vec<TrackID>,
+    current_index: Option<usize>,
+    #[serde(default)]
+    position_ms: u64,
+    /// Name of the client saving the queue
+    client: Option<String>,
+}
+
+/// Save the play queue, to resume it later on another device (an empty queue clears it)
+async fn save_play_queue(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Json(payload): Json<SavePlayQueuePayload>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    let SavePlayQueuePayload {
+        track_ids,
+        current_index,
+        position_ms,
+        client,
+    } = payload;
+
+    let play_queue = PlayQueue::new(track_ids, current_index, position_ms, client)?;
+
+    data.save_play_queue(play_queue)
+        .await
+        .context("Failed to save play queue")?;
+
+    Ok(ApiResponse(()))
+}
@@ -5,17 +5,22 @@ use axum::{
     http::{Request, Response, StatusCode},
     routing::get,
 };
+use log::error;
 use serde::Deserialize;
 use tower_http::services::fs::ServeFileSystemResponseBody;
 
 use crate::{
-    arts::ArtSize,
+    arts::{ArtFormat, RequestedArtSize},
     index::{AlbumID, ArtistID, GenreID, TrackID},
     manager::Entity,
     server::{
         HttpState,
-        utils::files::{ServedFile, serve_file},
+        utils::{
+            auth::CurrentUser,
+            files::{ServedFile, accept_header, serve_art_file, serve_file},
+        },
     },
+    userdata::PlaylistID,
 };
 
 #[rustfmt::skip]
@@ -24,57 +29,121 @@ pub fn router() -> Router<HttpState> {
         .route("/artist/{id}/art", get(artist_art))
         .route("/album/{id}/art", get(album_art))
         .route("/genre/{id}/art", get(genre_art))
+        .route("/playlist/{id}/art", get(playlist_art))
         .route("/track/{id}/audio", get(track_audio_file))
 }
 
 async fn artist_art(
     State(state): State<HttpState>,
     Path(artist_id): Path<ArtistID>,
-    Query(ArtSizeQuery { size: art_size }): Query<ArtSizeQuery>,
+    Query(ArtQuery { size, format }): Query<ArtQuery>,
     req: Request<Body>,
 ) -> Result<Response<ServeFileSystemResponseBody>, (StatusCode, &'static str)> {
     if !state.index().await.artists.contains_key(&artist_id) {
         return Err((StatusCode::NOT_FOUND, "Provided artist was not found"));
     }
 
-    let art_path = state.get_art(Entity::Artist(artist_id), art_size).unwrap();
+    let format =
+        format.unwrap_or_else(|| ArtFormat::negotiate(accept_header(&req), ArtFormat::Webp));
 
-    Ok(serve_file(&art_path, req).await)
+    let art_path = state
+        .get_art_with_size(Entity::Artist(artist_id), size, format)
+        .await
+        .map_err(|err| {
+            error!("Failed to get art: {err:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get art")
+        })?;
+
+    Ok(serve_art_file(&art_path, req).await)
 }
 
 async fn album_art(
     State(state): State<HttpState>,
     Path(album_id): Path<AlbumID>,
-    Query(ArtSizeQuery { size: art_size }): Query<ArtSizeQuery>,
+    Query(ArtQuery { size, format }): Query<ArtQuery>,
     req: Request<Body>,
 ) -> Result<ServedFile, (StatusCode, &'static str)> {
     if !state.index().await.albums.contains_key(&album_id) {
         return Err((StatusCode::NOT_FOUND, "Provided album was not found"));
     }
 
-    let art_path = state.get_art(Entity::Album(album_id), art_size).unwrap();
+    let format =
+        format.unwrap_or_else(|| ArtFormat::negotiate(accept_header(&req), ArtFormat::Webp));
+
+    let art_path = state
+        .get_art_with_size(Entity::Album(album_id), size, format)
+        .await
+        .map_err(|err| {
+            error!("Failed to get art: {err:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get art")
+        })?;
 
-    Ok(serve_file(&art_path, req).await)
+    Ok(serve_art_file(&art_path, req).await)
 }
 
 async fn genre_art(
     State(state): State<HttpState>,
     Path(genre_id): Path<GenreID>,
-    Query(ArtSizeQuery { size: art_size }): Query<ArtSizeQuery>,
+    Query(ArtQuery { size, format }): Query<ArtQuery>,
     req: Request<Body>,
 ) -> Result<Response<ServeFileSystemResponseBody>, (StatusCode, &'static str)> {
     if !state.index().await.genres.contains_key(&genre_id) {
         return Err((StatusCode::NOT_FOUND, "Provided genre was not found"));
     }
 
-    let art_path = state.get_art(Entity::Genre(genre_id), art_size).unwrap();
+    let format =
+        format.unwrap_or_else(|| ArtFormat::negotiate(accept_header(&req), ArtFormat::Webp));
+
+    let art_path = state
+        .get_art_with_size(Entity::Genre(genre_id), size, format)
+        .await
+        .map_err(|err| {
+            error!("Failed to get art: {err:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get art")
+        })?;
+
+    Ok(serve_art_file(&art_path, req).await)
+}
+
+async fn playlist_art(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+    Query(ArtQuery { size, format }): Query<ArtQuery>,
+    req: Request<Body>,
+) -> Result<ServedFile, (StatusCode, &'static str)> {
+    let data = state.user_data(user_id);
+
+    let entity = {
+        let index = data.index().await;
+        let playlists = data.playlists().await;
+
+        let playlist = playlists
+            .get(&playlist_id)
+            .ok_or((StatusCode::NOT_FOUND, "Provided playlist was not found"))?;
+
+        data.playlist_art_entity(playlist, &index)
+            .ok_or((StatusCode::NOT_FOUND, "Provided playlist has no art"))?
+    };
+
+    let format =
+        format.unwrap_or_else(|| ArtFormat::negotiate(accept_header(&req), ArtFormat::Webp));
+
+    let art_path = data
+        .get_art_with_size(entity, size, format)
+        .await
+        .map_err(|err| {
+            error!("Failed to get art: {err:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get art")
+        })?;
 
-    Ok(serve_file(&art_path, req).await)
+    Ok(serve_art_file(&art_path, req).await)
 }
 
 #[derive(Deserialize)]
-struct ArtSizeQuery {
-    size: ArtSize,
+struct ArtQuery {
+    size: RequestedArtSize,
+    format: Option<ArtFormat>,
 }
 
 async fn track_audio_file(
@@ -2,30 +2,40 @@ use anyhow::Context;
 use axum::{
     Router,
     extract::{Query, State},
+    response::Response,
     routing::get,
 };
 use serde::Deserialize;
 
-use crate::server::{
-    HttpState,
-    utils::{
-        dtos::TrackCompleteInfos,
-        mixes,
-        pagination::{Paginated, Pagination, PaginationDir},
-        response::{ApiResponse, ApiResult},
+use crate::{
+    server::{
+        HttpState,
+        utils::{
+            auth::CurrentUser,
+            dtos::TrackCompleteInfos,
+            files::serve_playlist_file,
+            mixes,
+            pagination::{Paginated, Pagination, PaginationDir},
+            response::{ApiError, ApiResponse, ApiResult},
+        },
     },
+    userdata::{PlaylistFileFormat, write_playlist_file},
 };
 
 #[rustfmt::skip]
 pub fn router() -> Router<HttpState> {
     Router::new()
         .route("/mix", get(generate_mix))
+        .route("/mix/export", get(export_mix))
 }
 
 async fn generate_mix(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(params): Query<MixGenerationQuery>,
 ) -> ApiResult<Paginated<TrackCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let MixGenerationQuery {
         mix_params,
         limit,
@@ -35,13 +45,14 @@ async fn generate_mix(
     let mix_params =
         serde_json::from_str(&mix_params).context("Failed to parse mix parameters from query")?;
 
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     Ok(ApiResponse(mixes::generate_mix(
         mix_params,
         &index,
         &ratings,
+        &data,
         Pagination {
             limit,
             offset,
@@ -61,3 +72,53 @@ struct MixGenerationQuery {
     limit: usize,
     offset: Option<usize>,
 }
+
+async fn export_mix(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Query(params): Query<MixExportQuery>,
+) -> Result<Response, ApiError> {
+    let data = state.user_data(user_id);
+
+    let MixExportQuery {
+        mix_params,
+        format,
+        limit,
+        absolute_paths,
+    } = params;
+
+    let mix_params =
+        serde_json::from_str(&mix_params).context("Failed to parse mix parameters from query")?;
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    let mut tracks = mixes::mix_tracks(mix_params, &index, &ratings);
+
+    if let Some(limit) = limit {
+        tracks.truncate(limit);
+    }
+
+    let content = write_playlist_file(
+        format,
+        "Mix",
+        &tracks,
+        &index,
+        absolute_paths.then(|| data.music_dir()),
+    );
+
+    Ok(serve_playlist_file("Mix", format, content))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MixExportQuery {
+    // Cannot be parsed from a complex type (only types that can be represented in a query string)
+    mix_params: String,
+    format: PlaylistFileFormat,
+    limit: Option<usize>,
+
+    /// Write absolute paths instead of paths relative to the music directory
+    #[serde(default)]
+    absolute_paths: bool,
+}
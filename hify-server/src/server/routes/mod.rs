@@ -1,19 +1,27 @@
-use axum::Router;
+use axum::{Router, middleware};
 
-use super::HttpState;
+use super::{HttpState, utils::auth::authenticate};
 
+mod auth;
+mod bookmarks;
 mod files;
 mod mixes;
 mod mutations;
+mod playlists;
 mod queries;
 mod searches;
 
-pub fn router() -> Router<HttpState> {
+pub fn router(state: HttpState) -> Router<HttpState> {
     Router::new()
-        .route("/", axum::routing::get(async || "API is running"))
+        .merge(auth::router())
         .merge(queries::router())
         .merge(mutations::router())
         .merge(files::router())
         .merge(searches::router())
         .merge(mixes::router())
+        .merge(playlists::router())
+        .merge(bookmarks::router())
+        .route_layer(middleware::from_fn_with_state(state, authenticate))
+        .route("/", axum::routing::get(async || "API is running"))
+        .merge(auth::public_router())
 }
@@ -1,25 +1,142 @@
+use std::time::SystemTime;
+
 use anyhow::Context;
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, State},
+    http::{HeaderMap, header},
+    middleware,
     routing::{post, put},
 };
 use serde::Deserialize;
 use tokio::task::spawn_blocking;
 
 use crate::{
-    index::{Rating, TrackID},
+    arts::ArtsGarbageReport,
+    index::{AlbumID, ArtistID, GenreID, Rating, TrackID},
+    manager::Entity,
+    manager::UserDataManager,
     server::{
         HttpState,
-        utils::response::{ApiResponse, ApiResult},
+        utils::{
+            auth::{CurrentUser, require_admin},
+            response::{ApiResponse, ApiResult},
+        },
     },
+    userdata::{Listen, RatedItem, StarredItem},
 };
 
 #[rustfmt::skip]
 pub fn router() -> Router<HttpState> {
-    Router::new()
+    // Mutations of the library itself, which is shared by all users
+    let library = Router::new()
         .route("/index/update", post(update_index))
+        .route("/arts/gc", post(collect_arts_garbage))
+        .route("/album/{id}/art", put(set_album_art).delete(remove_album_art).layer(art_upload_limit()))
+        .route("/artist/{id}/art", put(set_artist_art).delete(remove_artist_art).layer(art_upload_limit()))
+        .route("/genre/{id}/art", put(set_genre_art).delete(remove_genre_art).layer(art_upload_limit()))
+        .route_layer(middleware::from_fn(require_admin));
+
+    // Mutations of the user's own data
+    Router::new()
+        .merge(library)
         .route("/tracks/{id}/rating", put(set_track_rating).delete(remove_track_rating))
+        .route("/album/{id}/rating", put(set_album_rating).delete(remove_album_rating))
+        .route("/artist/{id}/rating", put(set_artist_rating).delete(remove_artist_rating))
+        .route("/tracks/{id}/playback", post(report_playback))
+        .route("/tracks/{id}/star", put(star_track).delete(unstar_track))
+        .route("/album/{id}/star", put(star_album).delete(unstar_album))
+        .route("/artist/{id}/star", put(star_artist).delete(unstar_artist))
+}
+
+/// Maximum size of uploaded arts
+static MAX_ART_UPLOAD_BYTES: usize = 50 * 1024 * 1024;
+
+fn art_upload_limit() -> DefaultBodyLimit {
+    DefaultBodyLimit::max(MAX_ART_UPLOAD_BYTES)
+}
+
+/// Replace an art with either an uploaded image, or a mosaic of albums (as JSON)
+async fn set_art_override(
+    state: HttpState,
+    entity: Entity,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> ApiResult<()> {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    if is_json {
+        let SetArtMosaicPayload { albums } =
+            serde_json::from_slice(&body).context("Invalid mosaic override payload")?;
+
+        spawn_blocking(move || state.set_art_override_mosaic(entity, albums)).await??;
+    } else {
+        spawn_blocking(move || state.set_art_override_image(entity, &body)).await??;
+    }
+
+    Ok(ApiResponse(()))
+}
+
+#[derive(Deserialize)]
+struct SetArtMosaicPayload {
+    albums: Vec<AlbumID>,
+}
+
+async fn remove_art_override(state: HttpState, entity: Entity) -> ApiResult<()> {
+    spawn_blocking(move || state.remove_art_override(entity)).await??;
+    Ok(ApiResponse(()))
+}
+
+async fn set_album_art(
+    State(state): State<HttpState>,
+    Path(album_id): Path<AlbumID>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult<()> {
+    set_art_override(state, Entity::Album(album_id), &headers, body).await
+}
+
+async fn remove_album_art(
+    State(state): State<HttpState>,
+    Path(album_id): Path<AlbumID>,
+) -> ApiResult<()> {
+    remove_art_override(state, Entity::Album(album_id)).await
+}
+
+async fn set_artist_art(
+    State(state): State<HttpState>,
+    Path(artist_id): Path<ArtistID>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult<()> {
+    set_art_override(state, Entity::Artist(artist_id), &headers, body).await
+}
+
+async fn remove_artist_art(
+    State(state): State<HttpState>,
+    Path(artist_id): Path<ArtistID>,
+) -> ApiResult<()> {
+    remove_art_override(state, Entity::Artist(artist_id)).await
+}
+
+async fn set_genre_art(
+    State(state): State<HttpState>,
+    Path(genre_id): Path<GenreID>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult<()> {
+    set_art_override(state, Entity::Genre(genre_id), &headers, body).await
+}
+
+async fn remove_genre_art(
+    State(state): State<HttpState>,
+    Path(genre_id): Path<GenreID>,
+) -> ApiResult<()> {
+    remove_art_override(state, Entity::Genre(genre_id)).await
 }
 
 async fn update_index(State(state): State<HttpState>) -> ApiResult<()> {
@@ -27,15 +144,19 @@ async fn update_index(State(state): State<HttpState>) -> ApiResult<()> {
     Ok(ApiResponse(()))
 }
 
-async fn set_track_rating(
-    State(state): State<HttpState>,
-    Path(track_id): Path<TrackID>,
-    Json(payload): Json<SetRatingPayload>,
+async fn collect_arts_garbage(State(state): State<HttpState>) -> ApiResult<ArtsGarbageReport> {
+    let report = spawn_blocking(move || state.collect_arts_garbage()).await??;
+    Ok(ApiResponse(report))
+}
+
+async fn set_rating(
+    data: &UserDataManager<'_>,
+    item: RatedItem,
+    rating: Option<Rating>,
 ) -> ApiResult<()> {
-    state
-        .set_track_rating(track_id, payload.rating)
+    data.set_rating(item, rating)
         .await
-        .with_context(|| format!("Failed to set rating for track ID {track_id:?}"))?;
+        .with_context(|| format!("Failed to update rating for {item:?}"))?;
 
     Ok(ApiResponse(()))
 }
@@ -45,14 +166,193 @@ struct SetRatingPayload {
     rating: Rating,
 }
 
+async fn set_track_rating(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(track_id): Path<TrackID>,
+    Json(payload): Json<SetRatingPayload>,
+) -> ApiResult<()> {
+    set_rating(
+        &state.user_data(user_id),
+        RatedItem::Track(track_id),
+        Some(payload.rating),
+    )
+    .await
+}
+
 async fn remove_track_rating(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(track_id): Path<TrackID>,
+) -> ApiResult<()> {
+    set_rating(&state.user_data(user_id), RatedItem::Track(track_id), None).await
+}
+
+async fn set_album_rating(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(album_id): Path<AlbumID>,
+    Json(payload): Json<SetRatingPayload>,
+) -> ApiResult<()> {
+    set_rating(
+        &state.user_data(user_id),
+        RatedItem::Album(album_id),
+        Some(payload.rating),
+    )
+    .await
+}
+
+async fn remove_album_rating(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(album_id): Path<AlbumID>,
+) -> ApiResult<()> {
+    set_rating(&state.user_data(user_id), RatedItem::Album(album_id), None).await
+}
+
+async fn set_artist_rating(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(artist_id): Path<ArtistID>,
+    Json(payload): Json<SetRatingPayload>,
+) -> ApiResult<()> {
+    set_rating(
+        &state.user_data(user_id),
+        RatedItem::Artist(artist_id),
+        Some(payload.rating),
+    )
+    .await
+}
+
+async fn remove_artist_rating(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(artist_id): Path<ArtistID>,
+) -> ApiResult<()> {
+    set_rating(
+        &state.user_data(user_id),
+        RatedItem::Artist(artist_id),
+        None,
+    )
+    .await
+}
+
+/// Report that a track was listened to, to record it in the play history
+async fn report_playback(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(track_id): Path<TrackID>,
+    Json(payload): Json<ReportPlaybackPayload>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    let ReportPlaybackPayload { completion, client } = payload;
+
+    data.record_listen(Listen {
+        track_id,
+        listened_at: SystemTime::now(),
+        client,
+        completion,
+    })
+    .await
+    .with_context(|| format!("Failed to record playback for track ID {track_id:?}"))?;
+
+    Ok(ApiResponse(()))
+}
+
+#[derive(Deserialize)]
+struct ReportPlaybackPayload {
+    /// Ratio of the track that was listened to (between 0 and 1)
+    completion: f32,
+    client: Option<String>,
+}
+
+async fn set_starred(
+    data: &UserDataManager<'_>,
+    item: StarredItem,
+    starred: bool,
 ) -> ApiResult<()> {
-    state
-        .remove_track_rating(track_id)
+    data.set_starred(&[item], starred)
         .await
-        .with_context(|| format!("Failed to remove rating for track ID {track_id:?}"))?;
+        .with_context(|| format!("Failed to update star for {item:?}"))?;
 
     Ok(ApiResponse(()))
 }
+
+async fn star_track(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(track_id): Path<TrackID>,
+) -> ApiResult<()> {
+    set_starred(
+        &state.user_data(user_id),
+        StarredItem::Track(track_id),
+        true,
+    )
+    .await
+}
+
+async fn unstar_track(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(track_id): Path<TrackID>,
+) -> ApiResult<()> {
+    set_starred(
+        &state.user_data(user_id),
+        StarredItem::Track(track_id),
+        false,
+    )
+    .await
+}
+
+async fn star_album(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(album_id): Path<AlbumID>,
+) -> ApiResult<()> {
+    set_starred(
+        &state.user_data(user_id),
+        StarredItem::Album(album_id),
+        true,
+    )
+    .await
+}
+
+async fn unstar_album(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(album_id): Path<AlbumID>,
+) -> ApiResult<()> {
+    set_starred(
+        &state.user_data(user_id),
+        StarredItem::Album(album_id),
+        false,
+    )
+    .await
+}
+
+async fn star_artist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(artist_id): Path<ArtistID>,
+) -> ApiResult<()> {
+    set_starred(
+        &state.user_data(user_id),
+        StarredItem::Artist(artist_id),
+        true,
+    )
+    .await
+}
+
+async fn unstar_artist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(artist_id): Path<ArtistID>,
+) -> ApiResult<()> {
+    set_starred(
+        &state.user_data(user_id),
+        StarredItem::Artist(artist_id),
+        false,
+    )
+    .await
+}
@@ -0,0 +1,577 @@
+use anyhow::Context;
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    response::Response,
+    routing::{get, post, put},
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
+
+use crate::{
+    index::TrackID,
+    server::{
+        HttpState,
+        utils::{
+            auth::CurrentUser,
+            dtos::{PlaylistEntryInfos, PlaylistInfos, SmartPlaylistInfos, TrackCompleteInfos},
+            files::serve_playlist_file,
+            pagination::{Paginated, Pagination, PaginationDir},
+            response::{ApiError, ApiResponse, ApiResult},
+        },
+    },
+    userdata::{
+        PlaylistFileFormat, PlaylistID, SmartPlaylistID, SmartPlaylistRules, write_playlist_file,
+    },
+};
+
+/// Maximum size of uploaded covers
+static MAX_COVER_UPLOAD_BYTES: usize = 50 * 1024 * 1024;
+
+/// Maximum size of imported playlist files
+static MAX_PLAYLIST_FILE_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+#[rustfmt::skip]
+pub fn router() -> Router<HttpState> {
+    Router::new()
+        .route("/playlists", get(playlists).post(create_playlist))
+        .route("/playlists/import", post(import_playlist).layer(DefaultBodyLimit::max(MAX_PLAYLIST_FILE_UPLOAD_BYTES)))
+        .route("/playlist/{id}", get(playlist).patch(update_playlist).delete(delete_playlist))
+        .route("/playlist/{id}/tracks", get(playlist_tracks).post(add_playlist_tracks).delete(remove_playlist_tracks))
+        .route("/playlist/{id}/tracks/move", post(move_playlist_tracks))
+        .route("/playlist/{id}/export", get(export_playlist))
+        .route("/playlist/{id}/cover", put(set_playlist_cover).delete(remove_playlist_cover).layer(DefaultBodyLimit::max(MAX_COVER_UPLOAD_BYTES)))
+        .route("/smart-playlists", get(smart_playlists).post(create_smart_playlist))
+        .route("/smart-playlist/{id}", get(smart_playlist).patch(update_smart_playlist).delete(delete_smart_playlist))
+        .route("/smart-playlist/{id}/tracks", get(smart_playlist_tracks))
+        .route("/smart-playlist/{id}/export", get(export_smart_playlist))
+}
+
+async fn playlists(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+) -> ApiResponse<Vec<PlaylistInfos>> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let playlists = data.playlists().await;
+
+    ApiResponse(
+        playlists
+            .values()
+            .map(|playlist| PlaylistInfos::new(playlist, &index, &data))
+            .collect(),
+    )
+}
+
+async fn create_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Json(payload): Json<CreatePlaylistPayload>,
+) -> ApiResult<PlaylistInfos> {
+    let data = state.user_data(user_id);
+
+    let CreatePlaylistPayload { name, description } = payload;
+
+    let playlist = data.create_playlist(&name, description, &[]).await?;
+
+    Ok(ApiResponse(PlaylistInfos::new(
+        &playlist,
+        &*data.index().await,
+        &data,
+    )))
+}
+
+#[derive(Deserialize)]
+struct CreatePlaylistPayload {
+    name: String,
+    description: Option<String>,
+}
+
+async fn import_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Query(params): Query<ImportPlaylistQuery>,
+    body: Bytes,
+) -> ApiResult<ImportedPlaylistInfos> {
+    let data = state.user_data(user_id);
+
+    let ImportPlaylistQuery { name, format } = params;
+
+    let (playlist, unresolved_entries) =
+        data.import_playlist(name.as_deref(), &body, format).await?;
+
+    Ok(ApiResponse(ImportedPlaylistInfos {
+        playlist: PlaylistInfos::new(&playlist, &*data.index().await, &data),
+        unresolved_entries,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ImportPlaylistQuery {
+    /// Defaults to the title found in the file
+    name: Option<String>,
+    format: PlaylistFileFormat,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportedPlaylistInfos {
+    playlist: PlaylistInfos,
+
+    /// Entries no track could be found for
+    unresolved_entries: Vec<String>,
+}
+
+async fn playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+) -> ApiResult<PlaylistInfos> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let playlists = data.playlists().await;
+
+    let playlist = playlists
+        .get(&playlist_id)
+        .context("Provided playlist ID was not found")?;
+
+    Ok(ApiResponse(PlaylistInfos::new(playlist, &index, &data)))
+}
+
+async fn update_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+    Json(payload): Json<UpdatePlaylistPayload>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    let UpdatePlaylistPayload { name, description } = payload;
+
+    data.update_playlist(playlist_id, |playlist, _| {
+        if let Some(name) = name {
+            playlist.rename(&name)?;
+        }
+
+        if let Some(description) = description {
+            playlist.set_description(Some(description));
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(ApiResponse(()))
+}
+
+#[derive(Deserialize)]
+struct UpdatePlaylistPayload {
+    name: Option<String>,
+
+    /// An empty description removes the current one
+    description: Option<String>,
+}
+
+async fn delete_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    data.delete_playlist(playlist_id).await?;
+    Ok(ApiResponse(()))
+}
+
+async fn playlist_tracks(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+) -> ApiResult<Vec<PlaylistEntryInfos>> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+    let playlists = data.playlists().await;
+
+    let playlist = playlists
+        .get(&playlist_id)
+        .context("Provided playlist ID was not found")?;
+
+    Ok(ApiResponse(
+        playlist
+            .entries
+            .iter()
+            .map(|entry| PlaylistEntryInfos::new(entry, &index, &ratings, &data))
+            .collect(),
+    ))
+}
+
+async fn add_playlist_tracks(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+    Json(payload): Json<AddPlaylistTracksPayload>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    let AddPlaylistTracksPayload {
+        track_ids,
+        position,
+    } = payload;
+
+    data.update_playlist(playlist_id, |playlist, index| {
+        playlist.add_tracks(&track_ids, position, index)
+    })
+    .await?;
+
+    Ok(ApiResponse(()))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddPlaylistTracksPayload {
+    track_ids: Vec<TrackID>,
+
+    /// Position to insert the tracks at (defaults to the end of the playlist)
+    position: Option<usize>,
+}
+
+async fn remove_playlist_tracks(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+    Json(payload): Json<RemovePlaylistTracksPayload>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    data.update_playlist(playlist_id, |playlist, _| {
+        playlist.remove_entries(&payload.positions)
+    })
+    .await?;
+
+    Ok(ApiResponse(()))
+}
+
+#[derive(Deserialize)]
+struct RemovePlaylistTracksPayload {
+    positions: Vec<usize>,
+}
+
+async fn move_playlist_tracks(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+    Json(payload): Json<MovePlaylistTracksPayload>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    let MovePlaylistTracksPayload { from, count, to } = payload;
+
+    data.update_playlist(playlist_id, |playlist, _| {
+        playlist.move_entries(from, count.unwrap_or(1), to)
+    })
+    .await?;
+
+    Ok(ApiResponse(()))
+}
+
+#[derive(Deserialize)]
+struct MovePlaylistTracksPayload {
+    from: usize,
+
+    /// Number of consecutive entries to move (defaults to 1)
+    count: Option<usize>,
+
+    to: usize,
+}
+
+async fn export_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+    Query(params): Query<ExportPlaylistQuery>,
+) -> Result<Response, ApiError> {
+    let data = state.user_data(user_id);
+
+    let ExportPlaylistQuery {
+        format,
+        absolute_paths,
+    } = params;
+
+    let index = data.index().await;
+    let playlists = data.playlists().await;
+
+    let playlist = playlists
+        .get(&playlist_id)
+        .context("Provided playlist ID was not found")?;
+
+    // Entries whose track disappeared from the index cannot be exported
+    let tracks = playlist
+        .entries
+        .iter()
+        .filter_map(|entry| index.tracks.get(&entry.track_id))
+        .collect::<Vec<_>>();
+
+    let content = write_playlist_file(
+        format,
+        &playlist.name,
+        &tracks,
+        &index,
+        absolute_paths.then(|| data.music_dir()),
+    );
+
+    Ok(serve_playlist_file(&playlist.name, format, content))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportPlaylistQuery {
+    format: PlaylistFileFormat,
+
+    /// Write absolute paths instead of paths relative to the music directory
+    #[serde(default)]
+    absolute_paths: bool,
+}
+
+async fn set_playlist_cover(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+    body: Bytes,
+) -> ApiResult<()> {
+    spawn_blocking(move || {
+        state
+            .user_data(user_id)
+            .set_playlist_cover(playlist_id, &body)
+    })
+    .await??;
+    Ok(ApiResponse(()))
+}
+
+async fn remove_playlist_cover(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(playlist_id): Path<PlaylistID>,
+) -> ApiResult<()> {
+    spawn_blocking(move || state.user_data(user_id).remove_playlist_cover(playlist_id)).await??;
+    Ok(ApiResponse(()))
+}
+
+async fn smart_playlists(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+) -> ApiResponse<Vec<SmartPlaylistInfos>> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+    let smart_playlists = data.smart_playlists().await;
+
+    ApiResponse(
+        smart_playlists
+            .values()
+            .map(|smart_playlist| SmartPlaylistInfos::new(smart_playlist, &index, &ratings, &data))
+            .collect(),
+    )
+}
+
+async fn create_smart_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Json(payload): Json<CreateSmartPlaylistPayload>,
+) -> ApiResult<SmartPlaylistInfos> {
+    let data = state.user_data(user_id);
+
+    let CreateSmartPlaylistPayload {
+        name,
+        description,
+        rules,
+    } = payload;
+
+    let smart_playlist = data
+        .create_smart_playlist(&name, description, rules)
+        .await?;
+
+    Ok(ApiResponse(SmartPlaylistInfos::new(
+        &smart_playlist,
+        &*data.index().await,
+        &*data.ratings().await,
+        &data,
+    )))
+}
+
+#[derive(Deserialize)]
+struct CreateSmartPlaylistPayload {
+    name: String,
+    description: Option<String>,
+    rules: SmartPlaylistRules,
+}
+
+async fn smart_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(smart_playlist_id): Path<SmartPlaylistID>,
+) -> ApiResult<SmartPlaylistInfos> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+    let smart_playlists = data.smart_playlists().await;
+
+    let smart_playlist = smart_playlists
+        .get(&smart_playlist_id)
+        .context("Provided smart playlist ID was not found")?;
+
+    Ok(ApiResponse(SmartPlaylistInfos::new(
+        smart_playlist,
+        &index,
+        &ratings,
+        &data,
+    )))
+}
+
+async fn update_smart_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(smart_playlist_id): Path<SmartPlaylistID>,
+    Json(payload): Json<UpdateSmartPlaylistPayload>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    let UpdateSmartPlaylistPayload {
+        name,
+        description,
+        rules,
+    } = payload;
+
+    data.update_smart_playlist(smart_playlist_id, |smart_playlist| {
+        if let Some(name) = name {
+            smart_playlist.rename(&name)?;
+        }
+
+        if let Some(description) = description {
+            smart_playlist.set_description(Some(description));
+        }
+
+        if let Some(rules) = rules {
+            smart_playlist.set_rules(rules)?;
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(ApiResponse(()))
+}
+
+#[derive(Deserialize)]
+struct UpdateSmartPlaylistPayload {
+    name: Option<String>,
+
+    /// An empty description removes the current one
+    description: Option<String>,
+
+    rules: Option<SmartPlaylistRules>,
+}
+
+async fn delete_smart_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(smart_playlist_id): Path<SmartPlaylistID>,
+) -> ApiResult<()> {
+    let data = state.user_data(user_id);
+
+    data.delete_smart_playlist(smart_playlist_id).await?;
+    Ok(ApiResponse(()))
+}
+
+async fn smart_playlist_tracks(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(smart_playlist_id): Path<SmartPlaylistID>,
+    Query(params): Query<SmartPlaylistTracksQuery>,
+) -> ApiResult<Paginated<TrackCompleteInfos>> {
+    let data = state.user_data(user_id);
+
+    let SmartPlaylistTracksQuery { limit, offset } = params;
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+    let smart_playlists = data.smart_playlists().await;
+
+    let smart_playlist = smart_playlists
+        .get(&smart_playlist_id)
+        .context("Provided smart playlist ID was not found")?;
+
+    let track_ids = smart_playlist
+        .rules
+        .evaluate(&index, &ratings, &data.play_stats());
+
+    Ok(ApiResponse(Paginated::paginate(
+        track_ids.into_iter().map(|track_id| {
+            TrackCompleteInfos::new(
+                index.tracks.get(&track_id).unwrap().clone(),
+                &index,
+                &ratings,
+                &data,
+            )
+        }),
+        Pagination {
+            limit,
+            offset,
+            dir: PaginationDir::Asc,
+        },
+    )))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SmartPlaylistTracksQuery {
+    // Cannot flatten [`Pagination`] because of axum limitations
+    // See https://github.com/serde-rs/serde/issues/1183
+    limit: usize,
+    offset: Option<usize>,
+}
+
+async fn export_smart_playlist(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+    Path(smart_playlist_id): Path<SmartPlaylistID>,
+    Query(params): Query<ExportPlaylistQuery>,
+) -> Result<Response, ApiError> {
+    let data = state.user_data(user_id);
+
+    let ExportPlaylistQuery {
+        format,
+        absolute_paths,
+    } = params;
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+    let smart_playlists = data.smart_playlists().await;
+
+    let smart_playlist = smart_playlists
+        .get(&smart_playlist_id)
+        .context("Provided smart playlist ID was not found")?;
+
+    let tracks = smart_playlist
+        .rules
+        .evaluate(&index, &ratings, &data.play_stats())
+        .into_iter()
+        .map(|track_id| index.tracks.get(&track_id).unwrap())
+        .collect::<Vec<_>>();
+
+    let content = write_playlist_file(
+        format,
+        &smart_playlist.name,
+        &tracks,
+        &index,
+        absolute_paths.then(|| data.music_dir()),
+    );
+
+    Ok(serve_playlist_file(&smart_playlist.name, format, content))
+}
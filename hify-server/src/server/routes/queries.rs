@@ -11,8 +11,10 @@ use crate::{
     server::{
         HttpState,
         utils::{
+            auth::CurrentUser,
             dtos::{
-                AlbumCompleteInfos, ArtistCompleteInfos, GenreCompleteInfos, TrackCompleteInfos,
+                AlbumCompleteInfos, ArtistCompleteInfos, GenreCompleteInfos, StarredInfos,
+                TrackCompleteInfos,
             },
             pagination::{Paginated, Pagination, PaginationDir},
             response::{ApiError, ApiResponse, ApiResult},
@@ -43,6 +45,7 @@ pub fn router() -> Router<HttpState> {
         .route("/genres", get(genres))
         .route("/genre/{id}", get(genre))
         .route("/genre/{id}/albums", get(genre_albums))
+        .route("/starred", get(starred))
 }
 
 async fn ping(State(_): State<HttpState>) -> ApiResponse<&'static str> {
@@ -51,8 +54,11 @@ async fn ping(State(_): State<HttpState>) -> ApiResponse<&'static str> {
 
 async fn artists(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<ArtistsQuery>,
 ) -> ApiResponse<Paginated<ArtistCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let ArtistsQuery {
         sort_by,
         limit,
@@ -60,21 +66,25 @@ async fn artists(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
 
     ApiResponse(paginate_sort_artists(
         index.artists.values().collect(),
         sort_by,
         Pagination { limit, offset, dir },
         &index,
-        &*state.ratings().await,
+        &*data.ratings().await,
+        &data,
     ))
 }
 
 async fn artists_with_albums(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<ArtistsQuery>,
 ) -> ApiResponse<Paginated<ArtistCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let ArtistsQuery {
         sort_by,
         limit,
@@ -82,7 +92,7 @@ async fn artists_with_albums(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
 
     ApiResponse(paginate_sort_artists(
         index
@@ -94,7 +104,8 @@ async fn artists_with_albums(
         sort_by,
         Pagination { limit, offset, dir },
         &index,
-        &*state.ratings().await,
+        &*data.ratings().await,
+        &data,
     ))
 }
 
@@ -112,9 +123,12 @@ struct ArtistsQuery {
 
 async fn artist(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(artist_id): Path<ArtistID>,
 ) -> ApiResult<ArtistCompleteInfos> {
-    let index = state.index().await;
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
 
     let artist = index
         .artists
@@ -124,14 +138,19 @@ async fn artist(
     Ok(ApiResponse(ArtistCompleteInfos::new(
         artist.clone(),
         &index,
+        &*data.ratings().await,
+        &data,
     )))
 }
 
 async fn artist_albums(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(artist_id): Path<ArtistID>,
     Query(query): Query<ArtistAlbumsQuery>,
 ) -> ApiResult<Paginated<AlbumCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let ArtistAlbumsQuery {
         sort_by,
         limit,
@@ -139,7 +158,7 @@ async fn artist_albums(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
 
     let artist_albums = index
         .artists_albums
@@ -154,15 +173,19 @@ async fn artist_albums(
         sort_by,
         Pagination { limit, offset, dir },
         &index,
-        &*state.ratings().await,
+        &*data.ratings().await,
+        &data,
     )))
 }
 
 async fn artist_album_participations(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(artist_id): Path<ArtistID>,
     Query(query): Query<ArtistAlbumsQuery>,
 ) -> ApiResult<Paginated<AlbumCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let ArtistAlbumsQuery {
         sort_by,
         limit,
@@ -170,7 +193,7 @@ async fn artist_album_participations(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
 
     let artist_album_participations = index
         .artists_album_participations
@@ -185,7 +208,8 @@ async fn artist_album_participations(
         sort_by,
         Pagination { limit, offset, dir },
         &index,
-        &*state.ratings().await,
+        &*data.ratings().await,
+        &data,
     )))
 }
 
@@ -203,9 +227,12 @@ struct ArtistAlbumsQuery {
 
 async fn artist_track_participations(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(artist_id): Path<ArtistID>,
     Query(query): Query<ArtistTracksQuery>,
 ) -> ApiResult<Paginated<TrackCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let ArtistTracksQuery {
         sort_by,
         limit,
@@ -213,7 +240,7 @@ async fn artist_track_participations(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
 
     let artist_track_participations = index
         .artists_track_participations
@@ -228,7 +255,8 @@ async fn artist_track_participations(
         sort_by,
         Pagination { limit, offset, dir },
         &index,
-        &*state.ratings().await,
+        &*data.ratings().await,
+        &data,
     )))
 }
 
@@ -246,8 +274,11 @@ struct ArtistTracksQuery {
 
 async fn albums(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<AlbumsQuery>,
 ) -> ApiResponse<Paginated<AlbumCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let AlbumsQuery {
         sort_by,
         limit,
@@ -255,14 +286,15 @@ async fn albums(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
 
     ApiResponse(paginate_sort_albums(
         index.albums.values().collect(),
         sort_by,
         Pagination { limit, offset, dir },
         &index,
-        &*state.ratings().await,
+        &*data.ratings().await,
+        &data,
     ))
 }
 
@@ -280,29 +312,40 @@ struct AlbumsQuery {
 
 async fn album(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(album_id): Path<AlbumID>,
 ) -> ApiResult<AlbumCompleteInfos> {
-    let index = state.index().await;
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
 
     let album = index
         .albums
         .get(&album_id)
         .context("Provided album ID was not found")?;
 
-    Ok(ApiResponse(AlbumCompleteInfos::new(album.clone(), &index)))
+    Ok(ApiResponse(AlbumCompleteInfos::new(
+        album.clone(),
+        &index,
+        &*data.ratings().await,
+        &data,
+    )))
 }
 
 async fn album_tracks(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(album_id): Path<AlbumID>,
 ) -> ApiResult<Vec<TrackCompleteInfos>> {
-    let index = state.index().await;
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
 
     if !index.albums.contains_key(&album_id) {
         return Err(ApiError::new("Provided album ID was not found"));
     }
 
-    let ratings = state.ratings().await;
+    let ratings = data.ratings().await;
 
     Ok(ApiResponse(
         index
@@ -311,15 +354,18 @@ async fn album_tracks(
             .unwrap()
             .iter()
             .map(|track_id| index.tracks.get(track_id).unwrap())
-            .map(|track| TrackCompleteInfos::new(track.clone(), &index, &ratings))
+            .map(|track| TrackCompleteInfos::new(track.clone(), &index, &ratings, &data))
             .collect(),
     ))
 }
 
 async fn tracks(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<TracksQuery>,
 ) -> ApiResponse<Paginated<TrackCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let TracksQuery {
         sort_by,
         limit,
@@ -327,8 +373,8 @@ async fn tracks(
         dir,
     } = query;
 
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     ApiResponse(paginate_sort_tracks(
         index.tracks.values().collect(),
@@ -336,21 +382,25 @@ async fn tracks(
         Pagination { limit, offset, dir },
         &index,
         &ratings,
+        &data,
     ))
 }
 
 async fn multi_tracks(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<MultiTracksQuery>,
 ) -> ApiResult<Vec<TrackCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let MultiTracksQuery { ids } = query;
 
     // NOTE: this is necessary as Axum doesn't support decoding lists directly from query params
     let ids = serde_json::from_str::<Vec<TrackID>>(&ids)
         .context("Failed to decode the provided track IDs")?;
 
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     let mut tracks = vec![];
 
@@ -360,7 +410,12 @@ async fn multi_tracks(
             .get(&track_id)
             .with_context(|| format!("Provided track ID '{track_id:?}' was not found"))?;
 
-        tracks.push(TrackCompleteInfos::new(track.clone(), &index, &ratings));
+        tracks.push(TrackCompleteInfos::new(
+            track.clone(),
+            &index,
+            &ratings,
+            &data,
+        ));
     }
 
     Ok(ApiResponse(tracks))
@@ -386,10 +441,13 @@ struct TracksQuery {
 
 async fn track(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(track_id): Path<TrackID>,
 ) -> ApiResult<TrackCompleteInfos> {
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     let track = index
         .tracks
@@ -400,13 +458,17 @@ async fn track(
         track.clone(),
         &index,
         &ratings,
+        &data,
     )))
 }
 
 async fn genres(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<GenresQuery>,
 ) -> ApiResponse<Paginated<GenreCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let GenresQuery {
         sort_by,
         limit,
@@ -414,8 +476,8 @@ async fn genres(
         dir,
     } = query;
 
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     ApiResponse(paginate_sort_genres(
         index.genres.values().collect(),
@@ -423,6 +485,7 @@ async fn genres(
         Pagination { limit, offset, dir },
         &index,
         &ratings,
+        &data,
     ))
 }
 
@@ -449,14 +512,21 @@ async fn genre(
         .get(&genre_id)
         .context("Provided genre ID was not found")?;
 
-    Ok(ApiResponse(GenreCompleteInfos::new(genre.clone(), &index)))
+    Ok(ApiResponse(GenreCompleteInfos::new(
+        genre.clone(),
+        &index,
+        &state,
+    )))
 }
 
 async fn genre_albums(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Path(genre_id): Path<GenreID>,
     Query(query): Query<GenreAlbumsQuery>,
 ) -> ApiResult<Paginated<AlbumCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let GenreAlbumsQuery {
         sort_by,
         limit,
@@ -464,7 +534,7 @@ async fn genre_albums(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
 
     let genre_albums = index
         .genres_albums
@@ -479,7 +549,8 @@ async fn genre_albums(
         sort_by,
         Pagination { limit, offset, dir },
         &index,
-        &*state.ratings().await,
+        &*data.ratings().await,
+        &data,
     )))
 }
 
@@ -494,3 +565,15 @@ struct GenreAlbumsQuery {
     offset: Option<usize>,
     dir: PaginationDir,
 }
+
+async fn starred(
+    State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
+) -> ApiResponse<StarredInfos> {
+    let data = state.user_data(user_id);
+
+    let index = data.index().await;
+    let ratings = data.ratings().await;
+
+    ApiResponse(StarredInfos::new(&index, &ratings, &data))
+}
@@ -8,6 +8,7 @@ use serde::Deserialize;
 use crate::server::{
     HttpState,
     utils::{
+        auth::CurrentUser,
         dtos::{AlbumCompleteInfos, ArtistCompleteInfos, TrackCompleteInfos},
         pagination::{Paginated, Pagination, PaginationDir},
         response::ApiResponse,
@@ -25,8 +26,11 @@ pub fn router() -> Router<HttpState> {
 
 async fn search_tracks(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<SearchQuery>,
 ) -> ApiResponse<Paginated<TrackCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let SearchQuery {
         query,
         limit,
@@ -34,19 +38,22 @@ async fn search_tracks(
         dir,
     } = query;
 
-    let index = state.index().await;
-    let ratings = state.ratings().await;
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     ApiResponse(
         search::search_tracks(&query, Pagination { limit, offset, dir }, &index, &ratings)
-            .map(|track| TrackCompleteInfos::new(track, &index, &ratings)),
+            .map(|track| TrackCompleteInfos::new(track, &index, &ratings, &data)),
     )
 }
 
 async fn search_albums(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<SearchQuery>,
 ) -> ApiResponse<Paginated<AlbumCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let SearchQuery {
         query,
         limit,
@@ -54,18 +61,22 @@ async fn search_albums(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     ApiResponse(
         search::search_albums(&query, Pagination { limit, offset, dir }, &index)
-            .map(|album| AlbumCompleteInfos::new(album, &index)),
+            .map(|album| AlbumCompleteInfos::new(album, &index, &ratings, &data)),
     )
 }
 
 async fn search_artists(
     State(state): State<HttpState>,
+    CurrentUser(user_id): CurrentUser,
     Query(query): Query<SearchQuery>,
 ) -> ApiResponse<Paginated<ArtistCompleteInfos>> {
+    let data = state.user_data(user_id);
+
     let SearchQuery {
         query,
         limit,
@@ -73,11 +84,12 @@ async fn search_artists(
         dir,
     } = query;
 
-    let index = state.index().await;
+    let index = data.index().await;
+    let ratings = data.ratings().await;
 
     ApiResponse(
         search::search_artists(&query, Pagination { limit, offset, dir }, &index)
-            .map(|artist| ArtistCompleteInfos::new(artist, &index)),
+            .map(|artist| ArtistCompleteInfos::new(artist, &index, &ratings, &data)),
     )
 }
 
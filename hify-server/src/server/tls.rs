@@ -0,0 +1,255 @@
+use std::{
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result, ensure};
+use axum::{
+    Router,
+    http::{HeaderMap, StatusCode, Uri, header},
+    response::{IntoResponse, Redirect, Response},
+    serve::Listener,
+};
+use colored::Colorize;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    time::{sleep, timeout},
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+    server::TlsStream,
+};
+
+use super::shutdown_signal;
+
+/// Interval between two checks of the certificate files for changes
+static CERT_RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Maximum duration of a TLS handshake before the connection is dropped
+static HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of established connections waiting to be served
+static PENDING_CONNECTIONS: usize = 128;
+
+/// Settings of HTTPS
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsSettings {
+    /// Path to the certificate chain, in PEM format
+    pub cert_path: PathBuf,
+
+    /// Path to the private key, in PEM format
+    pub key_path: PathBuf,
+
+    /// Port of an HTTP listener redirecting all requests to HTTPS
+    #[serde(default)]
+    pub http_redirect_port: Option<u16>,
+}
+
+impl TlsSettings {
+    pub fn validate(&self, https_port: u16) -> Result<()> {
+        ensure!(
+            self.http_redirect_port != Some(https_port),
+            "HTTP redirection port must be different from the HTTPS port"
+        );
+
+        Ok(())
+    }
+}
+
+/// Listener accepting TLS connections
+///
+/// Handshakes are performed in the background, so slow clients don't delay other ones.
+/// The certificate is reloaded whenever its files are modified.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, settings: &TlsSettings) -> Result<Self> {
+        let server_config = Arc::new(RwLock::new(Arc::new(
+            load_server_config(settings).context("Failed to load TLS certificate")?,
+        )));
+
+        let local_addr = listener.local_addr()?;
+
+        let (sender, connections) = mpsc::channel(PENDING_CONNECTIONS);
+
+        tokio::spawn(accept_connections(
+            listener,
+            Arc::clone(&server_config),
+            sender,
+        ));
+
+        tokio::spawn(watch_certificate(settings.clone(), server_config));
+
+        Ok(Self {
+            local_addr,
+            connections,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accepting task never stops on its own
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+async fn accept_connections(
+    mut listener: TcpListener,
+    server_config: Arc<RwLock<Arc<ServerConfig>>>,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    loop {
+        // Accept errors are logged and retried by the underlying listener
+        let (stream, addr) = Listener::accept(&mut listener).await;
+
+        let acceptor = TlsAcceptor::from(Arc::clone(&server_config.read().unwrap()));
+        let sender = sender.clone();
+
+        tokio::spawn(async move {
+            match timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    // Only fails if the server is shutting down
+                    let _ = sender.send((stream, addr)).await;
+                }
+
+                Ok(Err(err)) => debug!("TLS handshake with {addr} failed: {err}"),
+
+                Err(_) => debug!("TLS handshake with {addr} timed out"),
+            }
+        });
+    }
+}
+
+/// Reload the certificate whenever its files are modified
+async fn watch_certificate(settings: TlsSettings, server_config: Arc<RwLock<Arc<ServerConfig>>>) {
+    let mut last_modified = cert_files_mtime(&settings);
+
+    loop {
+        sleep(CERT_RELOAD_CHECK_INTERVAL).await;
+
+        let modified = cert_files_mtime(&settings);
+
+        if modified == last_modified {
+            continue;
+        }
+
+        last_modified = modified;
+
+        match load_server_config(&settings) {
+            Ok(config) => {
+                *server_config.write().unwrap() = Arc::new(config);
+                info!("Reloaded TLS certificate");
+            }
+
+            // Files may be modified one after the other, so keep the previous certificate
+            // until both of them are valid again
+            Err(err) => {
+                warn!("Failed to reload TLS certificate, keeping the previous one: {err:?}");
+            }
+        }
+    }
+}
+
+fn cert_files_mtime(settings: &TlsSettings) -> [Option<SystemTime>; 2] {
+    let mtime = |path: &Path| fs::metadata(path).and_then(|mt| mt.modified()).ok();
+    [mtime(&settings.cert_path), mtime(&settings.key_path)]
+}
+
+fn load_server_config(settings: &TlsSettings) -> Result<ServerConfig> {
+    let TlsSettings {
+        cert_path,
+        key_path,
+        http_redirect_port: _,
+    } = settings;
+
+    let cert_chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .with_context(|| format!("Failed to read certificate at '{}'", cert_path.display()))?;
+
+    ensure!(
+        !cert_chain.is_empty(),
+        "No certificate found in '{}'",
+        cert_path.display()
+    );
+
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("Failed to read private key at '{}'", key_path.display()))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .context("Failed to set up TLS protocol versions")?
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .context("Invalid certificate or private key")?;
+
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(config)
+}
+
+/// Serve plain HTTP requests by redirecting them to HTTPS
+pub async fn serve_https_redirect(addr: SocketAddr, https_port: u16) -> Result<()> {
+    let app = Router::new().fallback(async move |headers: HeaderMap, uri: Uri| {
+        redirect_to_https(&headers, &uri, https_port)
+    });
+
+    info!(
+        "> Redirecting HTTP requests from {} to HTTPS",
+        addr.to_string().bright_green()
+    );
+
+    let listener = TcpListener::bind(addr).await?;
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    Ok(())
+}
+
+fn redirect_to_https(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Response {
+    let Some(host) = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Uri>().ok())
+        .and_then(|host| host.host().map(str::to_owned))
+    else {
+        return (StatusCode::BAD_REQUEST, "Missing or invalid host header").into_response();
+    };
+
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+
+    let location = if https_port == 443 {
+        format!("https://{host}{path}")
+    } else {
+        format!("https://{host}:{https_port}{path}")
+    };
+
+    Redirect::permanent(&location).into_response()
+}
@@ -0,0 +1,92 @@
+use axum::{
+    extract::{FromRequestParts, Query, Request, State},
+    http::{HeaderMap, StatusCode, Uri, header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+
+use crate::{
+    server::HttpState,
+    userdata::{UserID, UserRole},
+};
+
+use super::response::error_response;
+
+/// User a request was authenticated as
+///
+/// Only available on routes behind the [`authenticate`] middleware.
+#[derive(Clone, Copy)]
+pub struct CurrentUser(pub UserID);
+
+impl FromRequestParts<HttpState> for CurrentUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _: &HttpState) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().copied().ok_or_else(|| {
+            error_response(StatusCode::UNAUTHORIZED, "Request was not authenticated")
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenParams {
+    token: Option<String>,
+}
+
+/// Middleware rejecting requests that don't provide a valid device token
+///
+/// The authenticated user is made available to handlers through the [`CurrentUser`] extractor.
+pub async fn authenticate(
+    State(state): State<HttpState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = request_token(request.headers(), request.uri());
+
+    let Some(token) = token else {
+        return error_response(StatusCode::UNAUTHORIZED, "No device token was provided");
+    };
+
+    let Some(user) = state.find_device_token_user(&token).await else {
+        return error_response(StatusCode::UNAUTHORIZED, "Provided device token is invalid");
+    };
+
+    request.extensions_mut().insert(CurrentUser(user.id));
+    request.extensions_mut().insert(user.role);
+
+    next.run(request).await
+}
+
+/// Get the device token provided by a request
+///
+/// The token is provided in the `Authorization` header as a bearer token, or in the `token`
+/// query parameter for clients that cannot set headers (e.g. media elements in browsers).
+pub fn request_token(headers: &HeaderMap, uri: &Uri) -> Option<String> {
+    match headers.get(header::AUTHORIZATION) {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::to_owned),
+
+        None => Query::<TokenParams>::try_from_uri(uri)
+            .ok()
+            .and_then(|Query(TokenParams { token })| token),
+    }
+}
+
+/// Middleware rejecting requests from users that are not administrators
+///
+/// Must be applied behind the [`authenticate`] middleware.
+pub async fn require_admin(request: Request, next: Next) -> Response {
+    match request.extensions().get::<UserRole>() {
+        Some(UserRole::Admin) => next.run(request).await,
+
+        Some(UserRole::Listener) => {
+            error_response(StatusCode::FORBIDDEN, "Only administrators can do this")
+        }
+
+        None => error_response(StatusCode::UNAUTHORIZED, "Request was not authenticated"),
+    }
+}
@@ -0,0 +1,177 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, request::Parts, uri::Authority},
+};
+
+use crate::server::HttpState;
+
+/// Base of the URIs handed out to clients (e.g. `https://example.org/hify`)
+///
+/// Built from the configured base path and, if they are trusted,
+/// from the `X-Forwarded-Proto`, `X-Forwarded-Host` and `X-Forwarded-Prefix` headers.
+/// A forwarded prefix replaces the configured base path, as it describes the path
+/// the proxy exposes the server under. Without a forwarded host, the URI is relative
+/// to the server's root.
+pub struct BaseUri(String);
+
+impl BaseUri {
+    /// Append an absolute path (e.g. `/rest/getCoverArt`) to the base URI
+    pub fn join(&self, path: &str) -> String {
+        format!("{}{path}", self.0)
+    }
+
+    fn compute(base_path: &str, trust_forwarded_headers: bool, headers: &HeaderMap) -> Self {
+        if !trust_forwarded_headers {
+            return Self(base_path.to_owned());
+        }
+
+        let path = forwarded_header(headers, "x-forwarded-prefix")
+            .filter(|prefix| {
+                prefix.starts_with('/')
+                    && !prefix.starts_with("//")
+                    && !prefix.contains(|c: char| c.is_whitespace() || matches!(c, '?' | '#'))
+            })
+            .map_or(base_path, |prefix| prefix.trim_end_matches('/'));
+
+        let origin = forwarded_header(headers, "x-forwarded-host")
+            .filter(|host| host.parse::<Authority>().is_ok())
+            .map(|host| {
+                let proto = forwarded_header(headers, "x-forwarded-proto")
+                    .filter(|proto| matches!(*proto, "http" | "https"))
+                    .unwrap_or("http");
+
+                format!("{proto}://{host}")
+            });
+
+        Self(format!("{}{path}", origin.as_deref().unwrap_or("")))
+    }
+}
+
+impl FromRequestParts<HttpState> for BaseUri {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &HttpState,
+    ) -> Result<Self, Self::Rejection> {
+        let settings = &state.settings().server;
+
+        Ok(Self::compute(
+            settings.base_path_prefix(),
+            settings.trust_forwarded_headers,
+            &parts.headers,
+        ))
+    }
+}
+
+/// Get the first value of a forwarding header, as proxies may append their own
+fn forwarded_header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderName, HeaderValue};
+
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|&(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trusted_headers() {
+        for (base_path, pairs, expected) in [
+            ("", &[][..], ""),
+            ("/hify", &[], "/hify"),
+            (
+                "",
+                &[("x-forwarded-host", "example.org")],
+                "http://example.org",
+            ),
+            (
+                "/hify",
+                &[
+                    ("x-forwarded-host", "example.org:8443"),
+                    ("x-forwarded-proto", "https"),
+                ],
+                "https://example.org:8443/hify",
+            ),
+            (
+                "/hify",
+                &[
+                    ("x-forwarded-host", "example.org"),
+                    ("x-forwarded-prefix", "/music/"),
+                ],
+                "http://example.org/music",
+            ),
+            ("/hify", &[("x-forwarded-prefix", "/")], ""),
+            ("", &[("x-forwarded-prefix", "/music")], "/music"),
+            (
+                "",
+                &[
+                    ("x-forwarded-host", "example.org, internal:8891"),
+                    ("x-forwarded-proto", "https, http"),
+                ],
+                "https://example.org",
+            ),
+        ] {
+            assert_eq!(
+                BaseUri::compute(base_path, true, &headers(pairs)).0,
+                expected,
+                "Unexpected base URI for base path '{base_path}' and headers {pairs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejected_headers() {
+        for (pairs, expected) in [
+            (&[("x-forwarded-host", "example.org/path")][..], "/hify"),
+            (&[("x-forwarded-host", "")], "/hify"),
+            (
+                &[
+                    ("x-forwarded-host", "example.org"),
+                    ("x-forwarded-proto", "ftp"),
+                ],
+                "http://example.org/hify",
+            ),
+            (&[("x-forwarded-prefix", "music")], "/hify"),
+            (&[("x-forwarded-prefix", "//example.org")], "/hify"),
+            (&[("x-forwarded-prefix", "/music?a=b")], "/hify"),
+            (&[("x-forwarded-prefix", "/my music")], "/hify"),
+        ] {
+            assert_eq!(
+                BaseUri::compute("/hify", true, &headers(pairs)).0,
+                expected,
+                "Unexpected base URI for headers {pairs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_untrusted_headers() {
+        let headers = headers(&[
+            ("x-forwarded-host", "example.org"),
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-prefix", "/music"),
+        ]);
+
+        assert_eq!(BaseUri::compute("/hify", false, &headers).0, "/hify");
+    }
+}
@@ -1,8 +1,16 @@
+use std::{path::PathBuf, time::SystemTime};
+
 use serde::Serialize;
 
 use crate::{
+    arts::ArtColors,
     index::{Album, Artist, Genre, IndexCache, Rating, Track},
-    manager::Ratings,
+    manager::{DataManager, Entity, UserDataManager},
+    userdata::{
+        Bookmark, PlayQueue, Playlist, PlaylistEntry, PlaylistID, Ratings, SmartPlaylist,
+        SmartPlaylistID, SmartPlaylistRules, StarredItem, TrackSnapshot, User, UserID, UserRole,
+        most_recent_first,
+    },
 };
 
 #[derive(Serialize)]
@@ -11,11 +19,25 @@ pub struct ArtistCompleteInfos {
     artist: Artist,
     albums_count: usize,
     tracks_count: usize,
+    art_colors: Option<ArtColors>,
+    starred_at: Option<SystemTime>,
+    rating: Option<Rating>,
+    /// Average rating (from 0 to 5) of the artist's rated tracks
+    tracks_average_rating: Option<f32>,
 }
 
 impl ArtistCompleteInfos {
-    pub fn new(artist: Artist, index: &IndexCache) -> Self {
+    pub fn new(
+        artist: Artist,
+        index: &IndexCache,
+        ratings: &Ratings,
+        data: &UserDataManager,
+    ) -> Self {
         Self {
+            art_colors: data.get_art_colors(Entity::Artist(artist.id)),
+            starred_at: data.starred_at(StarredItem::Artist(artist.id)),
+            rating: ratings.artists.get(&artist.id).copied(),
+            tracks_average_rating: ratings.artist_tracks_average(artist.id, index),
             albums_count: index.artists_albums.get(&artist.id).unwrap().len(),
             tracks_count: index.artists_tracks.get(&artist.id).unwrap().len()
                 + index
@@ -35,16 +57,30 @@ pub struct AlbumCompleteInfos {
     artists: Vec<ArtistCompleteInfos>,
     genres: Vec<GenreCompleteInfos>,
     tracks_count: usize,
+    art_colors: Option<ArtColors>,
+    starred_at: Option<SystemTime>,
+    rating: Option<Rating>,
+    /// Average rating (from 0 to 5) of the album's rated tracks
+    tracks_average_rating: Option<f32>,
 }
 
 impl AlbumCompleteInfos {
-    pub fn new(album: Album, index: &IndexCache) -> Self {
+    pub fn new(
+        album: Album,
+        index: &IndexCache,
+        ratings: &Ratings,
+        data: &UserDataManager,
+    ) -> Self {
         Self {
+            art_colors: data.get_art_colors(Entity::Album(album.id)),
+            starred_at: data.starred_at(StarredItem::Album(album.id)),
+            rating: ratings.albums.get(&album.id).copied(),
+            tracks_average_rating: ratings.album_tracks_average(album.id, index),
             artists: album
                 .artists_id
                 .iter()
                 .map(|artist| index.artists.get(artist).unwrap())
-                .map(|artist| ArtistCompleteInfos::new(artist.clone(), index))
+                .map(|artist| ArtistCompleteInfos::new(artist.clone(), index, ratings, data))
                 .collect(),
 
             genres: index
@@ -53,7 +89,7 @@ impl AlbumCompleteInfos {
                 .unwrap()
                 .iter()
                 .map(|genre| index.genres.get(genre).unwrap())
-                .map(|genre| GenreCompleteInfos::new(genre.clone(), index))
+                .map(|genre| GenreCompleteInfos::new(genre.clone(), index, data))
                 .collect(),
 
             tracks_count: index.albums_tracks.get(&album.id).unwrap().len(),
@@ -71,11 +107,20 @@ pub struct TrackCompleteInfos {
     genres: Vec<GenreCompleteInfos>,
     album: AlbumCompleteInfos,
     rating: Option<Rating>,
+    play_count: u32,
+    last_played_at: Option<SystemTime>,
+    starred_at: Option<SystemTime>,
 }
 
 impl TrackCompleteInfos {
-    pub fn new(track: Track, index: &IndexCache, ratings: &Ratings) -> Self {
+    pub fn new(
+        track: Track,
+        index: &IndexCache,
+        ratings: &Ratings,
+        data: &UserDataManager,
+    ) -> Self {
         let album = index.albums.get(&track.tags.album_id).unwrap().clone();
+        let play_stats = data.track_play_stats(track.id);
 
         Self {
             artists: track
@@ -83,7 +128,7 @@ impl TrackCompleteInfos {
                 .artists_id
                 .iter()
                 .map(|artist| index.artists.get(artist).unwrap())
-                .map(|artist| ArtistCompleteInfos::new(artist.clone(), index))
+                .map(|artist| ArtistCompleteInfos::new(artist.clone(), index, ratings, data))
                 .collect(),
 
             genres: track
@@ -91,12 +136,15 @@ impl TrackCompleteInfos {
                 .genres_id
                 .iter()
                 .map(|genre| index.genres.get(genre).unwrap())
-                .map(|genre| GenreCompleteInfos::new(genre.clone(), index))
+                .map(|genre| GenreCompleteInfos::new(genre.clone(), index, data))
                 .collect(),
 
-            album: AlbumCompleteInfos::new(album, index),
+            album: AlbumCompleteInfos::new(album, index, ratings, data),
 
-            rating: ratings.get(&track.id).copied(),
+            rating: ratings.tracks.get(&track.id).copied(),
+            play_count: play_stats.play_count,
+            last_played_at: play_stats.last_played_at,
+            starred_at: data.starred_at(StarredItem::Track(track.id)),
 
             track,
         }
@@ -109,14 +157,284 @@ pub struct GenreCompleteInfos {
     genre: Genre,
     albums_count: usize,
     tracks_count: usize,
+    art_colors: Option<ArtColors>,
 }
 
 impl GenreCompleteInfos {
-    pub fn new(genre: Genre, index: &IndexCache) -> Self {
+    pub fn new(genre: Genre, index: &IndexCache, data: &DataManager) -> Self {
         Self {
+            art_colors: data.get_art_colors(Entity::Genre(genre.id)),
             albums_count: index.genres_albums.get(&genre.id).unwrap().len(),
             tracks_count: index.genres_tracks.get(&genre.id).unwrap().len(),
             genre,
         }
     }
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistInfos {
+    id: PlaylistID,
+    name: String,
+    description: Option<String>,
+    created_at: SystemTime,
+    updated_at: SystemTime,
+    tracks_count: usize,
+    missing_tracks_count: usize,
+    duration_s: u32,
+    art_colors: Option<ArtColors>,
+
+    /// Path of the playlist file this playlist was imported from, relative to the music directory
+    imported_from: Option<PathBuf>,
+}
+
+impl PlaylistInfos {
+    pub fn new(playlist: &Playlist, index: &IndexCache, data: &DataManager) -> Self {
+        let Playlist {
+            id,
+            name,
+            description,
+            entries,
+            created_at,
+            updated_at,
+            source,
+        } = playlist;
+
+        Self {
+            id: *id,
+            name: name.clone(),
+            description: description.clone(),
+            created_at: *created_at,
+            updated_at: *updated_at,
+            tracks_count: entries.len(),
+            missing_tracks_count: entries
+                .iter()
+                .filter(|entry| !index.tracks.contains_key(&entry.track_id))
+                .count(),
+            duration_s: entries.iter().map(|entry| entry.snapshot.duration_s).sum(),
+            art_colors: data
+                .playlist_art_entity(playlist, index)
+                .and_then(|entity| data.get_art_colors(entity)),
+            imported_from: source.as_ref().map(|source| source.relative_path.clone()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartPlaylistInfos {
+    id: SmartPlaylistID,
+    name: String,
+    description: Option<String>,
+    rules: SmartPlaylistRules,
+    created_at: SystemTime,
+    updated_at: SystemTime,
+    tracks_count: usize,
+    duration_s: u32,
+}
+
+impl SmartPlaylistInfos {
+    pub fn new(
+        smart_playlist: &SmartPlaylist,
+        index: &IndexCache,
+        ratings: &Ratings,
+        data: &UserDataManager,
+    ) -> Self {
+        let SmartPlaylist {
+            id,
+            name,
+            description,
+            rules,
+            created_at,
+            updated_at,
+        } = smart_playlist;
+
+        let track_ids = rules.evaluate(index, ratings, &data.play_stats());
+
+        Self {
+            id: *id,
+            name: name.clone(),
+            description: description.clone(),
+            rules: rules.clone(),
+            created_at: *created_at,
+            updated_at: *updated_at,
+            tracks_count: track_ids.len(),
+            duration_s: track_ids
+                .iter()
+                .map(|track_id| index.tracks.get(track_id).unwrap().metadata.duration_s)
+                .sum(),
+        }
+    }
+}
+
+/// A playlist's entry, whose track is missing if it disappeared from the index
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistEntryInfos {
+    track: Option<TrackCompleteInfos>,
+    snapshot: TrackSnapshot,
+    added_at: SystemTime,
+}
+
+impl PlaylistEntryInfos {
+    pub fn new(
+        entry: &PlaylistEntry,
+        index: &IndexCache,
+        ratings: &Ratings,
+        data: &UserDataManager,
+    ) -> Self {
+        Self {
+            track: index
+                .tracks
+                .get(&entry.track_id)
+                .map(|track| TrackCompleteInfos::new(track.clone(), index, ratings, data)),
+            snapshot: entry.snapshot.clone(),
+            added_at: entry.added_at,
+        }
+    }
+}
+
+/// Starred artists, albums and tracks, most recently starred first
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StarredInfos {
+    artists: Vec<ArtistCompleteInfos>,
+    albums: Vec<AlbumCompleteInfos>,
+    tracks: Vec<TrackCompleteInfos>,
+}
+
+impl StarredInfos {
+    pub fn new(index: &IndexCache, ratings: &Ratings, data: &UserDataManager) -> Self {
+        // Stars are released before building the DTOs, as they lock them again
+        let (artists, albums, tracks) = {
+            let stars = data.stars();
+
+            (
+                most_recent_first(&stars.artists, &index.artists),
+                most_recent_first(&stars.albums, &index.albums),
+                most_recent_first(&stars.tracks, &index.tracks),
+            )
+        };
+
+        Self {
+            artists: artists
+                .into_iter()
+                .map(|artist| ArtistCompleteInfos::new(artist.clone(), index, ratings, data))
+                .collect(),
+
+            albums: albums
+                .into_iter()
+                .map(|album| AlbumCompleteInfos::new(album.clone(), index, ratings, data))
+                .collect(),
+
+            tracks: tracks
+                .into_iter()
+                .map(|track| TrackCompleteInfos::new(track.clone(), index, ratings, data))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkInfos {
+    track: TrackCompleteInfos,
+    position_ms: u64,
+    comment: Option<String>,
+    created_at: SystemTime,
+    changed_at: SystemTime,
+}
+
+impl BookmarkInfos {
+    /// Returns `None` if the bookmarked track is not in the index anymore
+    pub fn new(
+        bookmark: Bookmark,
+        index: &IndexCache,
+        ratings: &Ratings,
+        data: &UserDataManager,
+    ) -> Option<Self> {
+        let track = index.tracks.get(&bookmark.track_id)?;
+
+        Some(Self {
+            track: TrackCompleteInfos::new(track.clone(), index, ratings, data),
+            position_ms: bookmark.position_ms,
+            comment: bookmark.comment,
+            created_at: bookmark.created_at,
+            changed_at: bookmark.changed_at,
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayQueueInfos {
+    tracks: Vec<TrackCompleteInfos>,
+    current_index: Option<usize>,
+    position_ms: u64,
+    changed_at: SystemTime,
+    changed_by: Option<String>,
+}
+
+impl PlayQueueInfos {
+    pub fn new(
+        play_queue: PlayQueue,
+        index: &IndexCache,
+        ratings: &Ratings,
+        data: &UserDataManager,
+    ) -> Self {
+        // Tracks that are not in the index anymore are skipped
+        let tracks = play_queue
+            .track_ids
+            .iter()
+            .filter_map(|track_id| index.tracks.get(track_id))
+            .collect::<Vec<_>>();
+
+        // Only count the tracks that were kept before the current one
+        let current_index = play_queue
+            .current_index
+            .filter(|&current_index| {
+                index
+                    .tracks
+                    .contains_key(&play_queue.track_ids[current_index])
+            })
+            .map(|current_index| {
+                play_queue.track_ids[..current_index]
+                    .iter()
+                    .filter(|track_id| index.tracks.contains_key(*track_id))
+                    .count()
+            });
+
+        Self {
+            tracks: tracks
+                .into_iter()
+                .map(|track| TrackCompleteInfos::new(track.clone(), index, ratings, data))
+                .collect(),
+            current_index,
+            position_ms: if current_index.is_some() {
+                play_queue.position_ms
+            } else {
+                0
+            },
+            changed_at: play_queue.changed_at,
+            changed_by: play_queue.changed_by,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInfos {
+    id: UserID,
+    name: String,
+    role: UserRole,
+}
+
+impl UserInfos {
+    pub fn new(user: &User) -> Self {
+        Self {
+            id: user.id,
+            name: user.name.clone(),
+            role: user.role,
+        }
+    }
+}
@@ -1,9 +1,19 @@
 use std::path::Path;
 
-use axum::{body::Body, extract::Request, response::Response};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{
+        HeaderValue,
+        header::{ACCEPT, CONTENT_DISPOSITION, CONTENT_TYPE, VARY},
+    },
+    response::{IntoResponse, Response},
+};
 use tower::ServiceExt;
 use tower_http::services::{ServeFile, fs::ServeFileSystemResponseBody};
 
+use crate::userdata::PlaylistFileFormat;
+
 pub type ServedFile = Response<ServeFileSystemResponseBody>;
 
 pub async fn serve_file(path: &Path, req: Request<Body>) -> ServedFile {
@@ -14,3 +24,50 @@ pub async fn serve_file(path: &Path, req: Request<Body>) -> ServedFile {
         // We can unwrap as the Err() variant is Infallible
         .unwrap()
 }
+
+/// Serve an art file whose format was negotiated using the request's `Accept` header
+pub async fn serve_art_file(path: &Path, req: Request<Body>) -> ServedFile {
+    let mut res = serve_file(path, req).await;
+
+    res.headers_mut()
+        .append(VARY, HeaderValue::from_static("accept"));
+
+    res
+}
+
+pub fn accept_header<B>(req: &Request<B>) -> Option<&str> {
+    req.headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Serve a generated playlist file as a download
+pub fn serve_playlist_file(name: &str, format: PlaylistFileFormat, content: String) -> Response {
+    // Only keep characters that are safe in file names and headers
+    let file_name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    (
+        [
+            (CONTENT_TYPE, format.mime_type().to_owned()),
+            (
+                CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    file_name.trim(),
+                    format.extension()
+                ),
+            ),
+        ],
+        content,
+    )
+        .into_response()
+}
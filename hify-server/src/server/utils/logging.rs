@@ -2,10 +2,12 @@ use axum::{extract::Request, middleware::Next, response::Response};
 use colored::Colorize;
 use log::{debug, error};
 
-// TODO: don't log authentication username/password from (Open)Subsonic
+/// Query parameters whose value must never be logged
+static CREDENTIALS_PARAMS: &[&str] = &["p", "t", "s", "apiKey", "token"];
+
 pub async fn log_errors(request: Request, next: Next) -> Response {
     let path = request.uri().path().to_owned();
-    let query = request.uri().query().map(str::to_owned);
+    let query = request.uri().query().map(redact_credentials);
     let method = request.method().as_str().to_owned();
 
     let res = next.run(request).await;
@@ -29,3 +31,64 @@ pub async fn log_errors(request: Request, next: Next) -> Response {
 
     res
 }
+
+/// Replace the value of credentials parameters in a query string
+///
+/// Keys are decoded before being compared, as percent-encoded keys are decoded by the routes too.
+fn redact_credentials(query: &str) -> String {
+    query
+        .split('&')
+        .map(|param| {
+            let key = param.split_once('=').map_or(param, |(key, _)| key);
+
+            let is_credential = serde_urlencoded::from_str::<Vec<(String, String)>>(key)
+                .ok()
+                .and_then(|decoded| decoded.into_iter().next())
+                .is_some_and(|(key, _)| CREDENTIALS_PARAMS.contains(&key.as_str()));
+
+            if is_credential {
+                format!("{key}=<redacted>")
+            } else {
+                param.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_credentials() {
+        let cases = [
+            ("", ""),
+            ("id=abc&f=json", "id=abc&f=json"),
+            ("u=alice&p=sesame&f=json", "u=alice&p=<redacted>&f=json"),
+            ("u=alice&p=enc:736573616d65", "u=alice&p=<redacted>"),
+            (
+                "u=alice&t=0123abcd&s=c19b2d&v=1.16.1",
+                "u=alice&t=<redacted>&s=<redacted>&v=1.16.1",
+            ),
+            ("apiKey=secret&id=abc", "apiKey=<redacted>&id=abc"),
+            ("token=secret", "token=<redacted>"),
+            // Empty values and parameters without a value
+            ("p=&id=abc", "p=<redacted>&id=abc"),
+            ("p&id=abc", "p=<redacted>&id=abc"),
+            // Values containing separators
+            ("p=a%3Db%26c&id=abc", "p=<redacted>&id=abc"),
+            ("p=a=b", "p=<redacted>"),
+            // Percent-encoded keys
+            ("api%4Bey=secret", "api%4Bey=<redacted>"),
+            ("%70=secret", "%70=<redacted>"),
+            // Keys are case-sensitive, like in the routes
+            ("P=value&apikey=value", "P=value&apikey=value"),
+            ("pp=value&username=alice", "pp=value&username=alice"),
+        ];
+
+        for (query, expected) in cases {
+            assert_eq!(redact_credentials(query), expected, "query: {query}");
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use serde::Deserialize;
 
 use crate::{
-    index::{ArtistID, GenreID, IndexCache, Rating},
-    manager::Ratings,
+    index::{ArtistID, GenreID, IndexCache, Rating, Track},
+    manager::UserDataManager,
+    userdata::Ratings,
     utils::{Rng, deterministic_shuffle},
 };
 
@@ -15,18 +16,29 @@ pub fn generate_mix(
     params: UserMixParams,
     index: &IndexCache,
     ratings: &Ratings,
+    data: &UserDataManager,
     pagination: Pagination,
 ) -> Paginated<TrackCompleteInfos> {
+    Paginated::paginate(
+        mix_tracks(params, index, ratings)
+            .into_iter()
+            .map(|track| TrackCompleteInfos::new(track.clone(), index, ratings, data)),
+        pagination,
+    )
+}
+
+/// Compute the tracks of a mix, in order
+pub fn mix_tracks<'a>(
+    params: UserMixParams,
+    index: &'a IndexCache,
+    ratings: &Ratings,
+) -> Vec<&'a Track> {
     let UserMixParams {
         source,
         filter,
         seed,
     } = params;
 
-    #[allow(
-        clippy::needless_collect,
-        reason = "required to get an ExactSizeIterator"
-    )]
     let mut tracks = index
         .tracks
         .values()
@@ -35,22 +47,18 @@ pub fn generate_mix(
             UserMixSource::Artist(artist_id) => track.tags.artists_id.contains(&artist_id),
             UserMixSource::Genre(genre_id) => track.tags.genres_id.contains(&genre_id),
         })
-        .filter(|track| match filter {
-            UserMixFilter::NotRated => ratings.get(&track.id).is_none(),
+        .filter(|track| {
+            // Tracks without a rating inherit the one of their album or artists,
+            // except when looking for tracks that were not rated yet
+            let rating = ratings.inherited_track_rating(track);
 
-            UserMixFilter::NotBadlyRated => ratings
-                .get(&track.id)
-                .is_none_or(|rating| *rating > Rating::Two),
-
-            UserMixFilter::WellRated => ratings
-                .get(&track.id)
-                .is_some_and(|rating| *rating >= Rating::Four),
-
-            UserMixFilter::BestRated => ratings
-                .get(&track.id)
-                .is_some_and(|rating| *rating == Rating::Five),
-
-            UserMixFilter::IncludeAll => true,
+            match filter {
+                UserMixFilter::NotRated => !ratings.tracks.contains_key(&track.id),
+                UserMixFilter::NotBadlyRated => rating.is_none_or(|rating| rating > Rating::Two),
+                UserMixFilter::WellRated => rating.is_some_and(|rating| rating >= Rating::Four),
+                UserMixFilter::BestRated => rating == Some(Rating::Five),
+                UserMixFilter::IncludeAll => true,
+            }
         })
         .collect::<Vec<_>>();
 
@@ -59,12 +67,7 @@ pub fn generate_mix(
         &mut Rng::with_seed(seed, Rng::DEFAULT_INCREMENT),
     );
 
-    Paginated::paginate(
-        tracks
-            .into_iter()
-            .map(|track| TrackCompleteInfos::new(track.clone(), index, ratings)),
-        pagination,
-    )
+    tracks
 }
 
 #[derive(Deserialize, Clone, Copy)]
@@ -85,7 +88,7 @@ pub enum UserMixSource {
 #[derive(Deserialize, Clone, Copy)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum UserMixFilter {
-    NotRated,      // rating = None
+    NotRated,      // track's own rating = None
     NotBadlyRated, // rating = Some(>= 3)
     WellRated,     // rating = Some(>= 4)
     BestRated,     // rating = Some(5)
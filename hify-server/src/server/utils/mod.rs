@@ -1,3 +1,5 @@
+pub mod auth;
+pub mod base_uri;
 pub mod dtos;
 pub mod files;
 pub mod logging;
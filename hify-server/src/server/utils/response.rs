@@ -32,15 +32,20 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
         let Self(data) = self;
 
-        let res = json!({
-            "ok": false,
-            "cause": format!("{data:?}")
-        });
-
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(res)).into_response()
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("{data:?}"))
     }
 }
 
+/// Build a failed response with a specific status code, shaped like the ones of [`ApiError`]
+pub fn error_response(status: StatusCode, cause: &str) -> axum::response::Response {
+    let res = json!({
+        "ok": false,
+        "cause": cause
+    });
+
+    (status, Json(res)).into_response()
+}
+
 // This enables using `?` on functions that return `Result<_, anyhow::Error>` to turn them into
 // `Result<_, AppError>`. That way you don't need to do that manually.
 impl<E> From<E> for ApiError
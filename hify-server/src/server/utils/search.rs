@@ -4,7 +4,7 @@ use regex::Regex;
 
 use crate::{
     index::{Album, Artist, CmpIndex, IndexCache, Rating, Track, cmp_artists},
-    manager::Ratings,
+    userdata::Ratings,
 };
 
 use super::pagination::{Paginated, Pagination};
@@ -31,7 +31,7 @@ pub fn search_tracks(
                 score += contains_with_multiplier(&album.name, word) * 3;
             }
 
-            if let Some(rating) = ratings.get(&track.id) {
+            if let Some(rating) = ratings.tracks.get(&track.id) {
                 score = match rating {
                     Rating::Zero => 0,
                     Rating::One => score / 4,
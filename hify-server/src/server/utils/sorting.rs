@@ -1,14 +1,17 @@
 // TODO: stable sort (to keep the order of the index for equal elements)
 
+use std::cmp::Ordering;
+
 use serde::Deserialize;
 
 use crate::{
     index::{Album, Artist, CmpIndex, Genre, IndexCache, Rating, Track},
-    manager::Ratings,
+    manager::UserDataManager,
     server::utils::{
         dtos::{AlbumCompleteInfos, ArtistCompleteInfos, TrackCompleteInfos},
         pagination::{Paginated, Pagination},
     },
+    userdata::Ratings,
 };
 
 use super::dtos::GenreCompleteInfos;
@@ -19,6 +22,7 @@ pub fn paginate_sort_artists(
     pagination: Pagination,
     index: &IndexCache,
     ratings: &Ratings,
+    data: &UserDataManager,
 ) -> Paginated<ArtistCompleteInfos> {
     match sort {
         ArtistsSort::Name => {
@@ -50,16 +54,33 @@ pub fn paginate_sort_artists(
                     .iter()
                     .filter(|track_id| {
                         ratings
+                            .tracks
                             .get(track_id)
                             .is_some_and(|rating| *rating >= Rating::Four)
                     })
                     .count()
             });
         }
+
+        ArtistsSort::Rating => {
+            artists.sort_by(|a, b| {
+                ratings
+                    .artists
+                    .get(&a.id)
+                    .cmp(&ratings.artists.get(&b.id))
+                    .then_with(|| {
+                        cmp_average_ratings(
+                            ratings.artist_tracks_average(a.id, index),
+                            ratings.artist_tracks_average(b.id, index),
+                        )
+                    })
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+        }
     }
 
     Paginated::paginate(artists.into_iter(), pagination)
-        .map(|artist| ArtistCompleteInfos::new(artist.clone(), index))
+        .map(|artist| ArtistCompleteInfos::new(artist.clone(), index, ratings, data))
 }
 
 #[derive(Deserialize, Clone, Copy)]
@@ -69,6 +90,7 @@ pub enum ArtistsSort {
     AlbumsCount,
     TracksCount,
     GreatTracksCount,
+    Rating,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -78,6 +100,7 @@ pub fn paginate_sort_albums(
     pagination: Pagination,
     index: &IndexCache,
     ratings: &Ratings,
+    data: &UserDataManager,
 ) -> Paginated<AlbumCompleteInfos> {
     let cmp_index = CmpIndex::new(index);
 
@@ -140,7 +163,7 @@ pub fn paginate_sort_albums(
 
                 album_tracks
                     .iter()
-                    .filter(|track_id| ratings.get(track_id).is_some())
+                    .filter(|track_id| ratings.tracks.contains_key(track_id))
                     .count()
             };
 
@@ -149,7 +172,7 @@ pub fn paginate_sort_albums(
 
                 album_tracks
                     .iter()
-                    .filter(|track_id| ratings.get(track_id).is_none())
+                    .filter(|track_id| !ratings.tracks.contains_key(track_id))
                     .count()
             };
 
@@ -171,7 +194,7 @@ pub fn paginate_sort_albums(
 
                 album_tracks
                     .iter()
-                    .filter(|track_id| ratings.get(track_id).is_some())
+                    .filter(|track_id| ratings.tracks.contains_key(track_id))
                     .count()
             };
 
@@ -191,16 +214,33 @@ pub fn paginate_sort_albums(
                     .iter()
                     .filter(|track_id| {
                         ratings
+                            .tracks
                             .get(track_id)
                             .is_some_and(|rating| *rating >= Rating::Four)
                     })
                     .count()
             });
         }
+
+        AlbumsSort::Rating => {
+            albums.sort_by(|a, b| {
+                ratings
+                    .albums
+                    .get(&a.id)
+                    .cmp(&ratings.albums.get(&b.id))
+                    .then_with(|| {
+                        cmp_average_ratings(
+                            ratings.album_tracks_average(a.id, index),
+                            ratings.album_tracks_average(b.id, index),
+                        )
+                    })
+                    .then_with(|| cmp_index.cmp_albums(a, b))
+            });
+        }
     }
 
     Paginated::paginate(albums.into_iter(), pagination)
-        .map(|album| AlbumCompleteInfos::new(album.clone(), index))
+        .map(|album| AlbumCompleteInfos::new(album.clone(), index, ratings, data))
 }
 
 #[derive(Deserialize, Clone, Copy)]
@@ -214,6 +254,7 @@ pub enum AlbumsSort {
     UnratedFirst,
     RatedTracksCount,
     BestTracksCount,
+    Rating,
 }
 
 pub fn paginate_sort_tracks(
@@ -222,6 +263,7 @@ pub fn paginate_sort_tracks(
     pagination: Pagination,
     index: &IndexCache,
     ratings: &Ratings,
+    data: &UserDataManager,
 ) -> Paginated<TrackCompleteInfos> {
     match sort {
         TracksSort::Title => {
@@ -237,12 +279,12 @@ pub fn paginate_sort_tracks(
         }
 
         TracksSort::UserRating => {
-            tracks.sort_by_key(|track| ratings.get(&track.id));
+            tracks.sort_by_key(|track| ratings.tracks.get(&track.id));
         }
     }
 
     Paginated::paginate(tracks.into_iter(), pagination)
-        .map(|track| TrackCompleteInfos::new(track.clone(), index, ratings))
+        .map(|track| TrackCompleteInfos::new(track.clone(), index, ratings, data))
 }
 
 #[derive(Deserialize, Clone, Copy)]
@@ -260,6 +302,7 @@ pub fn paginate_sort_genres(
     pagination: Pagination,
     index: &IndexCache,
     ratings: &Ratings,
+    data: &UserDataManager,
 ) -> Paginated<GenreCompleteInfos> {
     match sort {
         GenresSort::Name => {
@@ -285,6 +328,7 @@ pub fn paginate_sort_genres(
                     .iter()
                     .filter(|track_id| {
                         ratings
+                            .tracks
                             .get(track_id)
                             .is_some_and(|rating| *rating >= Rating::Four)
                     })
@@ -294,7 +338,7 @@ pub fn paginate_sort_genres(
     }
 
     Paginated::paginate(genres.into_iter(), pagination)
-        .map(|genre| GenreCompleteInfos::new(genre.clone(), index))
+        .map(|genre| GenreCompleteInfos::new(genre.clone(), index, data))
 }
 
 #[derive(Deserialize, Clone, Copy)]
@@ -305,3 +349,11 @@ pub enum GenresSort {
     TracksCount,
     GreatTracksCount,
 }
+
+/// Compare average ratings, items without any rated track coming first
+fn cmp_average_ratings(a: Option<f32>, b: Option<f32>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        _ => a.is_some().cmp(&b.is_some()),
+    }
+}
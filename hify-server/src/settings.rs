@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, ensure};
+use log::debug;
+use serde::Deserialize;
+
+use crate::{
+    arts::{ArtSettings, CoverSelectionRules},
+    indexer::IndexerSettings,
+    server::ServerSettings,
+};
+
+/// Name of the configuration file, located in the data directory
+pub static CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Settings of the whole server, loaded from the configuration file
+///
+/// Every key is optional and falls back to its default value.
+/// Command-line arguments take precedence over the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Maximum number of tasks run concurrently when updating the index and generating arts
+    pub max_concurrent_tasks: usize,
+
+    /// Path to a directory containing images for genres, named after them (e.g. 'Jazz.jpg')
+    pub genres_art_dir: Option<PathBuf>,
+
+    /// Maximum size (in MiB) of the cache for arts rendered at non-preset sizes
+    pub resized_arts_cache_mib: u64,
+
+    pub server: ServerSettings,
+    pub indexer: IndexerSettings,
+    pub arts: ArtSettings,
+    pub covers: CoverSelectionRules,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_tasks: 32,
+            genres_art_dir: None,
+            resized_arts_cache_mib: 512,
+            server: ServerSettings::default(),
+            indexer: IndexerSettings::default(),
+            arts: ArtSettings::default(),
+            covers: CoverSelectionRules::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Load the configuration file from the data directory, if it exists
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(CONFIG_FILE_NAME);
+
+        if !path.exists() {
+            debug!("> No configuration file found, using default settings.");
+            return Ok(Self::default());
+        }
+
+        debug!("> Loading configuration file...");
+
+        let config_str = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read configuration file '{}'", path.display()))?;
+
+        toml::from_str::<Self>(&config_str)
+            .with_context(|| format!("Failed to parse configuration file '{}'", path.display()))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let Self {
+            max_concurrent_tasks,
+            genres_art_dir: _,
+            resized_arts_cache_mib: _,
+            server,
+            indexer,
+            arts,
+            covers,
+        } = self;
+
+        ensure!(
+            *max_concurrent_tasks > 0,
+            "Maximum number of concurrent tasks ('max_concurrent_tasks') cannot be zero"
+        );
+
+        server
+            .validate()
+            .context("Invalid server settings ('server' section)")?;
+
+        indexer
+            .validate()
+            .context("Invalid indexer settings ('indexer' section)")?;
+
+        arts.validate()
+            .context("Invalid art settings ('arts' section)")?;
+
+        covers
+            .validate()
+            .context("Invalid cover selection rules ('covers' section)")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::SystemTime;
+
+use anyhow::{Result, ensure};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::index::TrackID;
+
+pub type Bookmarks = IndexMap<TrackID, Bookmark>;
+
+/// A position saved in a track, to resume listening to it later
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub track_id: TrackID,
+    pub position_ms: u64,
+    pub comment: Option<String>,
+    pub created_at: SystemTime,
+    pub changed_at: SystemTime,
+}
+
+impl Bookmark {
+    pub fn new(track_id: TrackID, position_ms: u64, comment: Option<String>) -> Self {
+        let now = SystemTime::now();
+
+        Self {
+            track_id,
+            position_ms,
+            comment: normalize_comment(comment),
+            created_at: now,
+            changed_at: now,
+        }
+    }
+
+    pub fn update(&mut self, position_ms: u64, comment: Option<String>) {
+        self.position_ms = position_ms;
+        self.comment = normalize_comment(comment);
+        self.changed_at = SystemTime::now();
+    }
+}
+
+fn normalize_comment(comment: Option<String>) -> Option<String> {
+    comment
+        .map(|comment| comment.trim().to_owned())
+        .filter(|comment| !comment.is_empty())
+}
+
+/// A play queue saved by a client, so it can be resumed on another device
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayQueue {
+    pub track_ids: Vec<TrackID>,
+
+    /// Index of the track being played in the queue
+    pub current_index: Option<usize>,
+
+    /// Position in the current track
+    pub position_ms: u64,
+
+    pub changed_at: SystemTime,
+
+    /// Name of the client that saved the queue, if provided
+    pub changed_by: Option<String>,
+}
+
+impl PlayQueue {
+    pub fn new(
+        track_ids: Vec<TrackID>,
+        current_index: Option<usize>,
+        position_ms: u64,
+        changed_by: Option<String>,
+    ) -> Result<Self> {
+        ensure!(
+            current_index.is_none_or(|current_index| current_index < track_ids.len()),
+            "Current track index is out of the queue"
+        );
+
+        Ok(Self {
+            track_ids,
+            current_index,
+            position_ms: if current_index.is_some() {
+                position_ms
+            } else {
+                0
+            },
+            changed_at: SystemTime::now(),
+            changed_by,
+        })
+    }
+
+    pub fn current_track_id(&self) -> Option<TrackID> {
+        self.current_index
+            .map(|current_index| self.track_ids[current_index])
+    }
+}
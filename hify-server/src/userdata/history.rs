@@ -0,0 +1,53 @@
+use std::{collections::HashMap, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::index::TrackID;
+
+/// Play statistics of all tracks that were played at least once
+pub type PlayStats = HashMap<TrackID, TrackPlayStats>;
+
+/// A listen of a track, as reported by a client
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Listen {
+    pub track_id: TrackID,
+    pub listened_at: SystemTime,
+
+    /// Name of the client the track was played with, if provided
+    pub client: Option<String>,
+
+    /// Ratio of the track that was listened to (between 0 and 1)
+    pub completion: f32,
+}
+
+impl Listen {
+    /// Listens only count as plays when at least half of the track was listened to
+    pub fn counts_as_play(&self) -> bool {
+        self.completion >= 0.5
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackPlayStats {
+    pub play_count: u32,
+    pub last_played_at: Option<SystemTime>,
+}
+
+impl TrackPlayStats {
+    pub fn record(&mut self, listen: &Listen) {
+        if !listen.counts_as_play() {
+            return;
+        }
+
+        self.play_count += 1;
+
+        if self
+            .last_played_at
+            .is_none_or(|last_played_at| last_played_at < listen.listened_at)
+        {
+            self.last_played_at = Some(listen.listened_at);
+        }
+    }
+}
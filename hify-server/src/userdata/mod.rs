@@ -0,0 +1,13 @@
+mod bookmarks;
+mod history;
+mod playlist_files;
+mod playlists;
+mod ratings;
+mod smart_playlists;
+mod stars;
+mod users;
+
+pub use self::{
+    bookmarks::*, history::*, playlist_files::*, playlists::*, ratings::*, smart_playlists::*,
+    stars::*, users::*,
+};
@@ -0,0 +1,740 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    path::{Component, Path, PathBuf},
+    sync::LazyLock,
+};
+
+use anyhow::{Result, bail};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::index::{IndexCache, Track, TrackID};
+
+/// Format of a playlist file exchanged with other software
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistFileFormat {
+    /// Extended M3U playlist, also used to read legacy `.m3u` files
+    M3u8,
+    Xspf,
+}
+
+impl PlaylistFileFormat {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "m3u" | "m3u8" => Some(Self::M3u8),
+            "xspf" => Some(Self::Xspf),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::M3u8 => "m3u8",
+            Self::Xspf => "xspf",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::M3u8 => "audio/x-mpegurl",
+            Self::Xspf => "application/xspf+xml",
+        }
+    }
+}
+
+/// Content of a playlist file, before its entries are matched against the index
+#[derive(Debug)]
+pub struct PlaylistFile {
+    pub title: Option<String>,
+    pub entries: Vec<PlaylistFileEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct PlaylistFileEntry {
+    /// Path of the track, as written in the file (URIs are decoded)
+    pub location: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_s: Option<u32>,
+}
+
+impl PlaylistFile {
+    pub fn parse(content: &[u8], format: PlaylistFileFormat) -> Result<Self> {
+        // Legacy M3U files are often encoded in Latin-1
+        let content = match std::str::from_utf8(content) {
+            Ok(content) => content.to_owned(),
+            Err(_) => content.iter().copied().map(char::from).collect(),
+        };
+
+        let content = content.trim_start_matches('\u{feff}');
+
+        match format {
+            PlaylistFileFormat::M3u8 => Ok(parse_m3u(content)),
+            PlaylistFileFormat::Xspf => parse_xspf(content),
+        }
+    }
+}
+
+fn parse_m3u(content: &str) -> PlaylistFile {
+    let mut title = None;
+    let mut entries = vec![];
+    let mut next_entry = PlaylistFileEntry::default();
+
+    for line in content.lines() {
+        let line = line.trim();
+
+        if let Some(infos) = line.strip_prefix("#EXTINF:") {
+            // Format is '#EXTINF:<duration> [attributes],<artist> - <title>'
+            let (duration, display) = infos.split_once(',').unwrap_or((infos, ""));
+
+            next_entry.duration_s = duration
+                .split_whitespace()
+                .next()
+                .and_then(|duration| duration.parse::<i64>().ok())
+                .and_then(|duration| u32::try_from(duration).ok());
+
+            match display.split_once(" - ") {
+                Some((artist, title)) => {
+                    next_entry.artist = Some(artist.trim().to_owned());
+                    next_entry.title = Some(title.trim().to_owned());
+                }
+
+                None if !display.trim().is_empty() => {
+                    next_entry.title = Some(display.trim().to_owned());
+                }
+
+                None => {}
+            }
+        } else if let Some(album) = line.strip_prefix("#EXTALB:") {
+            next_entry.album = Some(album.trim().to_owned());
+        } else if let Some(playlist_title) = line.strip_prefix("#PLAYLIST:") {
+            title = Some(playlist_title.trim().to_owned());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            next_entry.location = match line.strip_prefix("file://") {
+                Some(path) => percent_decode(path),
+                None => line.to_owned(),
+            };
+
+            entries.push(std::mem::take(&mut next_entry));
+        }
+    }
+
+    PlaylistFile { title, entries }
+}
+
+fn parse_xspf(content: &str) -> Result<PlaylistFile> {
+    let Some((header, track_list)) = content.split_once("<trackList>") else {
+        bail!("No track list found in XSPF playlist");
+    };
+
+    let title = XSPF_TAG
+        .captures_iter(header)
+        .find(|captures| &captures[1] == "title")
+        .map(|captures| xml_unescape(&captures[2]));
+
+    let entries = XSPF_TRACK
+        .captures_iter(track_list)
+        .map(|track| {
+            let mut entry = PlaylistFileEntry::default();
+
+            for captures in XSPF_TAG.captures_iter(&track[1]) {
+                let value = xml_unescape(captures[2].trim());
+
+                match &captures[1] {
+                    "location" => {
+                        entry.location = percent_decode(
+                            value
+                                .strip_prefix("file://localhost")
+                                .or_else(|| value.strip_prefix("file://"))
+                                .unwrap_or(&value),
+                        );
+                    }
+
+                    "title" => entry.title = Some(value),
+                    "creator" => entry.artist = Some(value),
+                    "album" => entry.album = Some(value),
+                    "duration" => entry.duration_s = value.parse::<u32>().ok().map(|ms| ms / 1000),
+
+                    _ => {}
+                }
+            }
+
+            entry
+        })
+        .collect();
+
+    Ok(PlaylistFile { title, entries })
+}
+
+static XSPF_TRACK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<track>(.*?)</track>").unwrap());
+
+static XSPF_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)<(location|title|creator|album|duration)>(.*?)</(?:location|title|creator|album|duration)>").unwrap()
+});
+
+/// Result of the matching of a playlist file's entries against the index
+#[derive(Debug)]
+pub struct PlaylistFileMatching {
+    pub track_ids: Vec<TrackID>,
+
+    /// Location of the entries no track could be found for
+    pub unresolved: Vec<String>,
+}
+
+impl PlaylistFile {
+    /// Find the tracks referenced by the playlist's entries
+    ///
+    /// Entries are first resolved by path, relative to the directory the playlist file is in
+    /// (relative to the music directory). Entries that cannot be resolved this way (e.g. files that
+    /// were moved since the playlist was written) are then matched using their tags.
+    pub fn match_tracks(
+        &self,
+        base_dir: &Path,
+        music_dir: &Path,
+        index: &IndexCache,
+    ) -> PlaylistFileMatching {
+        let tracks_by_path = index
+            .tracks
+            .values()
+            .map(|track| (track.relative_path.as_path(), track.id))
+            .collect::<HashMap<_, _>>();
+
+        let mut tracks_by_title = None::<HashMap<String, Vec<&Track>>>;
+
+        let mut track_ids = vec![];
+        let mut unresolved = vec![];
+
+        for entry in &self.entries {
+            let path = PathBuf::from(entry.location.replace('\\', "/"));
+
+            let path = match path.strip_prefix(music_dir) {
+                Ok(path) => path.to_owned(),
+                Err(_) if path.is_absolute() => path,
+                Err(_) => base_dir.join(path),
+            };
+
+            let track_id =
+                find_by_path_suffix(&normalize_path(&path), &tracks_by_path).or_else(|| {
+                    let tracks_by_title = tracks_by_title.get_or_insert_with(|| {
+                        let mut tracks_by_title = HashMap::<_, Vec<_>>::new();
+
+                        for track in index.tracks.values() {
+                            tracks_by_title
+                                .entry(normalize_text(&track.tags.title))
+                                .or_default()
+                                .push(track);
+                        }
+
+                        tracks_by_title
+                    });
+
+                    find_by_tags(entry, &path, tracks_by_title, index)
+                });
+
+            match track_id {
+                Some(track_id) => track_ids.push(track_id),
+                None => unresolved.push(entry.location.clone()),
+            }
+        }
+
+        PlaylistFileMatching {
+            track_ids,
+            unresolved,
+        }
+    }
+}
+
+/// Resolve '.' and '..' components without accessing the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Find the track whose path is the longest suffix of the provided one
+///
+/// This allows resolving absolute paths written on other machines, where the music directory
+/// is located elsewhere.
+fn find_by_path_suffix(path: &Path, tracks_by_path: &HashMap<&Path, TrackID>) -> Option<TrackID> {
+    let components = path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect::<Vec<_>>();
+
+    (0..components.len()).find_map(|start| {
+        let suffix = components[start..].iter().collect::<PathBuf>();
+        tracks_by_path.get(suffix.as_path()).copied()
+    })
+}
+
+/// Find the track matching an entry's tags best
+///
+/// When the entry doesn't provide tags, they are guessed from the file's name,
+/// e.g. '01 - Artist - Title.mp3'.
+fn find_by_tags(
+    entry: &PlaylistFileEntry,
+    path: &Path,
+    tracks_by_title: &HashMap<String, Vec<&Track>>,
+    index: &IndexCache,
+) -> Option<TrackID> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+
+    let mut stem_parts = stem
+        .split(" - ")
+        .map(str::trim)
+        .filter(|part| !part.chars().all(|c| c.is_ascii_digit() || c == '.'))
+        .collect::<Vec<_>>();
+
+    let title = match &entry.title {
+        Some(title) => title.as_str(),
+        // Strip track numbers like in '01. Title'
+        None => stem_parts
+            .pop()?
+            .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ' '),
+    };
+
+    let artist = entry.artist.as_deref().or_else(|| stem_parts.pop());
+
+    let candidates = tracks_by_title.get(&normalize_text(title))?;
+
+    let scored = candidates
+        .iter()
+        .filter_map(|track| {
+            let mut score = 0;
+
+            if let Some(duration_s) = entry.duration_s {
+                match duration_s.abs_diff(track.metadata.duration_s) {
+                    0..=2 => score += 1,
+                    3..=10 => {}
+                    _ => return None,
+                }
+            }
+
+            if let Some(artist) = artist {
+                let artist = normalize_text(artist);
+
+                let matches = track.tags.artists_id.iter().any(|artist_id| {
+                    let name = normalize_text(&index.artists.get(artist_id).unwrap().name);
+                    !name.is_empty() && (name == artist || artist.contains(&name))
+                });
+
+                if matches {
+                    score += 2;
+                }
+            }
+
+            if let Some(album) = &entry.album {
+                let album_name = &index.albums.get(&track.tags.album_id).unwrap().name;
+
+                if normalize_text(album_name) == normalize_text(album) {
+                    score += 1;
+                }
+            }
+
+            Some((score, track.id))
+        })
+        .collect::<Vec<_>>();
+
+    let best_score = scored.iter().map(|(score, _)| *score).max()?;
+
+    let mut best = scored
+        .into_iter()
+        .filter(|(score, _)| *score == best_score)
+        .map(|(_, track_id)| track_id);
+
+    // Ambiguous matches are not resolved
+    match (best.next(), best.next()) {
+        (Some(track_id), None) => Some(track_id),
+        _ => None,
+    }
+}
+
+/// Lowercase a text and only keep its alphanumeric characters, to compare tags loosely
+fn normalize_text(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Write a playlist file for other software
+///
+/// Paths are absolute when a music directory is provided, and relative to it otherwise.
+pub fn write_playlist_file(
+    format: PlaylistFileFormat,
+    name: &str,
+    tracks: &[&Track],
+    index: &IndexCache,
+    music_dir: Option<&Path>,
+) -> String {
+    let location = |track: &Track| match music_dir {
+        Some(music_dir) => music_dir.join(&track.relative_path),
+        None => track.relative_path.clone(),
+    };
+
+    let artists = |track: &Track| {
+        track
+            .tags
+            .artists_id
+            .iter()
+            .map(|artist_id| index.artists.get(artist_id).unwrap().name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut out = String::new();
+
+    match format {
+        PlaylistFileFormat::M3u8 => {
+            writeln!(out, "#EXTM3U").unwrap();
+            writeln!(out, "#PLAYLIST:{name}").unwrap();
+
+            for track in tracks {
+                writeln!(
+                    out,
+                    "#EXTINF:{},{} - {}",
+                    track.metadata.duration_s,
+                    artists(track),
+                    track.tags.title
+                )
+                .unwrap();
+
+                writeln!(out, "{}", location(track).display()).unwrap();
+            }
+        }
+
+        PlaylistFileFormat::Xspf => {
+            writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+            writeln!(
+                out,
+                r#"<playlist version="1" xmlns="http://xspf.org/ns/0/">"#
+            )
+            .unwrap();
+            writeln!(out, "  <title>{}</title>", xml_escape(name)).unwrap();
+            writeln!(out, "  <trackList>").unwrap();
+
+            for track in tracks {
+                let location = percent_encode(&location(track).to_string_lossy());
+
+                let location = match music_dir {
+                    Some(_) => format!("file://{location}"),
+                    None => location,
+                };
+
+                let album = &index.albums.get(&track.tags.album_id).unwrap().name;
+
+                writeln!(out, "    <track>").unwrap();
+                writeln!(out, "      <location>{}</location>", xml_escape(&location)).unwrap();
+                writeln!(
+                    out,
+                    "      <title>{}</title>",
+                    xml_escape(&track.tags.title)
+                )
+                .unwrap();
+                writeln!(
+                    out,
+                    "      <creator>{}</creator>",
+                    xml_escape(&artists(track))
+                )
+                .unwrap();
+                writeln!(out, "      <album>{}</album>", xml_escape(album)).unwrap();
+
+                if let Some(track_number) = track.tags.track_number {
+                    writeln!(out, "      <trackNum>{track_number}</trackNum>").unwrap();
+                }
+
+                writeln!(
+                    out,
+                    "      <duration>{}</duration>",
+                    u64::from(track.metadata.duration_s) * 1000
+                )
+                .unwrap();
+                writeln!(out, "    </track>").unwrap();
+            }
+
+            writeln!(out, "  </trackList>").unwrap();
+            writeln!(out, "</playlist>").unwrap();
+        }
+    }
+
+    out
+}
+
+fn xml_escape(str: &str) -> String {
+    str.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Decode the text content of an XML element, including CDATA sections and character references
+fn xml_unescape(str: &str) -> String {
+    let mut out = String::with_capacity(str.len());
+    let mut rest = str;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let (content, after) = cdata.split_once("]]>").unwrap_or((cdata, ""));
+
+            out.push_str(content);
+            rest = after;
+        } else if let Some((decoded, after)) = rest
+            .strip_prefix('&')
+            .and_then(|entity| entity.split_once(';'))
+            .and_then(|(name, after)| Some((xml_entity(name)?, after)))
+        {
+            out.push(decoded);
+            rest = after;
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    out
+}
+
+/// Decode a predefined XML entity or a character reference (e.g. `&#233;` or `&#xE9;`)
+fn xml_entity(name: &str) -> Option<char> {
+    match name {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "amp" => Some('&'),
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse::<u32>().ok()?,
+            };
+
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Encode a path for use in a URI
+fn percent_encode(str: &str) -> String {
+    let mut out = String::with_capacity(str.len());
+
+    for byte in str.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            out.push(char::from(byte));
+        } else {
+            write!(out, "%{byte:02X}").unwrap();
+        }
+    }
+
+    out
+}
+
+/// Decode a path from a URI (invalid sequences are kept as is)
+fn percent_decode(str: &str) -> String {
+    let bytes = str.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let decoded = (bytes[i] == b'%')
+            .then(|| str.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        if let Some(byte) = decoded {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str, format: PlaylistFileFormat) -> PlaylistFile {
+        PlaylistFile::parse(content.as_bytes(), format).unwrap()
+    }
+
+    #[test]
+    fn test_m3u_extinf() {
+        let playlist = parse(
+            "#EXTM3U\n\
+             #PLAYLIST:Road trip\n\
+             #EXTINF:215 tvg-id=\"x\",Some Artist - Some Title\n\
+             #EXTALB:Some Album\n\
+             Artist/Album/01 - Title.flac\n\
+             #EXTINF:-1,Untitled stream\n\
+             http://example.org/stream\n",
+            PlaylistFileFormat::M3u8,
+        );
+
+        assert_eq!(playlist.title.as_deref(), Some("Road trip"));
+        assert_eq!(playlist.entries.len(), 2);
+
+        let first = &playlist.entries[0];
+        assert_eq!(first.location, "Artist/Album/01 - Title.flac");
+        assert_eq!(first.duration_s, Some(215));
+        assert_eq!(first.artist.as_deref(), Some("Some Artist"));
+        assert_eq!(first.title.as_deref(), Some("Some Title"));
+        assert_eq!(first.album.as_deref(), Some("Some Album"));
+
+        let second = &playlist.entries[1];
+        assert_eq!(second.duration_s, None);
+        assert_eq!(second.artist, None);
+        assert_eq!(second.title.as_deref(), Some("Untitled stream"));
+    }
+
+    #[test]
+    fn test_m3u_paths() {
+        let playlist = parse(
+            "\u{feff}../Other/track.mp3\r\n\
+             /home/user/Music/Artist/track.mp3\r\n\
+             C:\\Music\\Artist\\track.mp3\r\n\
+             file:///home/user/My%20Music/track.mp3\r\n",
+            PlaylistFileFormat::M3u8,
+        );
+
+        let locations = playlist
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            locations,
+            [
+                "../Other/track.mp3",
+                "/home/user/Music/Artist/track.mp3",
+                "C:\\Music\\Artist\\track.mp3",
+                "/home/user/My Music/track.mp3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_m3u_latin1() {
+        let playlist = PlaylistFile::parse(
+            b"#EXTINF:10,Beyonc\xe9 - Halo\nhalo.mp3\n",
+            PlaylistFileFormat::M3u8,
+        )
+        .unwrap();
+
+        assert_eq!(playlist.entries[0].artist.as_deref(), Some("Beyoncé"));
+    }
+
+    #[test]
+    fn test_xspf() {
+        let playlist = parse(
+            r#"\u{feff}<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Rock &amp; Roll</title>
+  <trackList>
+    <track>
+      <location>file:///home/user/My%20Music/Artist/track.mp3</location>
+      <title><![CDATA[Fish & <Chips>]]></title>
+      <creator>Caf&#233; &#x26; Co</creator>
+      <album>&quot;Best&quot; &lt;of&gt;</album>
+      <duration>215500</duration>
+    </track>
+    <track>
+      <location>file://localhost/C:/Music/track.flac</location>
+    </track>
+    <track>
+      <location>../Other/track%231.ogg</location>
+      <title>A &unknown; entity</title>
+    </track>
+  </trackList>
+</playlist>"#,
+            PlaylistFileFormat::Xspf,
+        );
+
+        assert_eq!(playlist.title.as_deref(), Some("Rock & Roll"));
+        assert_eq!(playlist.entries.len(), 3);
+
+        let first = &playlist.entries[0];
+        assert_eq!(first.location, "/home/user/My Music/Artist/track.mp3");
+        assert_eq!(first.title.as_deref(), Some("Fish & <Chips>"));
+        assert_eq!(first.artist.as_deref(), Some("Café & Co"));
+        assert_eq!(first.album.as_deref(), Some("\"Best\" <of>"));
+        assert_eq!(first.duration_s, Some(215));
+
+        assert_eq!(playlist.entries[1].location, "/C:/Music/track.flac");
+        assert_eq!(playlist.entries[1].title, None);
+
+        assert_eq!(playlist.entries[2].location, "../Other/track#1.ogg");
+        assert_eq!(
+            playlist.entries[2].title.as_deref(),
+            Some("A &unknown; entity")
+        );
+    }
+
+    #[test]
+    fn test_xspf_without_track_list() {
+        assert!(PlaylistFile::parse(b"<playlist></playlist>", PlaylistFileFormat::Xspf).is_err());
+    }
+
+    #[test]
+    fn test_xml_escape_roundtrip() {
+        let text = r#"<"Rock" & 'Roll'>"#;
+        assert_eq!(xml_unescape(&xml_escape(text)), text);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path(Path::new("Playlists/../Artist/./Album/track.mp3")),
+            Path::new("Artist/Album/track.mp3")
+        );
+    }
+
+    #[test]
+    fn test_find_by_path_suffix() {
+        let track_id = TrackID::compute(Path::new("Artist/Album/track.mp3"));
+
+        let tracks_by_path = HashMap::from([(Path::new("Artist/Album/track.mp3"), track_id)]);
+
+        // Relative to the music directory
+        assert_eq!(
+            find_by_path_suffix(Path::new("Artist/Album/track.mp3"), &tracks_by_path),
+            Some(track_id)
+        );
+
+        // Absolute path written on another machine
+        assert_eq!(
+            find_by_path_suffix(
+                Path::new("/mnt/other/Music/Artist/Album/track.mp3"),
+                &tracks_by_path
+            ),
+            Some(track_id)
+        );
+
+        assert_eq!(
+            find_by_path_suffix(Path::new("Album/track.mp3"), &tracks_by_path),
+            None
+        );
+    }
+}
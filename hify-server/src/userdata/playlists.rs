@@ -0,0 +1,228 @@
+use std::{collections::HashSet, path::PathBuf, time::SystemTime};
+
+use anyhow::{Result, bail, ensure};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::{IdType, IndexCache, Track, TrackID, impl_id_type},
+    utils::{Rng, u64_base62_serialization},
+};
+
+pub type Playlists = IndexMap<PlaylistID, Playlist>;
+
+/// Playlist files (relative to the music directory) whose imported playlist was deleted by the user
+///
+/// They are not imported again, even if they are modified afterwards.
+pub type IgnoredPlaylistFiles = HashSet<PathBuf>;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlaylistID(#[serde(with = "u64_base62_serialization")] u64);
+
+impl PlaylistID {
+    pub fn generate() -> Self {
+        Self(Rng::new().next_u64())
+    }
+}
+
+impl_id_type!(PlaylistID);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Playlist {
+    pub id: PlaylistID,
+    pub name: String,
+    pub description: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+
+    /// Playlist file this playlist was imported from, if it was discovered in the music directory
+    #[serde(default)]
+    pub source: Option<PlaylistSource>,
+}
+
+/// A playlist file discovered in the music directory
+///
+/// The playlist is imported again when the file is modified.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistSource {
+    /// Path of the file, relative to the music directory
+    pub relative_path: PathBuf,
+
+    /// Modification time of the file when it was imported
+    pub mtime: SystemTime,
+}
+
+/// A track inside a playlist
+///
+/// Entries are never removed when their track disappears from the index. Instead, the snapshot is
+/// used to display them as placeholders, until the track is either found again or removed by hand.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistEntry {
+    pub track_id: TrackID,
+    pub snapshot: TrackSnapshot,
+    pub added_at: SystemTime,
+}
+
+/// Informations about a track at the time it was added to a playlist
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackSnapshot {
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub relative_path: PathBuf,
+    pub duration_s: u32,
+}
+
+impl TrackSnapshot {
+    pub fn new(track: &Track, index: &IndexCache) -> Self {
+        Self {
+            title: track.tags.title.clone(),
+            artists: track
+                .tags
+                .artists_id
+                .iter()
+                .map(|artist_id| index.artists.get(artist_id).unwrap().name.clone())
+                .collect(),
+            album: index.albums.get(&track.tags.album_id).unwrap().name.clone(),
+            relative_path: track.relative_path.clone(),
+            duration_s: track.metadata.duration_s,
+        }
+    }
+}
+
+impl Playlist {
+    pub fn new(name: &str, description: Option<String>) -> Result<Self> {
+        let now = SystemTime::now();
+
+        let mut playlist = Self {
+            id: PlaylistID::generate(),
+            name: String::new(),
+            description: None,
+            entries: vec![],
+            created_at: now,
+            updated_at: now,
+            source: None,
+        };
+
+        playlist.rename(name)?;
+        playlist.set_description(description);
+
+        Ok(playlist)
+    }
+
+    pub fn rename(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+
+        ensure!(!name.is_empty(), "Playlist name cannot be empty");
+
+        name.clone_into(&mut self.name);
+        self.updated_at = SystemTime::now();
+
+        Ok(())
+    }
+
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.description = description
+            .map(|description| description.trim().to_owned())
+            .filter(|description| !description.is_empty());
+
+        self.updated_at = SystemTime::now();
+    }
+
+    /// Insert tracks at the provided position, or at the end of the playlist
+    pub fn add_tracks(
+        &mut self,
+        track_ids: &[TrackID],
+        position: Option<usize>,
+        index: &IndexCache,
+    ) -> Result<()> {
+        let position = position.unwrap_or(self.entries.len());
+
+        ensure!(
+            position <= self.entries.len(),
+            "Insertion position is out of bounds"
+        );
+
+        let now = SystemTime::now();
+
+        let entries = track_ids
+            .iter()
+            .map(|track_id| match index.tracks.get(track_id) {
+                Some(track) => Ok(PlaylistEntry {
+                    track_id: *track_id,
+                    snapshot: TrackSnapshot::new(track, index),
+                    added_at: now,
+                }),
+
+                None => bail!("Provided track {} was not found", track_id.encode()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.entries.splice(position..position, entries);
+        self.updated_at = now;
+
+        Ok(())
+    }
+
+    /// Replace all entries with the provided tracks
+    pub fn replace_tracks(&mut self, track_ids: &[TrackID], index: &IndexCache) -> Result<()> {
+        self.entries.clear();
+        self.add_tracks(track_ids, None, index)
+    }
+
+    /// Remove the entries at the provided positions
+    pub fn remove_entries(&mut self, positions: &[usize]) -> Result<()> {
+        ensure!(
+            positions
+                .iter()
+                .all(|position| *position < self.entries.len()),
+            "Entry position is out of bounds"
+        );
+
+        let mut position = 0;
+
+        self.entries.retain(|_| {
+            let keep = !positions.contains(&position);
+            position += 1;
+            keep
+        });
+
+        self.updated_at = SystemTime::now();
+
+        Ok(())
+    }
+
+    /// Move a range of entries so that they start at the provided position
+    pub fn move_entries(&mut self, from: usize, count: usize, to: usize) -> Result<()> {
+        ensure!(
+            count > 0 && from + count <= self.entries.len(),
+            "Entries range is out of bounds"
+        );
+
+        ensure!(
+            to + count <= self.entries.len(),
+            "Destination position is out of bounds"
+        );
+
+        let moved = self.entries.drain(from..from + count).collect::<Vec<_>>();
+        self.entries.splice(to..to, moved);
+
+        self.updated_at = SystemTime::now();
+
+        Ok(())
+    }
+
+    /// Refresh the snapshot of the entries whose track is still in the index
+    pub fn refresh_snapshots(&mut self, index: &IndexCache) {
+        for entry in &mut self.entries {
+            if let Some(track) = index.tracks.get(&entry.track_id) {
+                entry.snapshot = TrackSnapshot::new(track, index);
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::index::{AlbumID, ArtistID, IndexCache, Rating, Track, TrackID};
+
+/// Ratings explicitly given to tracks, albums and artists
+///
+/// Album and artist ratings are independent from the ratings of their tracks,
+/// which can be summarized with [`Ratings::album_tracks_average`] and
+/// [`Ratings::artist_tracks_average`].
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Ratings {
+    #[serde(default)]
+    pub tracks: HashMap<TrackID, Rating>,
+    #[serde(default)]
+    pub albums: HashMap<AlbumID, Rating>,
+    #[serde(default)]
+    pub artists: HashMap<ArtistID, Rating>,
+
+    /// Average ratings of the albums' and artists' tracks, computed on first use
+    #[serde(skip)]
+    tracks_averages: OnceLock<TracksAverages>,
+}
+
+impl From<HashMap<TrackID, Rating>> for Ratings {
+    fn from(tracks: HashMap<TrackID, Rating>) -> Self {
+        Self {
+            tracks,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RatedItem {
+    Track(TrackID),
+    Album(AlbumID),
+    Artist(ArtistID),
+}
+
+impl Ratings {
+    pub fn set(&mut self, item: RatedItem, rating: Option<Rating>) {
+        if matches!(item, RatedItem::Track(_)) {
+            self.invalidate_tracks_averages();
+        }
+
+        match (item, rating) {
+            (RatedItem::Track(track_id), Some(rating)) => {
+                self.tracks.insert(track_id, rating);
+            }
+
+            (RatedItem::Track(track_id), None) => {
+                self.tracks.remove(&track_id);
+            }
+
+            (RatedItem::Album(album_id), Some(rating)) => {
+                self.albums.insert(album_id, rating);
+            }
+
+            (RatedItem::Album(album_id), None) => {
+                self.albums.remove(&album_id);
+            }
+
+            (RatedItem::Artist(artist_id), Some(rating)) => {
+                self.artists.insert(artist_id, rating);
+            }
+
+            (RatedItem::Artist(artist_id), None) => {
+                self.artists.remove(&artist_id);
+            }
+        }
+    }
+
+    /// Rating of a track, inherited from its album or artists if it isn't rated itself
+    ///
+    /// When inheriting from the artists, the best rating is used.
+    pub fn inherited_track_rating(&self, track: &Track) -> Option<Rating> {
+        self.tracks
+            .get(&track.id)
+            .or_else(|| self.albums.get(&track.tags.album_id))
+            .or_else(|| {
+                track
+                    .tags
+                    .artists_id
+                    .iter()
+                    .filter_map(|artist_id| self.artists.get(artist_id))
+                    .max()
+            })
+            .copied()
+    }
+
+    /// Average rating (from 0 to 5) of an album's rated tracks
+    pub fn album_tracks_average(&self, album_id: AlbumID, index: &IndexCache) -> Option<f32> {
+        self.tracks_averages(index).albums.get(&album_id).copied()
+    }
+
+    /// Average rating (from 0 to 5) of the rated tracks an artist appears in
+    pub fn artist_tracks_average(&self, artist_id: ArtistID, index: &IndexCache) -> Option<f32> {
+        self.tracks_averages(index).artists.get(&artist_id).copied()
+    }
+
+    /// Forget the computed average ratings, which must be done when the index changes
+    pub fn invalidate_tracks_averages(&mut self) {
+        self.tracks_averages = OnceLock::new();
+    }
+
+    fn tracks_averages(&self, index: &IndexCache) -> &TracksAverages {
+        self.tracks_averages
+            .get_or_init(|| TracksAverages::compute(&self.tracks, index))
+    }
+}
+
+/// Average ratings (from 0 to 5) of the rated tracks of every album and artist
+///
+/// They are computed in a single pass over the rated tracks, instead of going through
+/// the tracks of each album or artist every time a DTO is built.
+#[derive(Debug, Clone, Default)]
+struct TracksAverages {
+    albums: HashMap<AlbumID, f32>,
+    artists: HashMap<ArtistID, f32>,
+}
+
+impl TracksAverages {
+    fn compute(ratings: &HashMap<TrackID, Rating>, index: &IndexCache) -> Self {
+        let mut albums = HashMap::<AlbumID, (f32, f32)>::new();
+        let mut artists = HashMap::<ArtistID, (f32, f32)>::new();
+
+        for (track_id, rating) in ratings {
+            // Rated tracks may have been removed from the index
+            let Some(track) = index.tracks.get(track_id) else {
+                continue;
+            };
+
+            let rating = f32::from(rating.get_zero_to_five());
+
+            let album = index.albums.get(&track.tags.album_id).unwrap();
+
+            let (sum, count) = albums.entry(album.id).or_default();
+            *sum += rating;
+            *count += 1.0;
+
+            // Same artists as in the index's album tracks and track participations
+            let track_artists = album
+                .artists_id
+                .iter()
+                .chain(&track.tags.artists_id)
+                .chain(&track.tags.composers_id)
+                .collect::<HashSet<_>>();
+
+            for artist_id in track_artists {
+                let (sum, count) = artists.entry(*artist_id).or_default();
+                *sum += rating;
+                *count += 1.0;
+            }
+        }
+
+        Self {
+            albums: albums
+                .into_iter()
+                .map(|(album_id, (sum, count))| (album_id, sum / count))
+                .collect(),
+
+            artists: artists
+                .into_iter()
+                .map(|(artist_id, (sum, count))| (artist_id, sum / count))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use indexmap::IndexSet;
+
+    use super::*;
+    use crate::index::{Album, Artist, FileTimes, TrackAudioCodec, TrackMetadata, TrackTags};
+
+    fn make_track(album: &Album, artists: &[&Artist]) -> Track {
+        let relative_path = PathBuf::from(format!("{}/track.mp3", album.name));
+
+        Track {
+            id: TrackID::compute(&relative_path),
+            relative_path,
+            file_size_bytes: 0,
+            file_times: FileTimes {
+                ctime: None,
+                mtime: SystemTime::UNIX_EPOCH,
+            },
+            metadata: TrackMetadata {
+                duration_s: 0,
+                audio_codec: TrackAudioCodec::MP3,
+                embedded_art: None,
+            },
+            tags: TrackTags {
+                title: "Track".to_owned(),
+                artists_id: artists.iter().map(|artist| artist.id).collect(),
+                composers_id: IndexSet::new(),
+                album_id: album.id,
+                disc_number: None,
+                track_number: None,
+                genres_id: IndexSet::new(),
+                date: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_inherited_track_rating() {
+        let first_artist = Artist::new("First".to_owned());
+        let second_artist = Artist::new("Second".to_owned());
+
+        let album = Album::new("Album".to_owned(), IndexSet::from([first_artist.id]));
+
+        let track = make_track(&album, &[&first_artist, &second_artist]);
+
+        let mut ratings = Ratings::default();
+        assert_eq!(ratings.inherited_track_rating(&track), None);
+
+        // The best rating of the artists is used
+        ratings.set(RatedItem::Artist(first_artist.id), Some(Rating::Two));
+        ratings.set(RatedItem::Artist(second_artist.id), Some(Rating::Four));
+        assert_eq!(ratings.inherited_track_rating(&track), Some(Rating::Four));
+
+        // The album's rating takes precedence over the artists' ones
+        ratings.set(RatedItem::Album(album.id), Some(Rating::One));
+        assert_eq!(ratings.inherited_track_rating(&track), Some(Rating::One));
+
+        // The track's own rating takes precedence over everything else
+        ratings.set(RatedItem::Track(track.id), Some(Rating::Zero));
+        assert_eq!(ratings.inherited_track_rating(&track), Some(Rating::Zero));
+
+        ratings.set(RatedItem::Track(track.id), None);
+        assert_eq!(ratings.inherited_track_rating(&track), Some(Rating::One));
+    }
+
+    #[test]
+    fn test_inherited_track_rating_ignores_other_items() {
+        let artist = Artist::new("Artist".to_owned());
+        let other_artist = Artist::new("Other".to_owned());
+
+        let album = Album::new("Album".to_owned(), IndexSet::from([artist.id]));
+        let other_album = Album::new("Other".to_owned(), IndexSet::from([other_artist.id]));
+
+        let track = make_track(&album, &[&artist]);
+        let other_track = make_track(&other_album, &[&other_artist]);
+
+        let mut ratings = Ratings::default();
+        ratings.set(RatedItem::Artist(other_artist.id), Some(Rating::Five));
+        ratings.set(RatedItem::Album(other_album.id), Some(Rating::Five));
+        ratings.set(RatedItem::Track(other_track.id), Some(Rating::Five));
+
+        assert_eq!(ratings.inherited_track_rating(&track), None);
+    }
+}
@@ -0,0 +1,816 @@
+use std::{
+    cmp::Ordering,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Result, ensure};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::{
+        ArtistID, GenreID, IdType, IndexCache, Rating, Track, TrackAudioCodec, TrackID,
+        impl_id_type,
+    },
+    utils::{Rng, deterministic_shuffle, u64_base62_serialization},
+};
+
+use super::{PlayStats, Ratings};
+
+pub type SmartPlaylists = IndexMap<SmartPlaylistID, SmartPlaylist>;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SmartPlaylistID(#[serde(with = "u64_base62_serialization")] u64);
+
+impl SmartPlaylistID {
+    pub fn generate() -> Self {
+        Self(Rng::new().next_u64())
+    }
+}
+
+impl_id_type!(SmartPlaylistID);
+
+/// A playlist whose tracks are computed from a set of rules
+///
+/// Rules are evaluated against the index each time the tracks are requested, so the playlist
+/// follows changes in the library, in the ratings and in the play history.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartPlaylist {
+    pub id: SmartPlaylistID,
+    pub name: String,
+    pub description: Option<String>,
+    pub rules: SmartPlaylistRules,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+impl SmartPlaylist {
+    pub fn new(name: &str, description: Option<String>, rules: SmartPlaylistRules) -> Result<Self> {
+        let now = SystemTime::now();
+
+        let mut playlist = Self {
+            id: SmartPlaylistID::generate(),
+            name: String::new(),
+            description: None,
+            rules: SmartPlaylistRules::default(),
+            created_at: now,
+            updated_at: now,
+        };
+
+        playlist.rename(name)?;
+        playlist.set_description(description);
+        playlist.set_rules(rules)?;
+
+        Ok(playlist)
+    }
+
+    pub fn rename(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+
+        ensure!(!name.is_empty(), "Playlist name cannot be empty");
+
+        name.clone_into(&mut self.name);
+        self.updated_at = SystemTime::now();
+
+        Ok(())
+    }
+
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.description = description
+            .map(|description| description.trim().to_owned())
+            .filter(|description| !description.is_empty());
+
+        self.updated_at = SystemTime::now();
+    }
+
+    pub fn set_rules(&mut self, rules: SmartPlaylistRules) -> Result<()> {
+        rules.validate()?;
+
+        self.rules = rules;
+        self.updated_at = SystemTime::now();
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartPlaylistRules {
+    /// Rule tracks must match to be part of the playlist
+    pub filter: SmartPlaylistRule,
+
+    /// Order of the tracks (defaults to the index's order)
+    pub sort: Option<SmartPlaylistSort>,
+
+    /// Maximum number of tracks, applied after sorting
+    pub limit: Option<usize>,
+}
+
+impl SmartPlaylistRules {
+    pub fn validate(&self) -> Result<()> {
+        self.filter.validate()?;
+
+        ensure!(self.limit != Some(0), "Tracks limit cannot be zero");
+
+        Ok(())
+    }
+
+    /// Compute the tracks matching the rules
+    pub fn evaluate(
+        &self,
+        index: &IndexCache,
+        ratings: &Ratings,
+        play_stats: &PlayStats,
+    ) -> Vec<TrackID> {
+        let Self {
+            filter,
+            sort,
+            limit,
+        } = self;
+
+        let context = EvaluationContext {
+            ratings,
+            play_stats,
+            now: SystemTime::now(),
+        };
+
+        let mut tracks = index
+            .tracks
+            .values()
+            .filter(|track| filter.matches(track, &context))
+            .collect::<Vec<_>>();
+
+        if let Some(SmartPlaylistSort { by, desc }) = sort {
+            if let SmartPlaylistSortBy::Random = by {
+                deterministic_shuffle(&mut tracks, &mut Rng::new());
+            } else {
+                tracks.sort_by(|a, b| by.compare(a, b, index, &context));
+
+                if *desc {
+                    tracks.reverse();
+                }
+            }
+        }
+
+        tracks
+            .into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|track| track.id)
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum SmartPlaylistRule {
+    /// Match tracks that match all of the provided rules
+    All { rules: Vec<SmartPlaylistRule> },
+
+    /// Match tracks that match at least one of the provided rules
+    Any { rules: Vec<SmartPlaylistRule> },
+
+    /// Match tracks that have at least one of the provided genres
+    GenreIn { genres: Vec<GenreID> },
+
+    /// Match tracks that have at least one of the provided artists
+    ArtistIn { artists: Vec<ArtistID> },
+
+    /// Match tracks released between two years (inclusive)
+    YearRange { from: Option<u16>, to: Option<u16> },
+
+    /// Match tracks encoded with one of the provided codecs
+    CodecIn { codecs: Vec<TrackAudioCodec> },
+
+    /// Match tracks whose duration (in seconds) is in the provided range (inclusive)
+    DurationRange { min: Option<u32>, max: Option<u32> },
+
+    /// Match rated tracks whose rating satisfies the comparison
+    Rating { op: Comparison, rating: Rating },
+
+    /// Match tracks without a rating
+    NotRated,
+
+    /// Match tracks whose album is rated and whose rating satisfies the comparison
+    AlbumRating { op: Comparison, rating: Rating },
+
+    /// Match tracks with at least one rated artist whose rating satisfies the comparison
+    ArtistRating { op: Comparison, rating: Rating },
+
+    /// Match tracks added to the library in the last days
+    AddedWithinDays { days: u32 },
+
+    /// Match tracks whose play count satisfies the comparison
+    PlayCount { op: Comparison, count: u32 },
+
+    /// Match tracks that were never played
+    NeverPlayed,
+}
+
+impl Default for SmartPlaylistRule {
+    fn default() -> Self {
+        Self::All { rules: vec![] }
+    }
+}
+
+impl SmartPlaylistRule {
+    fn validate(&self) -> Result<()> {
+        match self {
+            Self::All { rules } | Self::Any { rules } => {
+                rules.iter().try_for_each(SmartPlaylistRule::validate)
+            }
+
+            Self::YearRange {
+                from: Some(from),
+                to: Some(to),
+            } => {
+                ensure!(from <= to, "Invalid years range");
+                Ok(())
+            }
+
+            Self::DurationRange {
+                min: Some(min),
+                max: Some(max),
+            } => {
+                ensure!(min <= max, "Invalid duration range");
+                Ok(())
+            }
+
+            _ => Ok(()),
+        }
+    }
+
+    fn matches(&self, track: &Track, context: &EvaluationContext) -> bool {
+        let EvaluationContext { ratings, now, .. } = context;
+
+        match self {
+            Self::All { rules } => rules.iter().all(|rule| rule.matches(track, context)),
+
+            Self::Any { rules } => rules.iter().any(|rule| rule.matches(track, context)),
+
+            Self::GenreIn { genres } => genres
+                .iter()
+                .any(|genre_id| track.tags.genres_id.contains(genre_id)),
+
+            Self::ArtistIn { artists } => artists
+                .iter()
+                .any(|artist_id| track.tags.artists_id.contains(artist_id)),
+
+            Self::YearRange { from, to } => track.tags.date.is_some_and(|date| {
+                from.is_none_or(|from| date.year >= from) && to.is_none_or(|to| date.year <= to)
+            }),
+
+            Self::CodecIn { codecs } => codecs.contains(&track.metadata.audio_codec),
+
+            Self::DurationRange { min, max } => {
+                let duration_s = track.metadata.duration_s;
+                min.is_none_or(|min| duration_s >= min) && max.is_none_or(|max| duration_s <= max)
+            }
+
+            Self::Rating { op, rating } => ratings
+                .tracks
+                .get(&track.id)
+                .is_some_and(|track_rating| op.test(track_rating.cmp(rating))),
+
+            Self::NotRated => !ratings.tracks.contains_key(&track.id),
+
+            Self::AlbumRating { op, rating } => ratings
+                .albums
+                .get(&track.tags.album_id)
+                .is_some_and(|album_rating| op.test(album_rating.cmp(rating))),
+
+            Self::ArtistRating { op, rating } => track.tags.artists_id.iter().any(|artist_id| {
+                ratings
+                    .artists
+                    .get(artist_id)
+                    .is_some_and(|artist_rating| op.test(artist_rating.cmp(rating)))
+            }),
+
+            Self::AddedWithinDays { days } => now
+                .duration_since(added_at(track))
+                .is_ok_and(|elapsed| elapsed <= Duration::from_secs(u64::from(*days) * 86_400)),
+
+            Self::PlayCount { op, count } => op.test(context.play_count(track).cmp(count)),
+
+            Self::NeverPlayed => context.play_count(track) == 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering.is_eq(),
+            Self::Ne => ordering.is_ne(),
+            Self::Lt => ordering.is_lt(),
+            Self::Le => ordering.is_le(),
+            Self::Gt => ordering.is_gt(),
+            Self::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartPlaylistSort {
+    pub by: SmartPlaylistSortBy,
+
+    #[serde(default)]
+    pub desc: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum SmartPlaylistSortBy {
+    /// Shuffle the tracks again on each evaluation
+    Random,
+    Title,
+    Album,
+    Year,
+    Duration,
+    AddedAt,
+    Rating,
+    PlayCount,
+    LastPlayed,
+}
+
+impl SmartPlaylistSortBy {
+    fn compare(
+        self,
+        a: &Track,
+        b: &Track,
+        index: &IndexCache,
+        context: &EvaluationContext,
+    ) -> Ordering {
+        let EvaluationContext {
+            ratings,
+            play_stats,
+            ..
+        } = context;
+
+        match self {
+            Self::Random => Ordering::Equal,
+
+            Self::Title => a.tags.title.cmp(&b.tags.title),
+
+            Self::Album => {
+                let album_name =
+                    |track: &Track| &index.albums.get(&track.tags.album_id).unwrap().name;
+
+                album_name(a)
+                    .cmp(album_name(b))
+                    .then_with(|| a.tags.album_id.encode().cmp(&b.tags.album_id.encode()))
+                    .then(a.tags.disc_number.cmp(&b.tags.disc_number))
+                    .then(a.tags.track_number.cmp(&b.tags.track_number))
+            }
+
+            Self::Year => a
+                .tags
+                .date
+                .map(|date| date.year)
+                .cmp(&b.tags.date.map(|date| date.year)),
+
+            Self::Duration => a.metadata.duration_s.cmp(&b.metadata.duration_s),
+
+            Self::AddedAt => added_at(a).cmp(&added_at(b)),
+
+            Self::Rating => ratings.tracks.get(&a.id).cmp(&ratings.tracks.get(&b.id)),
+
+            Self::PlayCount => context.play_count(a).cmp(&context.play_count(b)),
+
+            Self::LastPlayed => {
+                let last_played_at = |track: &Track| {
+                    play_stats
+                        .get(&track.id)
+                        .and_then(|stats| stats.last_played_at)
+                };
+
+                last_played_at(a).cmp(&last_played_at(b))
+            }
+        }
+    }
+}
+
+/// Time at which a track was added to the library
+fn added_at(track: &Track) -> SystemTime {
+    track.file_times.ctime.unwrap_or(track.file_times.mtime)
+}
+
+struct EvaluationContext<'a> {
+    ratings: &'a Ratings,
+    play_stats: &'a PlayStats,
+    now: SystemTime,
+}
+
+impl EvaluationContext<'_> {
+    fn play_count(&self, track: &Track) -> u32 {
+        self.play_stats
+            .get(&track.id)
+            .map_or(0, |stats| stats.play_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use indexmap::IndexSet;
+
+    use super::*;
+    use crate::{
+        index::{Album, Artist, FileTimes, Index, TrackDate, TrackMetadata, TrackTags},
+        userdata::RatedItem,
+    };
+
+    fn make_track(
+        album: &Album,
+        title: &str,
+        year: Option<u16>,
+        duration_s: u32,
+        added_days_ago: u64,
+    ) -> Track {
+        let relative_path = PathBuf::from(format!("{}/{title}.mp3", album.name));
+        let added_at = SystemTime::now() - Duration::from_secs(added_days_ago * 86_400);
+
+        Track {
+            id: TrackID::compute(&relative_path),
+            relative_path,
+            file_size_bytes: 0,
+            file_times: FileTimes {
+                ctime: Some(added_at),
+                mtime: added_at,
+            },
+            metadata: TrackMetadata {
+                duration_s,
+                audio_codec: TrackAudioCodec::MP3,
+                embedded_art: None,
+            },
+            tags: TrackTags {
+                title: title.to_owned(),
+                artists_id: album.artists_id.clone(),
+                composers_id: IndexSet::new(),
+                album_id: album.id,
+                disc_number: None,
+                track_number: None,
+                genres_id: IndexSet::new(),
+                date: year.map(|year| TrackDate {
+                    year,
+                    month: None,
+                    day: None,
+                }),
+            },
+        }
+    }
+
+    /// Build an index with a single album made of the following tracks:
+    ///
+    /// | Title   | Year | Duration | Added        |
+    /// | ------- | ---- | -------- | ------------ |
+    /// | Early   | 1985 | 120s     | 100 days ago |
+    /// | Middle  | 1995 | 240s     | 10 days ago  |
+    /// | Late    | 2005 | 360s     | 1 day ago    |
+    /// | Undated |      | 600s     | 400 days ago |
+    fn index() -> IndexCache {
+        let artist = Artist::new("Artist".to_owned());
+        let album = Album::new("Album".to_owned(), IndexSet::from([artist.id]));
+
+        let tracks = vec![
+            make_track(&album, "Early", Some(1985), 120, 100),
+            make_track(&album, "Middle", Some(1995), 240, 10),
+            make_track(&album, "Late", Some(2005), 360, 1),
+            make_track(&album, "Undated", None, 600, 400),
+        ];
+
+        IndexCache::build(&Index {
+            tracks,
+            albums: vec![album],
+            artists: vec![artist],
+            ..Index::default()
+        })
+        .unwrap()
+    }
+
+    fn track_id(index: &IndexCache, title: &str) -> TrackID {
+        index
+            .tracks
+            .values()
+            .find(|track| track.tags.title == title)
+            .unwrap()
+            .id
+    }
+
+    fn titles(index: &IndexCache, tracks: &[TrackID]) -> Vec<String> {
+        tracks
+            .iter()
+            .map(|track_id| index.tracks.get(track_id).unwrap().tags.title.clone())
+            .collect()
+    }
+
+    /// Titles of the tracks matching a rule, in alphabetical order
+    fn matching(index: &IndexCache, ratings: &Ratings, filter: SmartPlaylistRule) -> Vec<String> {
+        let rules = SmartPlaylistRules {
+            filter,
+            sort: None,
+            limit: None,
+        };
+
+        let mut titles = titles(
+            index,
+            &rules.evaluate(index, ratings, &PlayStats::default()),
+        );
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn test_ranges() {
+        use SmartPlaylistRule::*;
+
+        let index = index();
+        let ratings = Ratings::default();
+
+        let cases = [
+            // Years, with inclusive bounds and undated tracks never matching
+            (
+                YearRange {
+                    from: Some(1985),
+                    to: Some(1995),
+                },
+                vec!["Early", "Middle"],
+            ),
+            (
+                YearRange {
+                    from: Some(1996),
+                    to: None,
+                },
+                vec!["Late"],
+            ),
+            (
+                YearRange {
+                    from: None,
+                    to: Some(1985),
+                },
+                vec!["Early"],
+            ),
+            (
+                YearRange {
+                    from: None,
+                    to: None,
+                },
+                vec!["Early", "Late", "Middle"],
+            ),
+            // Durations, with inclusive bounds
+            (
+                DurationRange {
+                    min: Some(240),
+                    max: Some(360),
+                },
+                vec!["Late", "Middle"],
+            ),
+            (
+                DurationRange {
+                    min: None,
+                    max: Some(239),
+                },
+                vec!["Early"],
+            ),
+            (
+                DurationRange {
+                    min: Some(600),
+                    max: None,
+                },
+                vec!["Undated"],
+            ),
+            // Addition dates
+            (AddedWithinDays { days: 30 }, vec!["Late", "Middle"]),
+            (AddedWithinDays { days: 0 }, vec![]),
+        ];
+
+        for (rule, expected) in cases {
+            assert_eq!(
+                matching(&index, &ratings, rule.clone()),
+                expected,
+                "Unexpected tracks for rule {rule:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_composition() {
+        use SmartPlaylistRule::*;
+
+        let index = index();
+        let ratings = Ratings::default();
+
+        let cases = [
+            (
+                All { rules: vec![] },
+                vec!["Early", "Late", "Middle", "Undated"],
+            ),
+            (Any { rules: vec![] }, vec![]),
+            (
+                All {
+                    rules: vec![
+                        YearRange {
+                            from: Some(1985),
+                            to: Some(2005),
+                        },
+                        DurationRange {
+                            min: Some(200),
+                            max: None,
+                        },
+                    ],
+                },
+                vec!["Late", "Middle"],
+            ),
+            (
+                Any {
+                    rules: vec![
+                        YearRange {
+                            from: None,
+                            to: Some(1985),
+                        },
+                        DurationRange {
+                            min: Some(500),
+                            max: None,
+                        },
+                    ],
+                },
+                vec!["Early", "Undated"],
+            ),
+            (
+                All {
+                    rules: vec![
+                        Any {
+                            rules: vec![
+                                YearRange {
+                                    from: Some(2000),
+                                    to: None,
+                                },
+                                DurationRange {
+                                    min: None,
+                                    max: Some(150),
+                                },
+                            ],
+                        },
+                        AddedWithinDays { days: 30 },
+                    ],
+                },
+                vec!["Late"],
+            ),
+        ];
+
+        for (rule, expected) in cases {
+            assert_eq!(
+                matching(&index, &ratings, rule.clone()),
+                expected,
+                "Unexpected tracks for rule {rule:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_not_rated_ignores_inherited_ratings() {
+        let index = index();
+        let album_id = index.albums.keys().next().copied().unwrap();
+
+        let mut ratings = Ratings::default();
+
+        // Tracks of a rated album are not rated themselves
+        ratings.set(RatedItem::Album(album_id), Some(Rating::Two));
+
+        assert_eq!(
+            matching(&index, &ratings, SmartPlaylistRule::NotRated),
+            ["Early", "Late", "Middle", "Undated"]
+        );
+
+        assert_eq!(
+            matching(
+                &index,
+                &ratings,
+                SmartPlaylistRule::Rating {
+                    op: Comparison::Ge,
+                    rating: Rating::Zero
+                }
+            ),
+            Vec::<String>::new()
+        );
+
+        ratings.set(
+            RatedItem::Track(track_id(&index, "Early")),
+            Some(Rating::Four),
+        );
+
+        assert_eq!(
+            matching(&index, &ratings, SmartPlaylistRule::NotRated),
+            ["Late", "Middle", "Undated"]
+        );
+
+        assert_eq!(
+            matching(
+                &index,
+                &ratings,
+                SmartPlaylistRule::Rating {
+                    op: Comparison::Ge,
+                    rating: Rating::Three
+                }
+            ),
+            ["Early"]
+        );
+
+        assert_eq!(
+            matching(
+                &index,
+                &ratings,
+                SmartPlaylistRule::AlbumRating {
+                    op: Comparison::Eq,
+                    rating: Rating::Two
+                }
+            ),
+            ["Early", "Late", "Middle", "Undated"]
+        );
+    }
+
+    #[test]
+    fn test_sort_and_limit() {
+        let index = index();
+        let ratings = Ratings::default();
+        let play_stats = PlayStats::default();
+
+        let cases = [
+            (
+                SmartPlaylistSortBy::Year,
+                true,
+                Some(2),
+                vec!["Late", "Middle"],
+            ),
+            (
+                SmartPlaylistSortBy::Year,
+                false,
+                Some(2),
+                vec!["Undated", "Early"],
+            ),
+            (SmartPlaylistSortBy::Duration, false, Some(1), vec!["Early"]),
+            (
+                SmartPlaylistSortBy::AddedAt,
+                true,
+                Some(10),
+                vec!["Late", "Middle", "Early", "Undated"],
+            ),
+            (
+                SmartPlaylistSortBy::Title,
+                false,
+                None,
+                vec!["Early", "Late", "Middle", "Undated"],
+            ),
+        ];
+
+        for (by, desc, limit, expected) in cases {
+            let rules = SmartPlaylistRules {
+                filter: SmartPlaylistRule::default(),
+                sort: Some(SmartPlaylistSort { by, desc }),
+                limit,
+            };
+
+            assert_eq!(
+                titles(&index, &rules.evaluate(&index, &ratings, &play_stats)),
+                expected,
+                "Unexpected tracks when sorting by {by:?} (desc: {desc}) with limit {limit:?}"
+            );
+        }
+
+        // Shuffled tracks are limited as well
+        let rules = SmartPlaylistRules {
+            filter: SmartPlaylistRule::default(),
+            sort: Some(SmartPlaylistSort {
+                by: SmartPlaylistSortBy::Random,
+                desc: false,
+            }),
+            limit: Some(3),
+        };
+
+        assert_eq!(rules.evaluate(&index, &ratings, &play_stats).len(), 3);
+
+        let zero_limit = SmartPlaylistRules {
+            limit: Some(0),
+            ..SmartPlaylistRules::default()
+        };
+
+        assert!(zero_limit.validate().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::{collections::HashMap, hash::Hash, time::SystemTime};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::index::{AlbumID, ArtistID, TrackID};
+
+/// Starred (favourite) items, with the time at which they were starred
+///
+/// Stars are independent from ratings: a track may be starred without being rated,
+/// and the other way around.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Stars {
+    #[serde(default)]
+    pub tracks: HashMap<TrackID, SystemTime>,
+    #[serde(default)]
+    pub albums: HashMap<AlbumID, SystemTime>,
+    #[serde(default)]
+    pub artists: HashMap<ArtistID, SystemTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum StarredItem {
+    Track(TrackID),
+    Album(AlbumID),
+    Artist(ArtistID),
+}
+
+impl Stars {
+    pub fn starred_at(&self, item: StarredItem) -> Option<SystemTime> {
+        match item {
+            StarredItem::Track(track_id) => self.tracks.get(&track_id),
+            StarredItem::Album(album_id) => self.albums.get(&album_id),
+            StarredItem::Artist(artist_id) => self.artists.get(&artist_id),
+        }
+        .copied()
+    }
+
+    /// Star an item, keeping the original time if it was already starred
+    pub fn star(&mut self, item: StarredItem, at: SystemTime) {
+        match item {
+            StarredItem::Track(track_id) => {
+                self.tracks.entry(track_id).or_insert(at);
+            }
+
+            StarredItem::Album(album_id) => {
+                self.albums.entry(album_id).or_insert(at);
+            }
+
+            StarredItem::Artist(artist_id) => {
+                self.artists.entry(artist_id).or_insert(at);
+            }
+        }
+    }
+
+    pub fn unstar(&mut self, item: StarredItem) {
+        match item {
+            StarredItem::Track(track_id) => {
+                self.tracks.remove(&track_id);
+            }
+
+            StarredItem::Album(album_id) => {
+                self.albums.remove(&album_id);
+            }
+
+            StarredItem::Artist(artist_id) => {
+                self.artists.remove(&artist_id);
+            }
+        }
+    }
+}
+
+/// Get the starred items that are still in the index, most recently starred first
+///
+/// Starred items may have been deleted from the index, leaving dangling stars.
+/// They are kept so they are restored if the items are added back later.
+pub fn most_recent_first<'a, K: Eq + Hash, V>(
+    starred: &HashMap<K, SystemTime>,
+    items: &'a IndexMap<K, V>,
+) -> Vec<&'a V> {
+    let mut starred = starred
+        .iter()
+        .filter_map(|(id, starred_at)| Some((items.get(id)?, starred_at)))
+        .collect::<Vec<_>>();
+
+    starred.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    starred.into_iter().map(|(item, _)| item).collect()
+}
@@ -0,0 +1,171 @@
+use std::{fmt, time::SystemTime};
+
+use anyhow::{Result, ensure};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::{IdType, impl_id_type},
+    utils::{Rng, random_secret, u64_base62_serialization},
+};
+
+/// User accounts, the first one being the server's owner
+pub type Users = IndexMap<UserID, User>;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserID(#[serde(with = "u64_base62_serialization")] u64);
+
+impl UserID {
+    pub fn generate() -> Self {
+        Self(Rng::new().next_u64())
+    }
+}
+
+impl_id_type!(UserID);
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub id: UserID,
+    pub name: String,
+
+    /// Password of the user, stored as-is
+    ///
+    /// `OpenSubsonic`'s token authentication hashes the password with a random salt on the
+    /// client's side, so the server needs to know it to check the token.
+    pub password: String,
+
+    /// Missing for accounts created before roles existed, see [`crate::manager::load_users`]
+    #[serde(default)]
+    pub role: UserRole,
+
+    /// Key used to authenticate `OpenSubsonic` clients supporting the API key extension
+    ///
+    /// Generated when loading users created before API keys were supported.
+    #[serde(default = "generate_api_key")]
+    pub api_key: String,
+
+    pub created_at: SystemTime,
+}
+
+impl User {
+    /// Create a user with a randomly generated password and API key
+    pub fn new(name: &str, role: UserRole) -> Result<Self> {
+        let name = name.trim();
+
+        ensure!(!name.is_empty(), "Username cannot be empty");
+        ensure!(
+            !name.chars().any(char::is_whitespace),
+            "Username cannot contain whitespace"
+        );
+
+        Ok(Self {
+            id: UserID::generate(),
+            name: name.to_owned(),
+            password: generate_password(),
+            role,
+            api_key: generate_api_key(),
+            created_at: SystemTime::now(),
+        })
+    }
+
+    /// Replace the password with a new randomly generated one
+    pub fn reset_password(&mut self) {
+        self.password = generate_password();
+    }
+
+    /// Replace the API key with a new randomly generated one
+    pub fn reset_api_key(&mut self) {
+        self.api_key = generate_api_key();
+    }
+}
+
+// Secrets are kept out of the logs
+impl fmt::Debug for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("User")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("password", &"<redacted>")
+            .field("role", &self.role)
+            .field("api_key", &"<redacted>")
+            .field("created_at", &self.created_at)
+            .finish()
+    }
+}
+
+/// Role of a user, which determines what it is allowed to do
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum UserRole {
+    /// Can manage the library (index updates, arts overrides, ...)
+    Admin,
+
+    /// Can only browse and listen to the library, with its own data (ratings, playlists, ...)
+    #[default]
+    Listener,
+}
+
+/// Long-lived tokens authenticating devices on the native API, indexed by their value
+pub type DeviceTokens = IndexMap<String, DeviceToken>;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceToken {
+    pub user_id: UserID,
+    pub device_name: Option<String>,
+    pub created_at: SystemTime,
+}
+
+impl DeviceToken {
+    /// Generate a new token for a user's device, returned alongside its value
+    pub fn generate(user_id: UserID, device_name: Option<String>) -> (String, Self) {
+        (
+            random_secret(32),
+            Self {
+                user_id,
+                device_name,
+                created_at: SystemTime::now(),
+            },
+        )
+    }
+}
+
+fn generate_password() -> String {
+    random_secret(16)
+}
+
+fn generate_api_key() -> String {
+    random_secret(32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let user = User::new("alice", UserRole::Admin).unwrap();
+        let debug = format!("{user:?}");
+
+        assert!(debug.contains("alice"));
+        assert!(!debug.contains(&user.password));
+        assert!(!debug.contains(&user.api_key));
+    }
+
+    #[test]
+    fn test_missing_api_key_is_generated() {
+        let user = User::new("alice", UserRole::Admin).unwrap();
+
+        let mut json = serde_json::to_value(&user).unwrap();
+        json.as_object_mut().unwrap().remove("apiKey");
+
+        let json = json.to_string();
+
+        let first = serde_json::from_str::<User>(&json).unwrap();
+        let second = serde_json::from_str::<User>(&json).unwrap();
+
+        assert_eq!(first.api_key.len(), user.api_key.len());
+        assert_ne!(first.api_key, second.api_key);
+    }
+}
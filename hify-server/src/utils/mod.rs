@@ -3,5 +3,6 @@ mod hash;
 mod paths;
 mod rand;
 mod runner;
+mod secrets;
 
-pub use self::{base62::*, hash::*, paths::*, rand::*, runner::*};
+pub use self::{base62::*, hash::*, paths::*, rand::*, runner::*, secrets::*};
@@ -1,5 +1,7 @@
 use std::hash::{BuildHasher, Hasher, RandomState};
 
+use super::encode_base62;
+
 /// PCG-XSH-RR (PCG32) Random Number Generator
 /// A fast, statistically excellent random number generator
 ///
@@ -54,11 +56,12 @@ impl Rng {
         xorshifted.rotate_right(rot)
     }
 
-    // pub fn next_u64(&mut self) -> u64 {
-    //     let high = u64::from(self.next_u32());
-    //     let low = u64::from(self.next_u32());
-    //     (high << 32) | low
-    // }
+    /// Generates the next random u64 value
+    pub fn next_u64(&mut self) -> u64 {
+        let high = u64::from(self.next_u32());
+        let low = u64::from(self.next_u32());
+        (high << 32) | low
+    }
 }
 
 /// Shuffles a vector in place using the Fisher-Yates algorithm with a PCG32 generator.
@@ -89,3 +92,12 @@ pub fn deterministic_shuffle<T>(vec: &mut [T], rng: &mut Rng) {
         vec.swap(i, j);
     }
 }
+
+/// Generate a secret (e.g. a password or a token) from the operating system's secure randomness
+///
+/// The provided number of random bytes is encoded in base62.
+pub fn random_secret(bytes: usize) -> String {
+    let mut data = vec![0; bytes];
+    getrandom::fill(&mut data).expect("Failed to get random bytes from the operating system");
+    encode_base62(&data)
+}
@@ -3,11 +3,26 @@
 //! Provides `TaskRunner` for running multiple blocking tasks concurrently
 //! with bounded parallelism. When any task fails, no new tasks will be started.
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread::{self, JoinHandle};
 
 use anyhow::{Result, anyhow};
 
+/// Maximum concurrency of task runners created with [`TaskRunner::new`]
+static DEFAULT_MAX_CONCURRENT: AtomicUsize = AtomicUsize::new(32);
+
+/// Sets the maximum concurrency of task runners created with [`TaskRunner::new`].
+///
+/// # Panics
+///
+/// Panics if `max_concurrent` is 0.
+pub fn set_default_max_concurrent(max_concurrent: usize) {
+    assert!(max_concurrent > 0, "max_concurrent must be at least 1");
+
+    DEFAULT_MAX_CONCURRENT.store(max_concurrent, Ordering::Relaxed);
+}
+
 // ============================================================================
 // Internal Semaphore Implementation
 // ============================================================================
@@ -108,7 +123,7 @@ pub struct TaskRunner<T: Send + 'static> {
 }
 
 impl<T: Send + 'static> TaskRunner<T> {
-    /// Creates a new task runner with the default maximum concurrency.
+    /// Creates a new task runner with the default maximum concurrency (32 unless changed).
     ///
     /// # Example
     ///
@@ -116,10 +131,7 @@ impl<T: Send + 'static> TaskRunner<T> {
     /// let runner: TaskRunner<()> = TaskRunner::new();
     /// ```
     pub fn new() -> Self {
-        Self::new_custom(
-            // TODO: put into constant
-            32,
-        )
+        Self::new_custom(DEFAULT_MAX_CONCURRENT.load(Ordering::Relaxed))
     }
 
     /// Creates a new task runner with the specified maximum concurrency.
@@ -0,0 +1,8 @@
+use subtle::ConstantTimeEq;
+
+/// Compare two secrets (e.g. passwords or API keys) in constant time
+///
+/// Only the secrets' lengths may be leaked through timing.
+pub fn secrets_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}